@@ -1,32 +1,338 @@
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashSet;
+use std::sync::OnceLock;
 use std::time::Duration;
 
-#[derive(Debug, Clone)]
+static CONFIG: OnceLock<ServerConfig> = OnceLock::new();
+
+// 目标文件已存在时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    // 自动重命名为 name_1.ext、name_2.ext ...（默认，参数也可写作 suffix）
+    Rename,
+    // 覆盖已有文件
+    Overwrite,
+    // 拒绝上传（参数也可写作 reject）
+    Fail,
+}
+
+impl ConflictPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "rename" | "suffix" => Some(Self::Rename),
+            "overwrite" => Some(Self::Overwrite),
+            "fail" | "reject" => Some(Self::Fail),
+            _ => None,
+        }
+    }
+}
+
+// fsync 策略：在写入吞吐与断电后的数据持久性之间取舍
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsyncPolicy {
+    // 每次写入（包括分块上传的临时分片）完成后都 fsync，断电不丢已确认的分片，吞吐最低
+    Always,
+    // 仅在文件落到最终位置时（整文件上传完成、分块合并）fsync，分片丢失时客户端可重传（默认）
+    OnMerge,
+    // 从不 fsync，依赖操作系统回写；适合带电池保护缓存的存储或可重建的数据
+    Never,
+}
+
+impl FsyncPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace('-', "_").as_str() {
+            "always" => Some(Self::Always),
+            "on_merge" => Some(Self::OnMerge),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+
+    // 最终文件（上传完成、合并结果、改写后的文件）是否需要 fsync
+    pub fn sync_files(self) -> bool {
+        self != Self::Never
+    }
+
+    // 分块上传的临时分片是否需要 fsync
+    pub fn sync_chunks(self) -> bool {
+        self == Self::Always
+    }
+}
+
+// 病毒扫描模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanMode {
+    // 扫描完成且未检出病毒后才返回上传成功（默认）
+    Blocking,
+    // 先返回上传成功，在后台扫描，检出病毒时再隔离
+    Async,
+}
+
+impl ScanMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "blocking" => Some(Self::Blocking),
+            "async" => Some(Self::Async),
+            _ => None,
+        }
+    }
+}
+
+// 合并后校验 file_hash 使用的摘要算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha256,
+    Md5,
+}
+
+impl HashAlgorithm {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace('-', "").as_str() {
+            "sha256" => Some(Self::Sha256),
+            "md5" => Some(Self::Md5),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ServerConfig {
     pub chunk_size: usize,
+    // 写入上传文件/分片时的缓冲区大小（UPLOAD_WRITE_BUFFER_SIZE，如 "1MB"），
+    // 将请求体中零散的小数据块合并为较大的写入，减少系统调用次数
+    pub write_buffer_size: usize,
+    // JSON 请求体上限；批量接口（打包下载、批量移动/删除）携带文件列表，单独使用较大的上限
+    pub max_json_size: usize,
+    pub max_batch_json_size: usize,
     pub max_concurrent_chunks: usize,
     pub max_file_size: u64,
     pub temp_file_cleanup_interval: Duration,
+    // 临时目录中的分片和临时文件超过该时长未修改即被清理
+    pub temp_file_max_age: Duration,
     pub global_max_concurrent: usize,
     pub max_memory_locks: usize,
     pub lock_cleanup_interval: Duration,
     pub merge_max_concurrent: usize,
+    // 上传或合并的目标文件已存在时的默认处理策略（UPLOAD_ON_CONFLICT），可被请求参数 on_conflict 覆盖；
+    // 默认 rename，需要合并时覆盖同名文件的旧行为可设为 overwrite
+    pub on_conflict: ConflictPolicy,
+    // 请求未指定模块时上传到的模块
+    pub default_module: String,
+    // 为 true 时拒绝上传到不存在的模块（返回 404），否则自动创建模块目录
+    pub require_existing_module: bool,
+    // 单次上传/合并请求的最长处理时间，None 表示不限制
+    pub max_upload_duration: Option<Duration>,
+    // 单个列表请求内并发读取目录的上限
+    pub max_scan_concurrency: usize,
+    // 统计目录时允许的最大嵌套深度，超过时停止遍历并报告
+    pub max_walk_depth: usize,
+    // 模块树单次返回的最大子目录节点数，超过时不再展开
+    pub max_tree_nodes: usize,
+    // 存储文件完整路径（绝对路径）的最大字节数
+    pub max_path_bytes: usize,
+    // 全局文件总数上限，None 表示不限制
+    pub max_total_files: Option<u64>,
+    // 压缩存储: 超过阈值的文档类上传在写入时以 zstd 压缩保存为 {filename}.zst，下载时透明解压
+    pub compress_at_rest: bool,
+    pub compress_min_size: u64,
+    // 去重模式: 相同内容的文件通过硬链接共享 ./uploads/.cas/{sha256} 中的同一份数据
+    pub dedup_enabled: bool,
+    // 软删除: 删除的文件/文件夹移入 ./uploads/.trash/{module}，超过保留期后由后台清理
+    pub soft_delete: bool,
+    pub trash_retention: Duration,
+    // 优雅关闭时等待进行中的上传完成的最长时间
+    pub shutdown_timeout: Duration,
+    // 上传文件与临时分片的存储根目录（可指向挂载卷）
+    pub uploads_root: String,
+    pub temp_root: String,
+    // 合并时用于校验客户端 file_hash 的摘要算法
+    pub merge_hash_algorithm: HashAlgorithm,
+    // 是否拒绝同一分块上传会话中 total_chunks 不一致的分块
+    pub enforce_total_chunks: bool,
+    // 单个分块上传会话允许的最大分块数，防止客户端声明超大的 total_chunks 使合并循环失控；
+    // 默认 200000（5MB 分片时约 1TB，远大于 max_file_size）
+    pub max_total_chunks: usize,
+    // 是否在文件元数据中记录上传来源（上传者、来源 IP、User-Agent、请求 ID）
+    pub store_upload_provenance: bool,
+    // 单次搜索返回的最大文件数
+    pub max_search_results: usize,
+    // 单次 ZIP 打包下载允许的最大文件数
+    pub max_zip_files: usize,
+    // 允许上传的文件扩展名（小写，不含点）
+    pub allowed_extensions: HashSet<String>,
+    // 始终拒绝的扩展名，优先于 allowed_extensions
+    pub denied_extensions: HashSet<String>,
+    // 单个客户端 IP 同时进行的上传数上限，None 表示不限制
+    pub max_concurrent_uploads_per_ip: Option<usize>,
+    // 上传/合并完成后是否 fsync 文件及其所在目录（见 FsyncPolicy）；fsync 保证返回成功后断电不丢数据，
+    // 但会降低吞吐，临时性数据或有掉电保护的存储可设为 never
+    pub fsync_policy: FsyncPolicy,
+    // 上传时边写入边计算 SHA-256（整文件上传写入 file_hash，分块上传记录每个分片的摘要供合并时校验），
+    // 不需要再读一遍文件，但会增加 CPU 开销
+    pub hash_on_upload: bool,
+    // 单个客户端 IP 每分钟允许的上传/合并请求数（令牌桶），None 表示不限制
+    pub rate_limit_per_minute: Option<u32>,
+    // 单次上传的写入速率上限（字节/秒），None 表示不限制；请求可用 ?max_bps= 指定更低的值
+    pub max_upload_bps: Option<u64>,
+    // 上传前检查存储目录的可用 inode（仅 unix 文件系统有效）
+    pub check_free_inodes: bool,
+    pub min_free_inodes: u64,
+    // 存储目录所在文件系统的剩余空间低于该值时，健康检查返回 503（degraded）
+    pub min_free_bytes: u64,
+    // 吞吐历史的时间分辨率与保留的槽数（默认 60 x 1 分钟）
+    pub stats_history_resolution: Duration,
+    pub stats_history_window: usize,
+    // 吞吐历史持久化文件，None 表示仅保存在内存中
+    pub stats_history_path: Option<String>,
     pub address: String,
     pub port: String,
+    // 访问日志格式（actix Logger 格式串），None 时使用默认格式
+    pub access_log_format: Option<String>,
+    // 慢请求阈值（毫秒），None 表示不记录慢请求
+    pub slow_request_threshold_ms: Option<u64>,
+    // 图片自动转换的目标格式（webp / avif），None 表示不转换。
+    // webp 在进程内用 image crate 无损编码；avif 调用外部工具 avifenc（libavif），须另行安装并位于 PATH 中
+    pub image_convert_to: Option<String>,
+    // 有损编码质量（0-100），仅用于 avif
+    pub image_convert_quality: u8,
+    // 上传图片（jpg/jpeg/png/webp）时移除 EXIF、XMP 等元数据段
+    pub strip_exif: bool,
+    // 管理接口令牌，None 表示禁用管理接口
+    #[serde(serialize_with = "redact_secret")]
+    pub admin_token: Option<String>,
+    // 机器客户端使用的 API Key（通过 X-API-Key 头传递）
+    #[serde(serialize_with = "redact_secret_list")]
+    pub api_keys: Vec<String>,
+    // JWT (HS256) 签名密钥，配置后写操作需要携带 Authorization: Bearer <jwt>
+    #[serde(serialize_with = "redact_secret")]
+    pub jwt_secret: Option<String>,
+    // 读操作（GET）是否同样需要认证
+    pub require_auth_for_reads: bool,
+    // 是否在服务端校验写操作的 Origin/Referer
+    pub enforce_origin: bool,
+    pub allowed_origins: Vec<String>,
+    // 允许跨域访问的来源（"*" 表示任意来源，"http://localhost:*" 表示该主机的任意端口）
+    pub cors_allowed_origins: Vec<String>,
+    // 跨域请求允许的方法与请求头（请求头列表中的 "*" 表示任意请求头）
+    pub cors_allowed_methods: Vec<String>,
+    pub cors_allowed_headers: Vec<String>,
+    // 上传成功后通知的 webhook 地址（http:// 或 https://，启动时校验），None 表示不通知
+    pub webhook_url: Option<String>,
+    // webhook 请求体的 HMAC-SHA256 签名密钥（签名放在 X-Upload-Signature 头）
+    #[serde(serialize_with = "redact_secret")]
+    pub webhook_secret: Option<String>,
+    // clamd 地址（host:port 或 unix:/path/to/clamd.sock），配置后上传完成的文件先经 ClamAV 扫描，None 表示不扫描
+    pub clamav_address: Option<String>,
+    pub scan_mode: ScanMode,
+    // 审计日志（NDJSON）路径，None 表示不记录；超过 audit_log_max_size 后轮转为 <path>.1
+    pub audit_log_path: Option<String>,
+    pub audit_log_max_size: u64,
+    // 未部署前端时根路径的跳转地址，None 时返回服务描述 JSON
+    pub root_redirect: Option<String>,
 }
 
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             chunk_size: 5 * 1024 * 1024, // 5MB
+            write_buffer_size: 1024 * 1024, // 1MB
+            max_json_size: 64 * 1024, // 64KB
+            max_batch_json_size: 4 * 1024 * 1024, // 4MB
             max_concurrent_chunks: 3,
             max_file_size: 10 * 1024 * 1024 * 1024, // 10GB
             temp_file_cleanup_interval: Duration::from_secs(3600),
+            temp_file_max_age: Duration::from_secs(24 * 3600),
             global_max_concurrent: 64,
             merge_max_concurrent: 4,
+            on_conflict: ConflictPolicy::Rename,
+            default_module: "default".to_string(),
+            require_existing_module: false,
+            max_upload_duration: None,
             max_memory_locks: 10000,
+            max_scan_concurrency: 8,
+            max_walk_depth: 32,
+            max_tree_nodes: 10000,
+            max_path_bytes: 4096,
+            max_total_files: None,
             lock_cleanup_interval: Duration::from_secs(1800),
+            enforce_total_chunks: true,
+            max_total_chunks: 200_000,
+            merge_hash_algorithm: HashAlgorithm::Sha256,
+            dedup_enabled: false,
+            compress_at_rest: false,
+            compress_min_size: 64 * 1024,
+            soft_delete: false,
+            trash_retention: Duration::from_secs(7 * 24 * 3600),
+            shutdown_timeout: Duration::from_secs(30),
+            uploads_root: "./uploads".to_string(),
+            temp_root: "./temp".to_string(),
+            store_upload_provenance: true,
+            max_zip_files: 1000,
+            max_search_results: 500,
+            allowed_extensions: [
+                "jpg", "jpeg", "png", "gif", "webp", "bmp", "svg", "ico",
+                "zip", "rar", "7z", "tar", "gz",
+                "pdf", "doc", "docx", "txt", "md", "json", "xml", "csv", "xls", "xlsx", "ppt", "pptx",
+                "mp4", "avi", "mov", "wmv", "flv", "mkv",
+                "mp3", "wav", "ogg", "flac",
+            ].iter().map(|ext| ext.to_string()).collect(),
+            denied_extensions: HashSet::new(),
+            max_concurrent_uploads_per_ip: None,
+            rate_limit_per_minute: None,
+            max_upload_bps: None,
+            fsync_policy: FsyncPolicy::OnMerge,
+            hash_on_upload: false,
+            check_free_inodes: false,
+            min_free_inodes: 1000,
+            min_free_bytes: 100 * 1024 * 1024,
+            stats_history_resolution: Duration::from_secs(60),
+            stats_history_window: 60,
+            stats_history_path: None,
             address: "127.0.0.1".to_string(),
             port: "2233".to_string(),
+            access_log_format: None,
+            slow_request_threshold_ms: None,
+            image_convert_to: None,
+            image_convert_quality: 80,
+            strip_exif: false,
+            admin_token: None,
+            api_keys: Vec::new(),
+            jwt_secret: None,
+            require_auth_for_reads: false,
+            enforce_origin: false,
+            allowed_origins: Vec::new(),
+            cors_allowed_origins: vec!["http://localhost:*".to_string(), "http://127.0.0.1:*".to_string()],
+            cors_allowed_methods: ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"]
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+            cors_allowed_headers: [
+                "Content-Type",
+                "Authorization",
+                "X-API-Key",
+                "X-Admin-Token",
+                "X-Request-Id",
+                "Range",
+                "If-None-Match",
+                "Tus-Resumable",
+                "Upload-Length",
+                "Upload-Offset",
+                "Upload-Metadata",
+            ]
+            .iter()
+            .map(|h| h.to_string())
+            .collect(),
+            root_redirect: None,
+            webhook_url: None,
+            webhook_secret: None,
+            clamav_address: None,
+            scan_mode: ScanMode::Blocking,
+            audit_log_path: Some("./audit.log".to_string()),
+            audit_log_max_size: 100 * 1024 * 1024,
         }
     }
 }
@@ -38,26 +344,488 @@ impl ServerConfig {
         let address = args.get(1).cloned().unwrap_or_else(|| "127.0.0.1".to_string());
         let port = args.get(2).cloned().unwrap_or_else(|| "2233".to_string());
 
-        Self { address, port, ..Default::default() }
+        // 管理令牌属于密钥，只从环境变量读取
+        let admin_token = std::env::var("UPLOAD_ADMIN_TOKEN").ok().filter(|t| !t.is_empty());
+
+        let jwt_secret = std::env::var("UPLOAD_JWT_SECRET").ok().filter(|s| !s.is_empty());
+
+        let webhook_url = std::env::var("UPLOAD_WEBHOOK_URL").ok().filter(|u| !u.trim().is_empty());
+        let webhook_secret = std::env::var("UPLOAD_WEBHOOK_SECRET").ok().filter(|s| !s.is_empty());
+
+        let api_keys = std::env::var("UPLOAD_API_KEYS")
+            .map(|v| v.split(',').map(|k| k.trim().to_string()).filter(|k| !k.is_empty()).collect())
+            .unwrap_or_default();
+
+        // 其余参数可通过环境变量覆盖（便于容器部署），无效值记录警告并使用默认值
+        let defaults = Self::default();
+        let chunk_size = env_parse("UPLOAD_CHUNK_SIZE", defaults.chunk_size as u64, parse_size) as usize;
+        let max_json_size = env_parse("UPLOAD_MAX_JSON_SIZE", defaults.max_json_size as u64, parse_size) as usize;
+        let max_batch_json_size = env_parse("UPLOAD_MAX_BATCH_JSON_SIZE", defaults.max_batch_json_size as u64, parse_size) as usize;
+        let write_buffer_size = env_parse("UPLOAD_WRITE_BUFFER_SIZE", defaults.write_buffer_size as u64, parse_size) as usize;
+        let max_file_size = env_parse("UPLOAD_MAX_FILE_SIZE", defaults.max_file_size, parse_size);
+        let global_max_concurrent = env_parse("UPLOAD_GLOBAL_CONCURRENCY", defaults.global_max_concurrent, parse_count);
+        let merge_max_concurrent = env_parse("UPLOAD_MERGE_CONCURRENCY", defaults.merge_max_concurrent, parse_count);
+        let max_memory_locks = env_parse("UPLOAD_MAX_MEMORY_LOCKS", defaults.max_memory_locks, parse_count);
+        let hash_on_upload = env_parse("UPLOAD_HASH_ON_UPLOAD", defaults.hash_on_upload, parse_bool);
+        let fsync_policy = env_parse("UPLOAD_FSYNC_POLICY", defaults.fsync_policy, FsyncPolicy::parse);
+        let on_conflict = env_parse("UPLOAD_ON_CONFLICT", defaults.on_conflict, ConflictPolicy::parse);
+        let default_module = env_parse("UPLOAD_DEFAULT_MODULE", defaults.default_module.clone(), parse_module_name);
+        let require_existing_module = env_parse("UPLOAD_REQUIRE_EXISTING_MODULE", defaults.require_existing_module, parse_bool);
+        let cors_allowed_origins = env_parse("UPLOAD_CORS_ORIGINS", defaults.cors_allowed_origins.clone(), parse_list);
+        let cors_allowed_methods = env_parse("UPLOAD_CORS_METHODS", defaults.cors_allowed_methods.clone(), parse_method_list);
+        let cors_allowed_headers = env_parse("UPLOAD_CORS_HEADERS", defaults.cors_allowed_headers.clone(), parse_list);
+        let allowed_extensions = env_parse("UPLOAD_ALLOWED_EXTENSIONS", defaults.allowed_extensions.clone(), parse_extension_list);
+        let denied_extensions = env_parse("UPLOAD_DENIED_EXTENSIONS", defaults.denied_extensions.clone(), parse_extension_list);
+        let rate_limit_per_minute = env_parse("UPLOAD_RATE_LIMIT_PER_MINUTE", defaults.rate_limit_per_minute, |v| {
+            parse_count(v).and_then(|n| u32::try_from(n).ok()).map(Some)
+        });
+        let max_upload_bps = env_parse("UPLOAD_MAX_UPLOAD_BPS", defaults.max_upload_bps, |v| parse_size(v).map(Some));
+        let max_concurrent_uploads_per_ip = env_parse("UPLOAD_MAX_CONCURRENT_UPLOADS_PER_IP", defaults.max_concurrent_uploads_per_ip, |v| {
+            parse_count(v).map(Some)
+        });
+        let max_total_chunks = env_parse("UPLOAD_MAX_TOTAL_CHUNKS", defaults.max_total_chunks, parse_count);
+        let merge_hash_algorithm = env_parse("UPLOAD_MERGE_HASH_ALGORITHM", defaults.merge_hash_algorithm, HashAlgorithm::parse);
+        let enforce_total_chunks = env_parse("UPLOAD_ENFORCE_TOTAL_CHUNKS", defaults.enforce_total_chunks, parse_bool);
+        let store_upload_provenance = env_parse("UPLOAD_STORE_PROVENANCE", defaults.store_upload_provenance, parse_bool);
+        let max_search_results = env_parse("UPLOAD_MAX_SEARCH_RESULTS", defaults.max_search_results, parse_count);
+        let require_auth_for_reads = env_parse("UPLOAD_REQUIRE_AUTH_FOR_READS", defaults.require_auth_for_reads, parse_bool);
+        let enforce_origin = env_parse("UPLOAD_ENFORCE_ORIGIN", defaults.enforce_origin, parse_bool);
+        let allowed_origins = env_parse("UPLOAD_ALLOWED_ORIGINS", defaults.allowed_origins.clone(), parse_list);
+        let compress_at_rest = env_parse("UPLOAD_COMPRESS_AT_REST", defaults.compress_at_rest, parse_bool);
+        let compress_min_size = env_parse("UPLOAD_COMPRESS_MIN_SIZE", defaults.compress_min_size, parse_size);
+        let dedup_enabled = env_parse("UPLOAD_DEDUP_ENABLED", defaults.dedup_enabled, parse_bool);
+        let soft_delete = env_parse("UPLOAD_SOFT_DELETE", defaults.soft_delete, parse_bool);
+        let trash_retention = env_parse("UPLOAD_TRASH_RETENTION", defaults.trash_retention, parse_duration);
+        let temp_file_max_age = env_parse("UPLOAD_TEMP_FILE_MAX_AGE", defaults.temp_file_max_age, parse_duration);
+        let shutdown_timeout = env_parse("UPLOAD_SHUTDOWN_TIMEOUT", defaults.shutdown_timeout, parse_duration);
+        let max_upload_duration = env_parse("UPLOAD_MAX_UPLOAD_DURATION", defaults.max_upload_duration, |v| {
+            parse_duration(v).map(Some)
+        });
+        let uploads_root = env_parse("UPLOAD_UPLOADS_ROOT", defaults.uploads_root.clone(), parse_root_dir);
+        let temp_root = env_parse("UPLOAD_TEMP_ROOT", defaults.temp_root.clone(), parse_root_dir);
+        let strip_exif = env_parse("UPLOAD_STRIP_EXIF", defaults.strip_exif, parse_bool);
+        let image_convert_to = env_parse("UPLOAD_CONVERT_IMAGES_TO", defaults.image_convert_to.clone(), parse_image_format);
+        let image_convert_quality = env_parse("UPLOAD_IMAGE_CONVERT_QUALITY", defaults.image_convert_quality, |v| {
+            v.trim().parse::<u8>().ok().filter(|q| (1..=100).contains(q))
+        });
+        let clamav_address = std::env::var("UPLOAD_CLAMAV_ADDRESS")
+            .ok()
+            .map(|a| a.trim().to_string())
+            .filter(|a| !a.is_empty());
+        let scan_mode = env_parse("UPLOAD_SCAN_MODE", defaults.scan_mode, ScanMode::parse);
+        let audit_log_path = match std::env::var("UPLOAD_AUDIT_LOG") {
+            Ok(path) if path.trim().is_empty() || path.trim().eq_ignore_ascii_case("none") => None,
+            Ok(path) => Some(path.trim().to_string()),
+            Err(_) => defaults.audit_log_path.clone(),
+        };
+        let audit_log_max_size = env_parse("UPLOAD_AUDIT_LOG_MAX_SIZE", defaults.audit_log_max_size, parse_size);
+        let root_redirect = std::env::var("UPLOAD_ROOT_REDIRECT").ok().map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+        let access_log_format = std::env::var("UPLOAD_ACCESS_LOG_FORMAT").ok().filter(|f| !f.trim().is_empty());
+        let slow_request_threshold_ms = env_parse("UPLOAD_SLOW_REQUEST_THRESHOLD_MS", defaults.slow_request_threshold_ms, |v| {
+            v.trim().parse::<u64>().ok().filter(|ms| *ms > 0).map(Some)
+        });
+        let min_free_bytes = env_parse("UPLOAD_MIN_FREE_SPACE", defaults.min_free_bytes, parse_size);
+        let stats_history_resolution = env_parse("UPLOAD_STATS_HISTORY_RESOLUTION", defaults.stats_history_resolution, parse_duration);
+        let stats_history_window = env_parse("UPLOAD_STATS_HISTORY_WINDOW", defaults.stats_history_window, parse_count);
+        let stats_history_path = std::env::var("UPLOAD_STATS_HISTORY_PATH").ok().map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+        let check_free_inodes = env_parse("UPLOAD_CHECK_FREE_INODES", defaults.check_free_inodes, parse_bool);
+        let min_free_inodes = env_parse("UPLOAD_MIN_FREE_INODES", defaults.min_free_inodes, |v| {
+            v.trim().parse::<u64>().ok()
+        });
+        let max_scan_concurrency = env_parse("UPLOAD_MAX_SCAN_CONCURRENCY", defaults.max_scan_concurrency, parse_count);
+        let max_walk_depth = env_parse("UPLOAD_MAX_WALK_DEPTH", defaults.max_walk_depth, parse_count);
+        let max_path_bytes = env_parse("UPLOAD_MAX_PATH_BYTES", defaults.max_path_bytes, parse_count);
+        let max_tree_nodes = env_parse("UPLOAD_MAX_TREE_NODES", defaults.max_tree_nodes, parse_count);
+        let max_total_files = env_parse("UPLOAD_MAX_TOTAL_FILES", defaults.max_total_files, |v| {
+            parse_count(v).map(|n| Some(n as u64))
+        });
+
+        Self {
+            address,
+            port,
+            admin_token,
+            api_keys,
+            webhook_url,
+            webhook_secret,
+            audit_log_path,
+            audit_log_max_size,
+            clamav_address,
+            scan_mode,
+            jwt_secret,
+            require_auth_for_reads,
+            enforce_origin,
+            allowed_origins,
+            chunk_size,
+            write_buffer_size,
+            max_json_size,
+            max_batch_json_size,
+            max_file_size,
+            global_max_concurrent,
+            merge_max_concurrent,
+            max_memory_locks,
+            allowed_extensions,
+            denied_extensions,
+            max_search_results,
+            rate_limit_per_minute,
+            max_upload_bps,
+            max_concurrent_uploads_per_ip,
+            max_total_chunks,
+            merge_hash_algorithm,
+            enforce_total_chunks,
+            store_upload_provenance,
+            compress_at_rest,
+            compress_min_size,
+            dedup_enabled,
+            soft_delete,
+            trash_retention,
+            temp_file_max_age,
+            shutdown_timeout,
+            max_upload_duration,
+            uploads_root,
+            temp_root,
+            strip_exif,
+            image_convert_to,
+            image_convert_quality,
+            max_scan_concurrency,
+            max_walk_depth,
+            max_tree_nodes,
+            max_path_bytes,
+            max_total_files,
+            root_redirect,
+            access_log_format,
+            slow_request_threshold_ms,
+            min_free_bytes,
+            check_free_inodes,
+            min_free_inodes,
+            stats_history_resolution,
+            stats_history_window,
+            stats_history_path,
+            hash_on_upload,
+            fsync_policy,
+            on_conflict,
+            default_module,
+            require_existing_module,
+            cors_allowed_origins,
+            cors_allowed_methods,
+            cors_allowed_headers,
+            ..defaults
+        }
+    }
+
+    // 启动前校验无法回退到默认值的配置项（例如 webhook 地址），出错时拒绝启动
+    pub fn validate(&self) -> std::io::Result<()> {
+        if let Some(url) = &self.webhook_url {
+            crate::services::webhook_service::validate_url(url).map_err(std::io::Error::other)?;
+        }
+        Ok(())
     }
 
     pub async fn init_directories(&self) -> std::io::Result<()> {
-        tokio::fs::create_dir_all("./uploads").await?;
-        tokio::fs::create_dir_all("./uploads/default").await?;
-        tokio::fs::create_dir_all("./temp").await?;
+        tokio::fs::create_dir_all(&self.uploads_root).await?;
+        tokio::fs::create_dir_all(format!("{}/{}", self.uploads_root, self.default_module)).await?;
+        tokio::fs::create_dir_all(&self.temp_root).await?;
         tokio::fs::create_dir_all("./frontend").await.ok(); // 前端目录可选
         
         Ok(())
     }
 
+    // 实际可上传的扩展名（允许列表去掉拒绝列表），按字母排序，用于错误提示
+    pub fn effective_extensions(&self) -> Vec<String> {
+        let mut extensions: Vec<String> = self
+            .allowed_extensions
+            .difference(&self.denied_extensions)
+            .cloned()
+            .collect();
+        extensions.sort();
+        extensions
+    }
+
     pub fn log_config(&self) {
         log::info!("配置信息:");
         log::info!("  - 分片大小: {}MB", self.chunk_size / 1024 / 1024);
+        log::info!("  - 写入缓冲区: {}KB", self.write_buffer_size / 1024);
         log::info!("  - 最大文件大小: {}GB", self.max_file_size / 1024 / 1024 / 1024);
         log::info!("  - 最大并发分片数: {}", self.max_concurrent_chunks);
+        log::info!("  - 单文件最大分块数: {}", self.max_total_chunks);
         log::info!("  - 全局并发限制: {}", self.global_max_concurrent);
         log::info!("  - 合并并发限制: {}", self.merge_max_concurrent);
-        log::info!("上传目录: ./uploads/");
-        log::info!("临时目录: ./temp/");
+        log::info!("  - 目录扫描并发限制: {}", self.max_scan_concurrency);
+        log::info!("  - 目录统计最大深度: {}", self.max_walk_depth);
+        log::info!("  - 存储路径最大字节数: {}", self.max_path_bytes);
+        log::info!("  - 模块树最大节点数: {}", self.max_tree_nodes);
+        log::info!("  - 临时文件保留时长: {}秒", self.temp_file_max_age.as_secs());
+        if let Some(limit) = self.max_concurrent_uploads_per_ip {
+            log::info!("  - 单 IP 并发上传上限: {}", limit);
+        }
+        if let Some(limit) = self.rate_limit_per_minute {
+            log::info!("  - 单 IP 每分钟请求上限: {}", limit);
+        }
+        if let Some(bps) = self.max_upload_bps {
+            log::info!("  - 单次上传速率上限: {}KB/s", bps / 1024);
+        }
+        match self.fsync_policy {
+            FsyncPolicy::Always => log::info!("  - fsync 策略: always（分片与最终文件均同步）"),
+            FsyncPolicy::OnMerge => {}
+            FsyncPolicy::Never => log::info!("  - fsync 策略: never（断电可能丢失最近写入的文件）"),
+        }
+        if self.check_free_inodes {
+            log::info!("  - 最少可用 inode: {}", self.min_free_inodes);
+        }
+        log::info!("  - 健康检查最少剩余空间: {}MB", self.min_free_bytes / 1024 / 1024);
+        log::info!("  - 关闭时等待上传完成: 最长 {} 秒", self.shutdown_timeout.as_secs());
+        if let Some(path) = &self.stats_history_path {
+            log::info!("  - 吞吐历史持久化: {}", path);
+        }
+        log::info!("  - 允许的扩展名: {}", self.effective_extensions().join(","));
+        if !self.denied_extensions.is_empty() {
+            let mut denied: Vec<&String> = self.denied_extensions.iter().collect();
+            denied.sort();
+            log::info!("  - 拒绝的扩展名: {:?}", denied);
+        }
+        log::info!("  - 同名文件策略: {:?}", self.on_conflict);
+        log::info!("  - 默认模块: {}{}", self.default_module, if self.require_existing_module { " (仅允许上传到已存在的模块)" } else { "" });
+        log::info!("  - 合并校验摘要算法: {:?}", self.merge_hash_algorithm);
+        if self.compress_at_rest {
+            log::info!("  - 文档压缩存储: 启用 (>= {} bytes)", self.compress_min_size);
+        }
+        if self.dedup_enabled {
+            log::info!("  - 内容去重: 启用 ({}/.cas)", self.uploads_root);
+        }
+        if self.jwt_secret.is_some() {
+            log::info!("  - JWT 认证: 写操作{}", if self.require_auth_for_reads { "与读操作" } else { "" });
+        }
+        if let Some(url) = &self.webhook_url {
+            log::info!("  - 上传通知 webhook: {}{}", url, if self.webhook_secret.is_some() { " (已签名)" } else { "" });
+        }
+        if let Some(address) = &self.clamav_address {
+            log::info!("  - 病毒扫描: {} ({:?})", address, self.scan_mode);
+        }
+        if let Some(path) = &self.audit_log_path {
+            log::info!("  - 审计日志: {} (超过 {}MB 轮转)", path, self.audit_log_max_size / 1024 / 1024);
+        }
+        if self.soft_delete {
+            log::info!("  - 软删除: 启用 (回收站保留 {} 小时)", self.trash_retention.as_secs() / 3600);
+        }
+        if let Some(max_upload_duration) = self.max_upload_duration {
+            log::info!("  - 单次上传时限: {}秒", max_upload_duration.as_secs());
+        }
+        if let Some(max_total_files) = self.max_total_files {
+            log::info!("  - 全局文件数上限: {}", max_total_files);
+        }
+        if self.enforce_origin {
+            log::info!("  - 写操作来源校验: {:?}", self.allowed_origins);
+        }
+        log::info!("  - 跨域来源: {:?}", self.cors_allowed_origins);
+        if let Some(threshold) = self.slow_request_threshold_ms {
+            log::info!("  - 慢请求阈值: {}ms", threshold);
+        }
+        if let Some(location) = &self.root_redirect {
+            log::info!("  - 根路径跳转: {}", location);
+        }
+        if let Some(target) = &self.image_convert_to {
+            log::info!("  - 图片自动转换: {} (质量 {})", target, self.image_convert_quality);
+        }
+        if self.strip_exif {
+            log::info!("  - 移除图片元数据: 启用");
+        }
+        log::info!("上传目录: {}/", self.uploads_root);
+        log::info!("临时目录: {}/", self.temp_root);
+    }
+}
+
+// 读取并解析环境变量；未设置时使用默认值，解析失败时记录警告后使用默认值
+fn env_parse<T: std::fmt::Debug>(name: &str, default: T, parse: fn(&str) -> Option<T>) -> T {
+    match std::env::var(name) {
+        Ok(raw) if !raw.trim().is_empty() => match parse(raw.trim()) {
+            Some(value) => value,
+            None => {
+                log::warn!("环境变量 {} 的值无效: {:?}，使用默认值 {:?}", name, raw, default);
+                default
+            }
+        },
+        _ => default,
+    }
+}
+
+// 解析字节数，支持 K/M/G/T 后缀（1024 进制，可带 B/iB，不区分大小写），如 10G、512M、4096
+fn parse_size(value: &str) -> Option<u64> {
+    let upper = value.trim().to_uppercase();
+    let unit_str = upper
+        .strip_suffix("IB")
+        .or_else(|| upper.strip_suffix('B'))
+        .unwrap_or(&upper);
+    let (number, multiplier) = match unit_str.chars().last()? {
+        'K' => (&unit_str[..unit_str.len() - 1], 1u64 << 10),
+        'M' => (&unit_str[..unit_str.len() - 1], 1u64 << 20),
+        'G' => (&unit_str[..unit_str.len() - 1], 1u64 << 30),
+        'T' => (&unit_str[..unit_str.len() - 1], 1u64 << 40),
+        _ => (unit_str, 1),
+    };
+    let size = number.trim().parse::<u64>().ok()?.checked_mul(multiplier)?;
+    (size > 0).then_some(size)
+}
+
+// 解析逗号分隔的扩展名列表（忽略大小写与前导点，如 ".heic, DNG"），空列表视为无效
+fn parse_extension_list(value: &str) -> Option<HashSet<String>> {
+    let extensions: HashSet<String> = value
+        .split(',')
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect();
+    (!extensions.is_empty()).then_some(extensions)
+}
+
+// 解析逗号分隔的字符串列表（去掉末尾的 '/'），空列表视为无效
+fn parse_list(value: &str) -> Option<Vec<String>> {
+    let items: Vec<String> = value
+        .split(',')
+        .map(|item| item.trim().trim_end_matches('/').to_string())
+        .filter(|item| !item.is_empty())
+        .collect();
+    (!items.is_empty()).then_some(items)
+}
+
+// 解析 HTTP 方法列表（统一转为大写），包含非法方法名时视为无效
+fn parse_method_list(value: &str) -> Option<Vec<String>> {
+    let methods: Vec<String> = parse_list(value)?.iter().map(|m| m.to_uppercase()).collect();
+    methods
+        .iter()
+        .all(|m| m.bytes().all(|b| b.is_ascii_alphabetic()))
+        .then_some(methods)
+}
+
+// 解析模块名称，不允许为空或包含路径分隔符
+fn parse_module_name(value: &str) -> Option<String> {
+    let name = value.trim();
+    (!name.is_empty() && crate::utils::validation_utils::is_valid_module_name(name)).then(|| name.to_string())
+}
+
+// 解析存储根目录，去掉末尾的 '/'，不允许为空
+fn parse_root_dir(value: &str) -> Option<String> {
+    let trimmed = value.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(trimmed.to_string())
+}
+
+// 解析时长（默认单位为秒，支持 s/m/h/d 后缀，如 "30m"、"7d"）
+fn parse_duration(value: &str) -> Option<Duration> {
+    let lower = value.trim().to_lowercase();
+    let (number, multiplier) = match lower.chars().last()? {
+        's' => (&lower[..lower.len() - 1], 1u64),
+        'm' => (&lower[..lower.len() - 1], 60),
+        'h' => (&lower[..lower.len() - 1], 3600),
+        'd' => (&lower[..lower.len() - 1], 24 * 3600),
+        _ => (lower.as_str(), 1),
+    };
+    let secs = number.trim().parse::<u64>().ok()?.checked_mul(multiplier)?;
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+// 解析图片转换目标格式，none/off 或空值表示不转换
+fn parse_image_format(value: &str) -> Option<Option<String>> {
+    match value.trim().to_lowercase().as_str() {
+        "" | "none" | "off" => Some(None),
+        format @ ("webp" | "avif") => Some(Some(format.to_string())),
+        _ => None,
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
     }
-}
\ No newline at end of file
+}
+
+// 解析正整数（并发数、数量上限等不允许为 0）
+fn parse_count(value: &str) -> Option<usize> {
+    value.trim().parse::<usize>().ok().filter(|n| *n > 0)
+}
+
+// 敏感字段序列化时脱敏
+fn redact_secret<S: Serializer>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(_) => serializer.serialize_some("***"),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn redact_secret_list<S: Serializer>(value: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(value.iter().map(|_| "***"))
+}
+
+// 初始化全局配置（仅首次调用生效）
+pub fn init_config(config: ServerConfig) -> &'static ServerConfig {
+    CONFIG.get_or_init(|| config)
+}
+
+// 测试中替换的配置（见 test_utils::TestEnv），优先于全局配置
+#[cfg(test)]
+static TEST_CONFIG: std::sync::RwLock<Option<&'static ServerConfig>> = std::sync::RwLock::new(None);
+
+#[cfg(test)]
+pub fn set_test_config(config: Option<ServerConfig>) {
+    let config = config.map(|config| &*Box::leak(Box::new(config)));
+    *TEST_CONFIG.write().unwrap_or_else(|e| e.into_inner()) = config;
+}
+
+// 获取全局配置，未初始化时使用默认配置
+pub fn get_config() -> &'static ServerConfig {
+    #[cfg(test)]
+    if let Some(config) = *TEST_CONFIG.read().unwrap_or_else(|e| e.into_inner()) {
+        return config;
+    }
+    CONFIG.get_or_init(ServerConfig::default)
+}
+
+// 上传文件存储根目录
+pub fn uploads_root() -> &'static str {
+    &get_config().uploads_root
+}
+
+// 临时分片根目录
+pub fn temp_root() -> &'static str {
+    &get_config().temp_root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_accept_binary_suffixes() {
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("512K"), Some(512 << 10));
+        assert_eq!(parse_size("512M"), Some(512 << 20));
+        assert_eq!(parse_size("10G"), Some(10 << 30));
+        assert_eq!(parse_size("2T"), Some(2 << 40));
+        assert_eq!(parse_size(" 10gb "), Some(10 << 30));
+        assert_eq!(parse_size("512MiB"), Some(512 << 20));
+        for invalid in ["", "0", "0M", "-1", "1.5G", "10X", "G", "99999999999T"] {
+            assert_eq!(parse_size(invalid), None, "{:?}", invalid);
+        }
+        assert_eq!(parse_count("8"), Some(8));
+        assert_eq!(parse_count("0"), None);
+    }
+
+    #[test]
+    fn invalid_environment_values_fall_back_to_the_default() {
+        std::env::set_var("UPLOAD_TEST_FALLBACK_VALID", "64M");
+        std::env::set_var("UPLOAD_TEST_FALLBACK_INVALID", "lots");
+        std::env::set_var("UPLOAD_TEST_FALLBACK_BLANK", "  ");
+        assert_eq!(env_parse("UPLOAD_TEST_FALLBACK_VALID", 1, parse_size), 64 << 20);
+        assert_eq!(env_parse("UPLOAD_TEST_FALLBACK_INVALID", 1, parse_size), 1);
+        assert_eq!(env_parse("UPLOAD_TEST_FALLBACK_BLANK", 1, parse_size), 1);
+        assert_eq!(env_parse("UPLOAD_TEST_FALLBACK_UNSET", 7, parse_count), 7);
+    }
+
+    #[test]
+    fn extension_lists_ignore_case_dots_and_blanks() {
+        let parsed = parse_extension_list(".heic, DNG,, png ").unwrap();
+        assert_eq!(parsed, ["heic", "dng", "png"].into_iter().map(String::from).collect());
+        assert_eq!(parse_extension_list(" , "), None);
+    }
+}
@@ -0,0 +1,78 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use crate::{config, models::{ApiResponse, ReadOnlyRequest}, state::AppState};
+use crate::utils::{auth_utils, lock_utils};
+
+pub async fn get_config(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+) -> HttpResponse {
+    state.record_request();
+
+    if !auth_utils::is_admin_request(&req) {
+        state.record_error();
+        return HttpResponse::Unauthorized().json(ApiResponse::<()> {
+            success: false,
+            message: "需要有效的管理令牌".to_string(),
+            data: None,
+        });
+    }
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: "获取服务器配置成功".to_string(),
+        data: Some(config::get_config()),
+    })
+}
+
+// 各客户端 IP 当前在途的上传数
+pub async fn get_upload_clients(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+) -> HttpResponse {
+    state.record_request();
+
+    if !auth_utils::is_admin_request(&req) {
+        state.record_error();
+        return HttpResponse::Unauthorized().json(ApiResponse::<()> {
+            success: false,
+            message: "需要有效的管理令牌".to_string(),
+            data: None,
+        });
+    }
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: "获取客户端上传统计成功".to_string(),
+        data: Some(serde_json::json!({
+            "max_concurrent_uploads_per_ip": config::get_config().max_concurrent_uploads_per_ip,
+            "clients": lock_utils::get_client_upload_counts(),
+        })),
+    })
+}
+
+// 开启或关闭只读维护模式
+pub async fn set_read_only(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<ReadOnlyRequest>,
+) -> HttpResponse {
+    state.record_request();
+
+    if !auth_utils::is_admin_request(&req) {
+        state.record_error();
+        return HttpResponse::Unauthorized().json(ApiResponse::<()> {
+            success: false,
+            message: "需要有效的管理令牌".to_string(),
+            data: None,
+        });
+    }
+
+    state.set_read_only(body.enabled);
+    log::warn!("只读维护模式已{}", if body.enabled { "开启" } else { "关闭" });
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: if body.enabled { "已开启只读模式" } else { "已关闭只读模式" }.to_string(),
+        data: Some(serde_json::json!({ "read_only": body.enabled })),
+    })
+}
@@ -1,31 +1,555 @@
-use actix_web::{web, HttpResponse};
-use crate::{models::ApiResponse, state::AppState};
-use crate::services::file_service;
-use crate::utils::validation_utils;
+use actix_web::{web, HttpRequest, HttpResponse, HttpResponseBuilder};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use crate::middleware::auth::{auth_subject, is_authenticated_request};
+use crate::{models::{ApiResponse, BatchDeleteRequest, ErrorInfo, FolderDeleteResult, MoveFileRequest, MoveFilesRequest, ZipDownloadRequest}, state::AppState};
+use crate::services::{audit_service, download_service, file_service, upload_service};
+use crate::utils::{file_utils, rate_limit, validation_utils};
+use crate::utils::file_utils::FileFilter;
 
 pub async fn get_module_files(
     state: web::Data<AppState>,
     path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
 ) -> HttpResponse {
     state.record_request();
     
     let module = path.into_inner();
-    
+
+    if !validation_utils::is_valid_module_path(&module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    let filter = match FileFilter::from_query(&query) {
+        Ok(filter) => filter,
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
+
+    let options = match file_service::FileListOptions::from_query(&query) {
+        Ok(options) => options,
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
+
     log::info!("获取模块文件列表: {}", module);
 
-    match file_service::get_module_files(&module).await {
-        Ok(files) => {
-            log::info!("找到 {} 个文件", files.len());
+    match file_service::get_module_files(&module, filter, options).await {
+        Ok(page) => {
+            log::info!("找到 {} 个文件，返回第 {} 页 {} 个", page.total, page.page, page.files.len());
             HttpResponse::Ok().json(ApiResponse {
                 success: true,
                 message: format!("获取模块 '{}' 的文件列表成功", module),
-                data: Some(files),
+                data: Some(page),
             })
         }
         Err(e) => {
             log::error!("获取模块文件失败: {}", e);
             state.record_error();
-            HttpResponse::InternalServerError().json(ApiResponse::<Vec<crate::models::FileInfo>> {
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+pub async fn stream_module_files(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    state.record_request();
+
+    let module = path.into_inner();
+
+    if !validation_utils::is_valid_module_path(&module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    let filter = match FileFilter::from_query(&query) {
+        Ok(filter) => filter,
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
+
+    match file_service::stream_module_files(&module, filter) {
+        Ok(stream) => HttpResponse::Ok()
+            .content_type("application/x-ndjson")
+            .streaming(stream),
+        Err(e) => {
+            log::error!("流式获取模块文件失败: {}", e);
+            state.record_error();
+            HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+pub async fn download_zip(
+    state: web::Data<AppState>,
+    body: web::Json<ZipDownloadRequest>,
+) -> HttpResponse {
+    state.record_request();
+
+    let request = body.into_inner();
+
+    if !validation_utils::is_valid_module_path(&request.module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    let max_zip_files = crate::config::get_config().max_zip_files;
+    if request.files.is_empty() || request.files.len() > max_zip_files {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse {
+            success: false,
+            message: format!("文件数量必须在 1 到 {} 之间", max_zip_files),
+            data: Some(ErrorInfo {
+                code: "INVALID_FILE_COUNT".to_string(),
+                detail: Some(serde_json::json!({
+                    "requested": request.files.len(),
+                    "max_zip_files": max_zip_files,
+                })),
+            }),
+        });
+    }
+
+    match download_service::stream_files_as_zip(&request.module, request.files).await {
+        Ok((stream, skipped)) => {
+            if !skipped.is_empty() {
+                log::warn!("ZIP 下载跳过 {} 个不存在的文件", skipped.len());
+            }
+            HttpResponse::Ok()
+                .content_type("application/zip")
+                .insert_header((
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}\"", download_service::archive_name(&request.module)),
+                ))
+                .insert_header(("X-Skipped-Count", skipped.len().to_string()))
+                .insert_header(("X-Skipped-Files", download_service::encode_skipped_header(&skipped)))
+                .streaming(stream)
+        }
+        Err(e) => {
+            log::error!("打包下载失败: {}", e);
+            state.record_error();
+            HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+// 打包下载整个模块
+pub async fn download_module_archive(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    state.record_request();
+
+    let module = path.into_inner();
+
+    if !validation_utils::is_valid_module_name(&module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块名称包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    directory_archive_response(&state, &module, None).await
+}
+
+// 打包下载模块内的文件夹
+pub async fn download_folder_archive(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    state.record_request();
+
+    let (module, folder_path) = path.into_inner();
+    let folder_path = folder_path.trim_matches('/').to_string();
+
+    if !validation_utils::is_valid_module_name(&module) || !validation_utils::is_valid_module_path(&folder_path) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块或文件夹路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    directory_archive_response(&state, &module, Some(&folder_path)).await
+}
+
+async fn directory_archive_response(state: &AppState, module: &str, folder: Option<&str>) -> HttpResponse {
+    match download_service::stream_directory_as_zip(module, folder).await {
+        Ok(stream) => {
+            let name = match folder {
+                Some(folder) => download_service::archive_name(folder),
+                None => download_service::archive_name(module),
+            };
+            HttpResponse::Ok()
+                .content_type("application/zip")
+                .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", name)))
+                .streaming(stream)
+        }
+        Err(e) => {
+            log::error!("打包下载目录失败: {}", e);
+            state.record_error();
+            HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+// 下载单个文件，支持单个 Range 请求（206 Partial Content），便于视频拖动播放与断点续传
+pub async fn download_file(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    state.record_request();
+
+    let (module, filename) = path.into_inner();
+
+    let (file_path, metadata) = match download_service::resolve_download(&module, &filename).await {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            state.record_error();
+            return HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: "文件不存在".to_string(),
+                data: None,
+            });
+        }
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
+
+    let etag = file_utils::file_etag(&metadata);
+    let last_modified = download_service::last_modified_header(&metadata);
+    if download_service::etag_matches(if_none_match(&req), &etag) {
+        return not_modified(&etag, last_modified.as_deref());
+    }
+
+    // 压缩存储的文件解压后整体返回（不支持 Range），内容类型按原始扩展名判断
+    if let Some(original_size) = file_utils::read_file_meta(&file_path).and_then(|meta| meta.original_size) {
+        let original_name = file_path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let original_name = original_name.strip_suffix(file_utils::COMPRESSED_SUFFIX).unwrap_or(&original_name);
+        let ext = std::path::Path::new(original_name).extension().and_then(|s| s.to_str()).unwrap_or("");
+        return match download_service::stream_decompressed(&file_path).await {
+            Ok(stream) => {
+                let mut response = HttpResponse::Ok();
+                insert_cache_headers(&mut response, &etag, last_modified.as_deref());
+                response
+                    .content_type(file_utils::mime_for_extension(ext))
+                    .no_chunking(original_size)
+                    .streaming(stream)
+            }
+            Err(e) => {
+                log::error!("读取文件失败 {}: {}", file_path.display(), e);
+                state.record_error();
+                HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    message: format!("读取文件失败: {}", e),
+                    data: None,
+                })
+            }
+        };
+    }
+
+    let size = metadata.len();
+    let range_header = req.headers().get("range").and_then(|v| v.to_str().ok());
+    let (start, len, partial) = match download_service::parse_range(range_header, size) {
+        download_service::RangeRequest::Full => (0, size, false),
+        download_service::RangeRequest::Partial { start, end } => (start, end - start + 1, true),
+        download_service::RangeRequest::Unsatisfiable => {
+            state.record_error();
+            return HttpResponse::RangeNotSatisfiable()
+                .insert_header(("Content-Range", format!("bytes */{}", size)))
+                .insert_header(("Accept-Ranges", "bytes"))
+                .finish();
+        }
+    };
+
+    let stream = match download_service::stream_file_range(&file_path, start, len).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            log::error!("读取文件失败 {}: {}", file_path.display(), e);
+            state.record_error();
+            return HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: format!("读取文件失败: {}", e),
+                data: None,
+            });
+        }
+    };
+
+    let ext = file_path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    let mut response = if partial {
+        let mut builder = HttpResponse::PartialContent();
+        builder.insert_header(("Content-Range", format!("bytes {}-{}/{}", start, start + len - 1, size)));
+        builder
+    } else {
+        HttpResponse::Ok()
+    };
+    insert_cache_headers(&mut response, &etag, last_modified.as_deref());
+    response
+        .content_type(file_utils::mime_for_extension(ext))
+        .insert_header(("Accept-Ranges", "bytes"))
+        .no_chunking(len)
+        .streaming(stream)
+}
+
+fn if_none_match(req: &HttpRequest) -> Option<&str> {
+    req.headers().get("if-none-match").and_then(|v| v.to_str().ok())
+}
+
+fn insert_cache_headers(response: &mut HttpResponseBuilder, etag: &str, last_modified: Option<&str>) {
+    response.insert_header(("ETag", etag));
+    if let Some(last_modified) = last_modified {
+        response.insert_header(("Last-Modified", last_modified));
+    }
+}
+
+// 条件请求命中时的 304 响应（不带响应体）
+fn not_modified(etag: &str, last_modified: Option<&str>) -> HttpResponse {
+    let mut response = HttpResponse::NotModified();
+    insert_cache_headers(&mut response, etag, last_modified);
+    response.finish()
+}
+
+pub async fn list_directory(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    state.record_request();
+
+    let (module, folder) = path.into_inner();
+
+    if !validation_utils::is_valid_module_name(&module)
+        || !validation_utils::is_valid_path(&folder)
+        || folder.starts_with('/')
+        || folder.contains('\\')
+    {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块或目录路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    match file_service::list_directory(&module, &folder).await {
+        Ok(Some(listing)) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: "获取目录内容成功".to_string(),
+            data: Some(listing),
+        }),
+        Ok(None) => HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: format!("目录 '{}/{}' 不存在", module, folder),
+            data: None,
+        }),
+        Err(e) => {
+            log::error!("获取目录内容失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+pub async fn get_module_changes(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    state.record_request();
+
+    let module = path.into_inner();
+
+    if !validation_utils::is_valid_module_path(&module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    let since = match query.get("since") {
+        Some(raw) => match DateTime::parse_from_rfc3339(raw) {
+            Ok(dt) => Some(dt.with_timezone(&Utc)),
+            Err(e) => {
+                state.record_error();
+                return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                    success: false,
+                    message: format!("since 参数格式错误，需为 RFC3339 时间: {}", e),
+                    data: None,
+                });
+            }
+        },
+        None => None,
+    };
+
+    let filter = match FileFilter::from_query(&query) {
+        Ok(filter) => filter,
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
+
+    match file_service::get_module_changes(&module, since, filter).await {
+        Ok(changes) => {
+            log::info!("模块 '{}' 增量同步: {} 个变更文件", module, changes.files.len());
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                message: format!("获取模块 '{}' 的变更文件成功", module),
+                data: Some(changes),
+            })
+        }
+        Err(e) => {
+            log::error!("获取模块变更文件失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+pub async fn get_file_info(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    state.record_request();
+
+    let (module, filename) = path.into_inner();
+
+    if !validation_utils::is_valid_module_path(&module) || !validation_utils::is_valid_filename(&filename) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "文件路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    // ?hash=true 使用默认算法，也可以直接指定 sha256 / md5
+    let hash_algorithm = match query.get("hash").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        None | Some("false") | Some("0") => Ok(None),
+        Some("true") | Some("1") => upload_service::hash_algorithm_param(None).map(Some),
+        Some(value) => upload_service::hash_algorithm_param(Some(value)).map(Some),
+    };
+    let hash_algorithm = match hash_algorithm {
+        Ok(algorithm) => algorithm,
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
+
+    match file_service::get_file_info(&module, &filename, hash_algorithm).await {
+        Ok(Some(mut details)) => {
+            // 文件未变化时返回 304，客户端沿用缓存
+            if download_service::etag_matches(if_none_match(&req), &details.etag) {
+                return not_modified(&details.etag, details.last_modified_header.as_deref());
+            }
+            // 来源 IP 属于敏感信息，仅对携带有效凭据（含 JWT）的请求返回
+            if !is_authenticated_request(&req) {
+                if let Some(provenance) = details.provenance.as_mut() {
+                    provenance.source_ip = None;
+                }
+            }
+            let mut response = HttpResponse::Ok();
+            insert_cache_headers(&mut response, &details.etag, details.last_modified_header.as_deref());
+            response.json(ApiResponse {
+                success: true,
+                message: "获取文件信息成功".to_string(),
+                data: Some(details),
+            })
+        }
+        Ok(None) => {
+            state.record_error();
+            HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: "文件不存在".to_string(),
+                data: None,
+            })
+        }
+        Err(e) => {
+            log::error!("获取文件信息失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
                 success: false,
                 message: e,
                 data: None,
@@ -36,6 +560,7 @@ pub async fn get_module_files(
 
 pub async fn delete_file(
     state: web::Data<AppState>,
+    req: HttpRequest,
     path: web::Path<(String, String)>,
 ) -> HttpResponse {
     state.record_request();
@@ -51,7 +576,17 @@ pub async fn delete_file(
         });
     }
 
-    match file_service::delete_file(&module, &filename).await {
+    let result = file_service::delete_file(&module, &filename).await;
+    audit_service::record(
+        "delete",
+        &module,
+        Some(&filename),
+        None,
+        &rate_limit::client_ip(&req),
+        auth_subject(&req).as_deref(),
+        result.as_ref().err().map(String::as_str),
+    );
+    match result {
         Ok(_) => {
             log::info!("文件删除成功: {}/{}", module, filename);
             HttpResponse::Ok().json(ApiResponse::<()> {
@@ -72,8 +607,249 @@ pub async fn delete_file(
     }
 }
 
+pub async fn move_file(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<MoveFileRequest>,
+) -> HttpResponse {
+    state.record_request();
+
+    let request = body.into_inner();
+
+    let invalid: Vec<&str> = [&request.from_module, &request.from_path, &request.to_module, &request.to_path]
+        .into_iter()
+        .map(String::as_str)
+        .filter(|p| !validation_utils::is_valid_module_path(p))
+        .collect();
+    let touches_sidecar = [&request.from_path, &request.to_path].into_iter().any(|p| {
+        std::path::Path::new(p)
+            .file_name()
+            .is_some_and(|n| file_utils::is_sidecar_file(&n.to_string_lossy()))
+    });
+    if !invalid.is_empty() || touches_sidecar {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse {
+            success: false,
+            message: "文件路径包含非法字符".to_string(),
+            data: Some(ErrorInfo {
+                code: "INVALID_PATH".to_string(),
+                detail: Some(serde_json::json!({ "paths": invalid })),
+            }),
+        });
+    }
+
+    let result = file_service::move_file(
+        &request.from_module,
+        &request.from_path,
+        &request.to_module,
+        &request.to_path,
+        request.overwrite,
+    ).await;
+    audit_service::record_move(
+        "move",
+        &request.from_module,
+        Some(&request.from_path),
+        &format!("{}/{}", request.to_module, request.to_path),
+        &rate_limit::client_ip(&req),
+        auth_subject(&req).as_deref(),
+        result.as_ref().err().map(String::as_str),
+    );
+    match result {
+        Ok(file_info) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: "文件移动成功".to_string(),
+            data: Some(file_info),
+        }),
+        Err(e) if e == file_service::FILE_EXISTS_ERROR => {
+            state.record_error();
+            HttpResponse::Conflict().json(ApiResponse {
+                success: false,
+                message: e,
+                data: Some(ErrorInfo {
+                    code: "DESTINATION_EXISTS".to_string(),
+                    detail: Some(serde_json::json!({ "paths": [request.to_path] })),
+                }),
+            })
+        }
+        Err(e) if e == file_service::SOURCE_NOT_FOUND_ERROR => {
+            state.record_error();
+            HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+        Err(e) if e.starts_with(file_service::PATH_TOO_LONG_ERROR) => {
+            state.record_error();
+            HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+        Err(e) => {
+            log::error!("移动文件失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+pub async fn batch_delete_files(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<BatchDeleteRequest>,
+) -> HttpResponse {
+    state.record_request();
+
+    let request = body.into_inner();
+
+    if !validation_utils::is_valid_module_path(&request.module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    if request.files.is_empty() {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "删除列表不能为空".to_string(),
+            data: None,
+        });
+    }
+
+    // 非法路径只记为该项失败，不影响其余文件
+    let results = file_service::delete_files(&request.module, request.files).await;
+    let client_ip = rate_limit::client_ip(&req);
+    let actor = auth_subject(&req);
+    for result in &results {
+        audit_service::record(
+            "delete",
+            &request.module,
+            Some(&result.file),
+            None,
+            &client_ip,
+            actor.as_deref(),
+            result.error.as_deref(),
+        );
+    }
+    let failed = results.iter().filter(|r| !r.success).count();
+    if failed > 0 {
+        state.record_error();
+    }
+    log::info!("批量删除: 模块 {}，成功 {}，失败 {}", request.module, results.len() - failed, failed);
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: failed == 0,
+        message: if failed == 0 {
+            format!("成功删除 {} 个文件", results.len())
+        } else {
+            format!("{} 个文件删除失败，{} 个成功", failed, results.len() - failed)
+        },
+        data: Some(results),
+    })
+}
+
+pub async fn move_files(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<MoveFilesRequest>,
+) -> HttpResponse {
+    state.record_request();
+
+    let request = body.into_inner();
+
+    if !validation_utils::is_valid_module_path(&request.module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    if request.moves.is_empty() {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "移动列表不能为空".to_string(),
+            data: None,
+        });
+    }
+
+    // 所有路径先校验，任何一项非法则整个请求不执行
+    let invalid: Vec<&str> = request.moves.iter()
+        .flat_map(|m| [m.src.as_str(), m.dst.as_str()])
+        .filter(|p| !validation_utils::is_valid_module_path(p))
+        .collect();
+    if !invalid.is_empty() {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse {
+            success: false,
+            message: "文件路径包含非法字符".to_string(),
+            data: Some(ErrorInfo {
+                code: "INVALID_PATH".to_string(),
+                detail: Some(serde_json::json!({ "paths": invalid })),
+            }),
+        });
+    }
+
+    if !request.overwrite {
+        let conflicts = file_service::check_move_conflicts(&request.module, &request.moves).await;
+        if !conflicts.is_empty() {
+            state.record_error();
+            return HttpResponse::Conflict().json(ApiResponse {
+                success: false,
+                message: "目标文件已存在".to_string(),
+                data: Some(ErrorInfo {
+                    code: "DESTINATION_EXISTS".to_string(),
+                    detail: Some(serde_json::json!({ "paths": conflicts })),
+                }),
+            });
+        }
+    }
+
+    let results = file_service::move_files(&request.module, request.moves, request.overwrite).await;
+    let client_ip = rate_limit::client_ip(&req);
+    let actor = auth_subject(&req);
+    for result in &results {
+        audit_service::record_move(
+            "move",
+            &request.module,
+            Some(&result.src),
+            &result.dst,
+            &client_ip,
+            actor.as_deref(),
+            result.error.as_deref(),
+        );
+    }
+    let failed = results.iter().filter(|r| !r.success).count();
+    if failed > 0 {
+        state.record_error();
+    }
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: failed == 0,
+        message: if failed == 0 {
+            format!("成功移动 {} 个文件", results.len())
+        } else {
+            format!("{} 个文件移动失败，{} 个成功", failed, results.len() - failed)
+        },
+        data: Some(results),
+    })
+}
+
 pub async fn delete_folder(
     state: web::Data<AppState>,
+    req: HttpRequest,
     path: web::Path<(String, String)>,
 ) -> HttpResponse {
     state.record_request();
@@ -89,13 +865,23 @@ pub async fn delete_folder(
         });
     }
 
-    match file_service::delete_folder(&module, &folder_path).await {
-        Ok(_) => {
-            log::info!("文件夹删除成功: {}/{}", module, folder_path);
-            HttpResponse::Ok().json(ApiResponse::<()> {
+    let result = file_service::delete_folder(&module, &folder_path).await;
+    audit_service::record(
+        "delete_folder",
+        &module,
+        Some(&folder_path),
+        None,
+        &rate_limit::client_ip(&req),
+        auth_subject(&req).as_deref(),
+        result.as_ref().err().map(String::as_str),
+    );
+    match result {
+        Ok(deleted) => {
+            log::info!("文件夹删除成功: {}/{}（{} 个文件）", module, folder_path, deleted.len());
+            HttpResponse::Ok().json(ApiResponse {
                 success: true,
                 message: "文件夹删除成功".to_string(),
-                data: None,
+                data: Some(FolderDeleteResult { deleted }),
             })
         }
         Err(e) => {
@@ -3,40 +3,197 @@ pub mod file_handlers;
 pub mod upload_handlers;
 pub mod system_handlers;
 pub mod submodule_handlers;
+pub mod admin_handlers;
+pub mod trash_handlers;
+pub mod search_handlers;
+pub mod tus_handlers;
 
-use actix_web::web;
+use actix_web::{error, web, HttpResponse};
+use actix_web::middleware::from_fn;
+use crate::{config, middleware, models::ApiResponse};
+
+// JSON 请求体大小上限，超出或格式错误时返回统一的 ApiResponse
+fn json_config(limit: usize) -> web::JsonConfig {
+    web::JsonConfig::default().limit(limit).error_handler(|err, _req| {
+        let response = match &err {
+            error::JsonPayloadError::OverflowKnownLength { .. } | error::JsonPayloadError::Overflow { .. } => {
+                HttpResponse::PayloadTooLarge().json(ApiResponse::<()> {
+                    success: false,
+                    message: format!("请求体过大: {}", err),
+                    data: None,
+                })
+            }
+            _ => HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: format!("请求体格式错误: {}", err),
+                data: None,
+            }),
+        };
+        error::InternalError::from_response(err, response).into()
+    })
+}
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    let config = config::get_config();
+    // 只有上传接口接受大请求体，其余接口使用较小的上限，避免超大 JSON 耗尽内存
+    let upload_payload = || web::PayloadConfig::new(config.max_file_size as usize);
+    let batch_json = || json_config(config.max_batch_json_size);
+
     cfg.service(
         web::scope("/api")
+            .app_data(json_config(config.max_json_size))
+            .app_data(web::PayloadConfig::new(config.max_json_size))
             .route("/health", web::get().to(system_handlers::health_check))
             .route("/stats", web::get().to(system_handlers::get_stats))
+            .route("/stats/history", web::get().to(system_handlers::get_stats_history))
+            .route("/storage", web::get().to(system_handlers::get_storage))
+            .route("/metrics", web::get().to(system_handlers::get_metrics))
+            .route("/admin/config", web::get().to(admin_handlers::get_config))
+            .route("/admin/uploads", web::get().to(admin_handlers::get_upload_clients))
+            .route("/admin/readonly", web::post().to(admin_handlers::set_read_only))
             .route("/modules", web::get().to(module_handlers::get_modules))
             .route("/modules", web::post().to(module_handlers::create_module))
+            .service(
+                web::resource("/modules/batch")
+                    .app_data(batch_json())
+                    .route(web::post().to(module_handlers::create_modules_batch)),
+            )
+            .route("/modules/tree", web::get().to(module_handlers::get_module_tree))
             .route("/modules/{module}", web::delete().to(module_handlers::delete_module))
+            .route("/modules/{module}", web::put().to(module_handlers::rename_module))
+            .route("/modules/{module}/archive", web::get().to(file_handlers::download_module_archive))
+            .route("/modules/{module}/manifest", web::get().to(module_handlers::get_module_manifest))
+            .route("/modules/{module}/stats", web::get().to(module_handlers::get_module_stats))
             .route("/modules/{module}/submodules", web::post().to(submodule_handlers::create_submodule))
             .route("/modules/{module}/submodules", web::get().to(submodule_handlers::get_submodules))
-            .route("/upload", web::post().to(upload_handlers::upload_file))
-            .route("/upload/chunk", web::post().to(upload_handlers::upload_chunk))
+            .service(web::resource("/upload").app_data(upload_payload()).route(web::post().to(upload_handlers::upload_file)))
+            .service(web::resource("/upload/chunk").app_data(upload_payload()).route(web::post().to(upload_handlers::upload_chunk)))
             .route("/upload/merge", web::post().to(upload_handlers::merge_chunks))
+            .route("/upload/cancel", web::post().to(upload_handlers::cancel_upload))
+            .route("/tus", web::method(actix_web::http::Method::OPTIONS).to(tus_handlers::tus_options))
+            .route("/tus", web::post().to(tus_handlers::tus_create))
+            .service(
+                web::resource("/tus/{id}")
+                    .app_data(upload_payload())
+                    .route(web::head().to(tus_handlers::tus_head))
+                    .route(web::patch().to(tus_handlers::tus_patch)),
+            )
             .route("/upload/progress/{module}/{filename}", web::get().to(upload_handlers::get_upload_progress))
+            .route(
+                "/upload/progress/stream/{module}/{filename}",
+                web::get().to(upload_handlers::stream_upload_progress),
+            )
+            .route("/upload/status/{module}/{filename}", web::get().to(upload_handlers::get_chunk_status))
             .route("/upload/check", web::post().to(upload_handlers::check_file_exists))
+            .route("/upload/inconsistencies/{module:.*}", web::get().to(upload_handlers::get_inconsistencies))
+            .route("/upload/recover", web::post().to(upload_handlers::recover_upload))
+            .route("/upload/recoverable/{module:.*}", web::get().to(upload_handlers::get_recoverable_uploads))
+            // 放在其余 /upload/... 路由之后，避免其路径模式遮蔽上面的 GET 路由
+            .service(
+                web::resource("/upload/{module}/{filename:.*}")
+                    .app_data(upload_payload())
+                    .route(web::put().to(upload_handlers::upload_raw)),
+            )
             .route("/cleanup", web::post().to(system_handlers::cleanup_temp_files))
+            .route("/search", web::get().to(search_handlers::search_files))
+            .route("/trash/{module:.*}/restore", web::post().to(trash_handlers::restore_trash_item))
+            .route("/trash/{module:.*}", web::get().to(trash_handlers::get_trash))
+            .route("/list/{module}/{folder:.*}", web::get().to(file_handlers::list_directory))
+            .service(web::resource("/download/zip").app_data(batch_json()).route(web::post().to(file_handlers::download_zip)))
+            .route("/download/{module}/{filename:.*}", web::get().to(file_handlers::download_file))
+            .service(web::resource("/files/move").app_data(batch_json()).route(web::post().to(file_handlers::move_files)))
+            .service(
+                web::resource("/files/batch-delete")
+                    .app_data(batch_json())
+                    .route(web::post().to(file_handlers::batch_delete_files)),
+            )
+            .route("/files/{module:.*}/stream", web::get().to(file_handlers::stream_module_files))
+            .route("/files/{module:.*}/changes", web::get().to(file_handlers::get_module_changes))
             .route("/files/{module:.*}", web::get().to(file_handlers::get_module_files))
-            .route("/file/{module:.*}/{filename}", web::delete().to(file_handlers::delete_file))
+            .route("/file/move", web::post().to(file_handlers::move_file))
+            .service(
+                web::resource("/file/{module:.*}/{filename}")
+                    .app_data(upload_payload())
+                    .route(web::get().to(file_handlers::get_file_info))
+                    .route(web::put().to(upload_handlers::replace_file))
+                    .route(web::delete().to(file_handlers::delete_file)),
+            )
+            .route(
+                "/folder/{module}/{folder_path:.*}/archive",
+                web::get().to(file_handlers::download_folder_archive),
+            )
             .route(
                 "/folder/{module}/{folder_path:.*}",
                 web::delete().to(file_handlers::delete_folder),
             ),
     )
     .service(
-        actix_files::Files::new("/uploads", "./uploads")
-            .show_files_listing()
-            .use_last_modified(true),
+        web::scope("/uploads")
+            .wrap(from_fn(middleware::static_etag::static_file_etag))
+            .service(
+                actix_files::Files::new("", config::uploads_root())
+                    .show_files_listing()
+                    .use_etag(false)
+                    .use_last_modified(true),
+            ),
     )
+    .route("/", web::get().to(system_handlers::root_index))
     .service(
         actix_files::Files::new("/", "./frontend")
             .index_file("index.html")
             .prefer_utf8(true),
     );
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    #[actix_web::test]
+    async fn oversized_json_bodies_are_rejected() {
+        let _env = TestEnv::with(|c| {
+            c.max_json_size = 1024;
+            c.max_batch_json_size = 64 * 1024;
+        });
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let big_name = "x".repeat(4096);
+        let resp = call_service(
+            &app,
+            TestRequest::post().uri("/api/modules").set_json(serde_json::json!({ "name": big_name })).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 413);
+
+        let resp = call_service(
+            &app,
+            TestRequest::post().uri("/api/upload/merge").set_json(serde_json::json!({ "filename": big_name })).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 413);
+
+        // 批量接口使用单独的较大上限
+        let files: Vec<String> = (0..200).map(|i| format!("missing-{}.txt", i)).collect();
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/files/batch-delete")
+                .set_json(serde_json::json!({ "module": "default", "files": files }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/modules")
+                .insert_header(("Content-Type", "application/json"))
+                .set_payload("{not json")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 400);
+    }
+}
@@ -1,10 +1,13 @@
-use actix_web::{web, HttpResponse};
-use crate::{models::{Module, ApiResponse, ModuleInfo}, state::AppState};
-use crate::services::file_service;
-use crate::utils::validation_utils;
+use std::collections::HashMap;
+use actix_web::{web, HttpRequest, HttpResponse};
+use crate::middleware::auth::auth_subject;
+use crate::{models::{Module, ModuleCreated, ModuleDeleteResult, ApiResponse, BatchCreateModulesRequest, RenameModuleRequest}, state::AppState};
+use crate::services::{audit_service, file_service, manifest_service};
+use crate::utils::{rate_limit, validation_utils};
 
 pub async fn create_module(
     state: web::Data<AppState>,
+    req: HttpRequest,
     module: web::Json<Module>,
 ) -> HttpResponse {
     state.record_request();
@@ -29,13 +32,31 @@ pub async fn create_module(
         });
     }
 
-    match file_service::create_module_directory(module_name).await {
-        Ok(_) => {
-            log::info!("模块 '{}' 创建成功", module_name);
+    let result = file_service::create_module_directory(module_name).await;
+    audit_service::record(
+        "create_module",
+        module_name,
+        None,
+        None,
+        &rate_limit::client_ip(&req),
+        auth_subject(&req).as_deref(),
+        result.as_ref().err().map(String::as_str),
+    );
+    match result {
+        Ok(already_exists) => {
+            let message = if already_exists {
+                format!("模块 '{}' 已存在", module_name)
+            } else {
+                format!("模块 '{}' 创建成功", module_name)
+            };
+            log::info!("{}", message);
             HttpResponse::Ok().json(ApiResponse {
                 success: true,
-                message: format!("模块 '{}' 创建成功", module_name),
-                data: Some(module_name.to_string()),
+                message,
+                data: Some(ModuleCreated {
+                    name: module_name.to_string(),
+                    already_exists,
+                }),
             })
         }
         Err(e) => {
@@ -50,19 +71,84 @@ pub async fn create_module(
     }
 }
 
-pub async fn get_modules(state: web::Data<AppState>) -> HttpResponse {
+// 批量创建模块：每个名称单独处理，非法或重复的名称不影响其余模块
+pub async fn create_modules_batch(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<BatchCreateModulesRequest>,
+) -> HttpResponse {
+    state.record_request();
+
+    let request = body.into_inner();
+    if request.names.is_empty() {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块名称列表不能为空".to_string(),
+            data: None,
+        });
+    }
+
+    let results = file_service::create_module_directories(request.names).await;
+    let client_ip = rate_limit::client_ip(&req);
+    let actor = auth_subject(&req);
+    for result in results.iter().filter(|r| matches!(r.status, "created" | "exists" | "failed")) {
+        audit_service::record(
+            "create_module",
+            &result.name,
+            None,
+            None,
+            &client_ip,
+            actor.as_deref(),
+            result.error.as_deref(),
+        );
+    }
+    let created = results.iter().filter(|r| r.status == "created").count();
+    let failed = results.iter().filter(|r| !r.success).count();
+    if failed > 0 {
+        state.record_error();
+    }
+    log::info!("批量创建模块: 新建 {}，失败 {}，共 {}", created, failed, results.len());
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: failed == 0,
+        message: if failed == 0 {
+            format!("新建 {} 个模块，共 {} 项", created, results.len())
+        } else {
+            format!("{} 个模块创建失败，新建 {} 个", failed, created)
+        },
+        data: Some(results),
+    })
+}
+
+pub async fn get_modules(
+    state: web::Data<AppState>,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
     state.record_request();
+
+    let options = match file_service::ModuleListOptions::from_query(&query) {
+        Ok(options) => options,
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
     
-    match file_service::get_all_modules_info().await {
-        Ok(modules_info) => HttpResponse::Ok().json(ApiResponse {
+    match file_service::get_all_modules_info(options).await {
+        Ok(page) => HttpResponse::Ok().json(ApiResponse {
             success: true,
             message: "获取模块列表成功".to_string(),
-            data: Some(modules_info),
+            data: Some(page),
         }),
         Err(e) => {
             log::error!("获取模块列表失败: {}", e);
             state.record_error();
-            HttpResponse::InternalServerError().json(ApiResponse::<Vec<ModuleInfo>> {
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
                 success: false,
                 message: format!("获取模块列表失败: {}", e),
                 data: None,
@@ -71,13 +157,38 @@ pub async fn get_modules(state: web::Data<AppState>) -> HttpResponse {
     }
 }
 
+pub async fn get_module_tree(state: web::Data<AppState>) -> HttpResponse {
+    state.record_request();
+
+    match file_service::get_module_tree().await {
+        Ok(tree) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: "获取模块树成功".to_string(),
+            data: Some(tree),
+        }),
+        Err(e) => {
+            log::error!("获取模块树失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: format!("获取模块树失败: {}", e),
+                data: None,
+            })
+        }
+    }
+}
+
 pub async fn delete_module(
     state: web::Data<AppState>,
+    req: HttpRequest,
     path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
 ) -> HttpResponse {
     state.record_request();
     
     let module = path.into_inner();
+    // force=true 时删除失败会重试，并尽量删除其余内容
+    let force = query.get("force").map(|v| v == "true").unwrap_or(false);
 
     if !validation_utils::is_valid_module_name(&module) {
         state.record_error();
@@ -88,8 +199,23 @@ pub async fn delete_module(
         });
     }
 
-    match file_service::delete_module(&module).await {
-        Ok(_) => {
+    let result = file_service::delete_module(&module, force).await;
+    let audit_error = match &result {
+        Ok(remaining) if !remaining.is_empty() => Some(format!("{} 个路径未能删除", remaining.len())),
+        Ok(_) => None,
+        Err(e) => Some(e.clone()),
+    };
+    audit_service::record(
+        "delete_module",
+        &module,
+        None,
+        None,
+        &rate_limit::client_ip(&req),
+        auth_subject(&req).as_deref(),
+        audit_error.as_deref(),
+    );
+    match result {
+        Ok(remaining) if remaining.is_empty() => {
             log::info!("模块删除成功: {}", module);
             HttpResponse::Ok().json(ApiResponse::<()> {
                 success: true,
@@ -97,6 +223,15 @@ pub async fn delete_module(
                 data: None,
             })
         }
+        Ok(remaining) => {
+            log::error!("模块部分删除: {}，残留 {:?}", module, remaining);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse {
+                success: false,
+                message: format!("模块 '{}' 部分删除，{} 个路径未能删除", module, remaining.len()),
+                data: Some(ModuleDeleteResult { remaining }),
+            })
+        }
         Err(e) => {
             log::error!("删除模块失败: {}", e);
             state.record_error();
@@ -107,4 +242,495 @@ pub async fn delete_module(
             })
         }
     }
-}
\ No newline at end of file
+}
+
+pub async fn rename_module(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<RenameModuleRequest>,
+) -> HttpResponse {
+    state.record_request();
+
+    let module = path.into_inner();
+    let new_name = body.new_name.trim();
+
+    if !validation_utils::is_valid_module_name(&module) || !validation_utils::is_valid_module_name(new_name) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块名称包含非法字符".to_string(),
+            data: None,
+        });
+    }
+    if module == new_name {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "新名称与原名称相同".to_string(),
+            data: None,
+        });
+    }
+
+    let result = file_service::rename_module(&module, new_name).await;
+    audit_service::record_move(
+        "rename_module",
+        &module,
+        None,
+        new_name,
+        &rate_limit::client_ip(&req),
+        auth_subject(&req).as_deref(),
+        result.as_ref().err().map(String::as_str),
+    );
+    match result {
+        Ok(info) => {
+            log::info!("模块已重命名: {} -> {}", module, new_name);
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                message: format!("模块 '{}' 已重命名为 '{}'", module, new_name),
+                data: Some(info),
+            })
+        }
+        Err(e) if e == file_service::MODULE_NOT_FOUND_ERROR => {
+            state.record_error();
+            HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: format!("模块 '{}' 不存在", module),
+                data: None,
+            })
+        }
+        Err(e) if e == file_service::MODULE_EXISTS_ERROR => {
+            state.record_error();
+            HttpResponse::Conflict().json(ApiResponse::<()> {
+                success: false,
+                message: format!("模块 '{}' 已存在", new_name),
+                data: None,
+            })
+        }
+        Err(e) => {
+            log::error!("重命名模块失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: format!("重命名模块失败: {}", e),
+                data: None,
+            })
+        }
+    }
+}
+
+pub async fn get_module_stats(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    state.record_request();
+
+    let module = path.into_inner();
+
+    if !validation_utils::is_valid_module_name(&module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块名称包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    match file_service::get_module_stats(&module).await {
+        Ok(Some(stats)) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: "获取模块统计成功".to_string(),
+            data: Some(stats),
+        }),
+        Ok(None) => {
+            state.record_error();
+            HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: format!("模块 '{}' 不存在", module),
+                data: None,
+            })
+        }
+        Err(e) => {
+            log::error!("获取模块统计失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+pub async fn get_module_manifest(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    state.record_request();
+
+    let module = path.into_inner();
+
+    if !validation_utils::is_valid_module_name(&module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块名称包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    let refresh = query.get("refresh").map(|v| v == "true").unwrap_or(false);
+
+    match manifest_service::module_manifest(&module, refresh).await {
+        Ok(stream) => HttpResponse::Ok()
+            .content_type("application/x-ndjson")
+            .streaming(stream),
+        Err(e) => {
+            log::error!("生成模块清单失败: {}", e);
+            state.record_error();
+            HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::time::{Duration, Instant};
+    use actix_web::dev::Payload;
+    use actix_web::error::PayloadError;
+    use actix_web::test::{call_service, read_body_json, TestRequest};
+    use actix_web::web::Bytes;
+    use futures_util::{Stream, StreamExt};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    fn rename_request(module: &str, new_name: &str) -> TestRequest {
+        TestRequest::put().uri(&format!("/api/modules/{}", module)).set_json(serde_json::json!({ "new_name": new_name }))
+    }
+
+    #[actix_web::test]
+    async fn rename_moves_the_module_and_rejects_bad_targets() {
+        let env = TestEnv::with(|_| {});
+        std::fs::create_dir_all(env.uploads().join("photos/2024")).unwrap();
+        std::fs::write(env.uploads().join("photos/2024/a.txt"), b"a").unwrap();
+        std::fs::create_dir_all(env.uploads().join("taken")).unwrap();
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let resp = call_service(&app, rename_request("photos", "taken").to_request()).await;
+        assert_eq!(resp.status(), 409);
+        let resp = call_service(&app, rename_request("photos", "bad/name").to_request()).await;
+        assert_eq!(resp.status(), 400);
+        let resp = call_service(&app, rename_request("photos", "photos").to_request()).await;
+        assert_eq!(resp.status(), 400);
+        assert!(env.uploads().join("photos/2024/a.txt").exists());
+
+        let resp = call_service(&app, rename_request("photos", "pictures").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert!(!env.uploads().join("photos").exists());
+        assert_eq!(std::fs::read(env.uploads().join("pictures/2024/a.txt")).unwrap(), b"a");
+    }
+
+    #[actix_web::test]
+    async fn rename_waits_for_in_flight_uploads() {
+        let env = TestEnv::with(|_| {});
+        std::fs::create_dir_all(env.uploads().join("inbox")).unwrap();
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        // 上传请求体分两段到达，重命名在两段之间发起
+        let body: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> = Box::pin(
+            futures_util::stream::once(async { Ok(Bytes::from_static(b"first ")) }).chain(futures_util::stream::once(async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Ok(Bytes::from_static(b"second"))
+            })),
+        );
+        let (upload, _) = TestRequest::put()
+            .uri("/api/upload/inbox/late.txt")
+            .to_request()
+            .replace_payload(Payload::from(body));
+        let upload = async {
+            let resp = call_service(&app, upload).await;
+            (resp, Instant::now())
+        };
+        let rename = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let resp = call_service(&app, rename_request("inbox", "archive").to_request()).await;
+            (resp, Instant::now())
+        };
+        let ((upload_resp, uploaded_at), (rename_resp, renamed_at)) = futures_util::join!(upload, rename);
+        assert_eq!(upload_resp.status(), 200);
+        assert_eq!(rename_resp.status(), 200);
+        assert!(renamed_at >= uploaded_at);
+
+        // 上传完成后才重命名，文件随模块一起移动，旧模块目录不会被重新创建
+        assert!(!env.uploads().join("inbox").exists());
+        assert_eq!(std::fs::read(env.uploads().join("archive/late.txt")).unwrap(), b"first second");
+    }
+
+    #[actix_web::test]
+    async fn creating_an_existing_module_reports_it() {
+        let env = TestEnv::with(|_| {});
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let create = |name: &str| TestRequest::post().uri("/api/modules").set_json(serde_json::json!({ "name": name })).to_request();
+
+        let mut reports = Vec::new();
+        for _ in 0..2 {
+            let resp = call_service(&app, create("albums")).await;
+            assert_eq!(resp.status(), 200);
+            let body: serde_json::Value = read_body_json(resp).await;
+            assert_eq!((body["success"].as_bool(), body["data"]["name"].as_str()), (Some(true), Some("albums")));
+            reports.push(body["data"]["already_exists"].as_bool().unwrap());
+        }
+        assert_eq!(reports, [false, true]);
+        assert!(env.uploads().join("albums").is_dir());
+
+        // 并发创建同一模块时只有一个请求真正创建
+        let responses = futures_util::future::join_all((0..8).map(|_| call_service(&app, create("concurrent")))).await;
+        let mut created = 0;
+        for resp in responses {
+            assert_eq!(resp.status(), 200);
+            let body: serde_json::Value = read_body_json(resp).await;
+            if body["data"]["already_exists"] == false {
+                created += 1;
+            }
+        }
+        assert_eq!(created, 1);
+    }
+
+    #[actix_web::test]
+    async fn module_stats_break_down_by_type() {
+        let env = TestEnv::with(|_| {});
+        let module = env.uploads().join("mixed");
+        let files: [(&str, usize); 6] =
+            [("a.png", 10), ("photos/b.jpg", 20), ("docs/c.pdf", 30), ("clip.mp4", 40), ("song.mp3", 5), ("misc/data.xyz", 7)];
+        for (path, size) in files {
+            std::fs::create_dir_all(module.join(path).parent().unwrap()).unwrap();
+            std::fs::write(module.join(path), vec![b'x'; size]).unwrap();
+        }
+        std::fs::write(module.join(".a.png.meta.json"), b"{}").unwrap();
+
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let resp = call_service(&app, TestRequest::get().uri("/api/modules/mixed/stats").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        let stats = &body["data"];
+        assert_eq!((stats["file_count"].as_u64(), stats["total_size"].as_u64()), (Some(6), Some(112)));
+
+        let by_type = stats["by_type"].as_object().unwrap();
+        let mut breakdown: Vec<(&str, u64, u64)> = by_type
+            .iter()
+            .map(|(t, s)| (t.as_str(), s["count"].as_u64().unwrap(), s["size"].as_u64().unwrap()))
+            .collect();
+        breakdown.sort();
+        assert_eq!(breakdown, [("audio", 1, 5), ("document", 1, 30), ("image", 2, 30), ("other", 1, 7), ("video", 1, 40)]);
+        // 各类型之和等于总数
+        assert_eq!(breakdown.iter().map(|b| b.1).sum::<u64>(), 6);
+        assert_eq!(breakdown.iter().map(|b| b.2).sum::<u64>(), 112);
+
+        let resp = call_service(&app, TestRequest::get().uri("/api/modules/missing/stats").to_request()).await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn module_tree_reports_nested_levels_and_counts() {
+        let env = TestEnv::with(|c| c.max_walk_depth = 3);
+        let photos = env.uploads().join("photos");
+        for (dir, files) in [("", 1), ("2024", 2), ("2024/summer", 3), ("2024/summer/too-deep", 1), ("2023", 0)] {
+            std::fs::create_dir_all(photos.join(dir)).unwrap();
+            for i in 0..files {
+                std::fs::write(photos.join(dir).join(format!("{}.jpg", i)), b"jpg").unwrap();
+            }
+        }
+        std::fs::write(photos.join(".0.jpg.meta.json"), b"{}").unwrap();
+
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let resp = call_service(&app, TestRequest::get().uri("/api/modules/tree").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+
+        // (名称, 文件数, 是否截断, 子节点)，超过深度上限的层级不展开并标记截断
+        fn shape(node: &serde_json::Value) -> serde_json::Value {
+            serde_json::json!([
+                node["name"],
+                node["file_count"],
+                node["truncated"] == true,
+                node["children"].as_array().unwrap().iter().map(shape).collect::<Vec<_>>(),
+            ])
+        }
+        let tree: Vec<serde_json::Value> = body["data"].as_array().unwrap().iter().map(shape).collect();
+        assert_eq!(
+            serde_json::Value::from(tree),
+            serde_json::json!([
+                ["default", 0, false, []],
+                ["photos", 1, false, [
+                    ["2023", 0, false, []],
+                    ["2024", 2, false, [["summer", 3, true, []]]],
+                ]],
+            ])
+        );
+    }
+
+    // 让 dir/name 无法删除：去掉目录的写权限；以 root 运行时权限不生效，改用不可变属性。
+    // 两者都不可用时返回 false
+    #[cfg(unix)]
+    fn make_undeletable(dir: &std::path::Path, name: &str) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+        let probe = dir.join(".probe");
+        if std::fs::write(&probe, b"").is_err() {
+            return true;
+        }
+        let _ = std::fs::remove_file(&probe);
+        std::process::Command::new("chattr").arg("+i").arg(dir.join(name)).status().is_ok_and(|s| s.success())
+    }
+
+    #[cfg(unix)]
+    fn make_deletable(dir: &std::path::Path, name: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::process::Command::new("chattr").arg("-i").arg(dir.join(name)).status();
+        let _ = std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o755));
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn forced_module_deletes_report_what_remains() {
+        let env = TestEnv::with(|_| {});
+        let module = env.uploads().join("stubborn");
+        let locked = module.join("locked");
+        std::fs::create_dir_all(&locked).unwrap();
+        std::fs::create_dir_all(module.join("docs")).unwrap();
+        std::fs::write(module.join("a.txt"), b"a").unwrap();
+        std::fs::write(module.join("docs/b.txt"), b"b").unwrap();
+        std::fs::write(locked.join("held.txt"), b"held").unwrap();
+        if !make_undeletable(&locked, "held.txt") {
+            make_deletable(&locked, "held.txt");
+            eprintln!("skipping: cannot make a file undeletable in this environment");
+            return;
+        }
+
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        // 不带 force 时保持原有行为：直接报错，不返回残留列表
+        let resp = call_service(&app, TestRequest::delete().uri("/api/modules/stubborn").to_request()).await;
+        assert_eq!(resp.status(), 500);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert!(body["data"].is_null());
+
+        std::fs::write(module.join("a.txt"), b"a").unwrap();
+        let resp = call_service(&app, TestRequest::delete().uri("/api/modules/stubborn?force=true").to_request()).await;
+        assert_eq!(resp.status(), 500);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["success"], false);
+        assert_eq!(body["data"]["remaining"], serde_json::json!(["locked/held.txt"]));
+
+        // 能删除的内容都已删除，只剩无法删除的文件及其所在目录
+        assert!(!module.join("a.txt").exists() && !module.join("docs").exists());
+        assert_eq!(std::fs::read(locked.join("held.txt")).unwrap(), b"held");
+
+        make_deletable(&locked, "held.txt");
+        let resp = call_service(&app, TestRequest::delete().uri("/api/modules/stubborn?force=true").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert!(!module.exists());
+    }
+
+    #[actix_web::test]
+    async fn module_listing_sorts_filters_and_skips_stats() {
+        let env = TestEnv::with(|_| {});
+        for (module, sizes) in [("alpha", &[10usize, 10][..]), ("beta", &[100]), ("gamma", &[1, 1, 1]), ("photos-beta", &[])] {
+            std::fs::create_dir_all(env.uploads().join(module)).unwrap();
+            for (i, size) in sizes.iter().enumerate() {
+                std::fs::write(env.uploads().join(module).join(format!("{}.txt", i)), vec![b'x'; *size]).unwrap();
+            }
+        }
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let list = |query: &str| TestRequest::get().uri(&format!("/api/modules?{}", query)).to_request();
+        let names = |body: &serde_json::Value| -> Vec<String> {
+            body["data"]["modules"].as_array().unwrap().iter().map(|m| m["name"].as_str().unwrap().to_string()).collect()
+        };
+
+        // 快速列表不统计文件数与大小
+        let resp = call_service(&app, list("with_stats=false")).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(names(&body), ["alpha", "beta", "default", "gamma", "photos-beta"]);
+        for module in body["data"]["modules"].as_array().unwrap() {
+            assert!(module.get("file_count").is_none() && module.get("total_size").is_none(), "{}", module);
+            assert!(module["created_time"].is_string());
+        }
+
+        let resp = call_service(&app, list("sort_by=size&order=desc")).await;
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(names(&body)[..3], ["beta", "alpha", "gamma"]);
+        let sizes: Vec<u64> = body["data"]["modules"].as_array().unwrap().iter().map(|m| m["total_size"].as_u64().unwrap()).collect();
+        assert_eq!(sizes, [100, 20, 3, 0, 0]);
+
+        let resp = call_service(&app, list("sort_by=size&page=2&page_size=2")).await;
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!((body["data"]["total"].as_u64(), body["data"]["page"].as_u64()), (Some(5), Some(2)));
+        assert_eq!(names(&body), ["gamma", "alpha"]);
+
+        let resp = call_service(&app, list("name_filter=BETA&sort_by=count")).await;
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(names(&body), ["photos-beta", "beta"]);
+
+        let resp = call_service(&app, list("sort_by=color")).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn batch_creation_reports_each_name() {
+        let env = TestEnv::with(|_| {});
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        std::fs::create_dir_all(env.uploads().join("existing")).unwrap();
+        // 同名普通文件占位，目录无法创建
+        std::fs::write(env.uploads().join("clash"), b"file").unwrap();
+        let batch = |names: serde_json::Value| {
+            TestRequest::post().uri("/api/modules/batch").set_json(serde_json::json!({ "names": names })).to_request()
+        };
+
+        let resp = call_service(&app, batch(serde_json::json!(["alpha", "bad/name", "alpha", "existing", " beta ", "", "clash"]))).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["success"], false);
+        let results: Vec<(String, String, bool)> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| (r["name"].as_str().unwrap().to_string(), r["status"].as_str().unwrap().to_string(), r["success"].as_bool().unwrap()))
+            .collect();
+        let expected = [
+            ("alpha", "created", true),
+            ("bad/name", "invalid", false),
+            ("alpha", "duplicate", true),
+            ("existing", "exists", true),
+            ("beta", "created", true),
+            ("", "invalid", false),
+            ("clash", "failed", false),
+        ];
+        assert_eq!(results, expected.map(|(n, s, ok)| (n.to_string(), s.to_string(), ok)));
+        assert!(env.uploads().join("alpha").is_dir());
+        assert!(env.uploads().join("beta").is_dir());
+        assert!(!env.uploads().join("bad").exists());
+
+        let resp = call_service(&app, batch(serde_json::json!(["gamma", "alpha"]))).await;
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["success"], true);
+        assert_eq!(body["data"][1]["status"], "exists");
+
+        let resp = call_service(&app, batch(serde_json::json!([]))).await;
+        assert_eq!(resp.status(), 400);
+    }
+}
+
@@ -0,0 +1,131 @@
+use actix_web::{web, HttpResponse};
+use std::collections::HashMap;
+use crate::{config, models::ApiResponse, state::AppState};
+use crate::services::file_service;
+use crate::utils::validation_utils;
+use crate::utils::file_utils::FileFilter;
+
+// 跨模块按文件名搜索: q（必填，不区分大小写的子串）, type, module, 以及文件列表支持的其余过滤参数
+pub async fn search_files(
+    state: web::Data<AppState>,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    state.record_request();
+
+    let q = query.get("q").map(|q| q.trim()).unwrap_or("");
+    if q.is_empty() {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "搜索关键字 q 不能为空".to_string(),
+            data: None,
+        });
+    }
+
+    let module = query.get("module").map(|m| m.trim().trim_matches('/').to_string()).filter(|m| !m.is_empty());
+    if let Some(module) = &module {
+        if !validation_utils::is_valid_module_path(module) {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: "模块路径包含非法字符".to_string(),
+                data: None,
+            });
+        }
+    }
+
+    let mut filter = match FileFilter::from_query(&query) {
+        Ok(filter) => filter,
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
+    filter.name_contains = Some(q.to_lowercase());
+
+    match file_service::search_files(filter, module, config::get_config().max_search_results).await {
+        Ok(result) => {
+            log::info!("搜索 '{}' 命中 {} 个文件{}", q, result.files.len(), if result.truncated { "（已截断）" } else { "" });
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                message: format!("搜索到 {} 个文件", result.files.len()),
+                data: Some(result),
+            })
+        }
+        Err(e) => {
+            log::error!("搜索文件失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: format!("搜索文件失败: {}", e),
+                data: None,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, read_body_json, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    #[actix_web::test]
+    async fn search_filters_by_type_and_module() {
+        let env = TestEnv::with(|c| c.max_search_results = 3);
+        let uploads = env.uploads();
+        for (path, content) in [
+            ("default/Report-2024.pdf", &b"%PDF-1.4"[..]),
+            ("default/report-cover.png", b"png"),
+            ("default/notes.txt", b"notes"),
+            ("gallery/2024/report-photo.jpg", b"jpg"),
+            ("gallery/holiday.jpg", b"jpg"),
+        ] {
+            let path = uploads.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let search = |query: &str| TestRequest::get().uri(&format!("/api/search?{}", query)).to_request();
+        let names = |body: &serde_json::Value| -> Vec<String> {
+            body["data"]["files"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|f| format!("{}/{}", f["module"].as_str().unwrap(), f["filename"].as_str().unwrap()))
+                .collect()
+        };
+
+        // 不区分大小写，跨模块
+        let resp = call_service(&app, search("q=REPORT")).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(names(&body), ["default/Report-2024.pdf", "default/report-cover.png", "gallery/report-photo.jpg"]);
+        assert_eq!(body["data"]["truncated"], false);
+
+        let resp = call_service(&app, search("q=report&type=image")).await;
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(names(&body), ["default/report-cover.png", "gallery/report-photo.jpg"]);
+
+        let resp = call_service(&app, search("q=report&type=image&module=gallery")).await;
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(names(&body), ["gallery/report-photo.jpg"]);
+        assert_eq!(body["data"]["files"][0]["relative_path"], "2024");
+
+        // 命中数超过上限时截断
+        let resp = call_service(&app, search("q=.")).await;
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(names(&body).len(), 3);
+        assert_eq!(body["data"]["truncated"], true);
+
+        for query in ["q=", "q=%20%20", "type=image", "q=a&module=../etc"] {
+            let resp = call_service(&app, search(query)).await;
+            assert_eq!(resp.status(), 400, "{}", query);
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use crate::middleware::auth::auth_subject;
 use crate::{models::{ApiResponse}, state::AppState};
-use crate::services::file_service;
-use crate::utils::validation_utils;
+use crate::services::{audit_service, file_service};
+use crate::utils::{rate_limit, validation_utils};
 
 #[derive(serde::Deserialize)]
 pub struct CreateSubmoduleRequest {
@@ -10,6 +11,7 @@ pub struct CreateSubmoduleRequest {
 
 pub async fn create_submodule(
     state: web::Data<AppState>,
+    req: HttpRequest,
     path: web::Path<String>,
     info: web::Json<CreateSubmoduleRequest>,
 ) -> HttpResponse {
@@ -36,7 +38,17 @@ pub async fn create_submodule(
         });
     }
 
-    match file_service::create_submodule_directory(&module, sub_name).await {
+    let result = file_service::create_submodule_directory(&module, sub_name).await;
+    audit_service::record(
+        "create_submodule",
+        &module,
+        Some(sub_name),
+        None,
+        &rate_limit::client_ip(&req),
+        auth_subject(&req).as_deref(),
+        result.as_ref().err().map(String::as_str),
+    );
+    match result {
         Ok(_) => HttpResponse::Ok().json(ApiResponse {
             success: true,
             message: format!("子模块 '{}' 在模块 '{}' 下创建成功", sub_name, module),
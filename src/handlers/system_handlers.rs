@@ -1,12 +1,21 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use crate::{models::ApiResponse, state::AppState};
 use crate::services::{system_service, cleanup_service};
 
 pub async fn health_check(state: web::Data<AppState>) -> HttpResponse {
     state.record_request();
     
-    let health_info = system_service::get_health_info(state.clone()).await;
-    
+    let (healthy, health_info) = system_service::get_health_info(state.clone()).await;
+
+    if !healthy {
+        state.record_error();
+        return HttpResponse::ServiceUnavailable().json(ApiResponse {
+            success: false,
+            message: "存储不可写或剩余空间不足".to_string(),
+            data: Some(health_info),
+        });
+    }
+
     HttpResponse::Ok().json(ApiResponse {
         success: true,
         message: "服务运行正常".to_string(),
@@ -35,6 +44,45 @@ pub async fn get_stats(state: web::Data<AppState>) -> HttpResponse {
     }
 }
 
+pub async fn get_stats_history(state: web::Data<AppState>) -> HttpResponse {
+    state.record_request();
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: "获取吞吐历史成功".to_string(),
+        data: Some(state.throughput_history.series()),
+    })
+}
+
+pub async fn get_storage(state: web::Data<AppState>) -> HttpResponse {
+    state.record_request();
+
+    match system_service::get_storage_info().await {
+        Ok(storage) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: "获取存储信息成功".to_string(),
+            data: Some(storage),
+        }),
+        Err(e) => {
+            log::error!("获取存储信息失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+pub async fn get_metrics(state: web::Data<AppState>) -> HttpResponse {
+    state.record_request();
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(state.render_metrics())
+}
+
 pub async fn cleanup_temp_files(state: web::Data<AppState>) -> HttpResponse {
     state.record_request();
     
@@ -57,4 +105,37 @@ pub async fn cleanup_temp_files(state: web::Data<AppState>) -> HttpResponse {
             })
         }
     }
-}
\ No newline at end of file
+}
+
+// 根路径: 存在前端时返回 index.html，否则按配置跳转或返回服务描述
+pub async fn root_index(req: HttpRequest) -> HttpResponse {
+    let index_path = std::path::Path::new("./frontend/index.html");
+    if let Ok(file) = actix_files::NamedFile::open_async(index_path).await {
+        return file.prefer_utf8(true).into_response(&req);
+    }
+
+    if let Some(location) = &crate::config::get_config().root_redirect {
+        return HttpResponse::Found()
+            .insert_header((actix_web::http::header::LOCATION, location.as_str()))
+            .finish();
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "service": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "endpoints": {
+            "health": "/api/health",
+            "stats": "/api/stats",
+            "metrics": "/api/metrics",
+            "modules": "/api/modules",
+            "upload": "/api/upload",
+            "upload_chunk": "/api/upload/chunk",
+            "upload_merge": "/api/upload/merge",
+            "files": "/api/files/{module}",
+            "files_stream": "/api/files/{module}/stream",
+            "manifest": "/api/modules/{module}/manifest",
+            "list": "/api/list/{module}/{folder}",
+            "uploads": "/uploads/{module}/{filename}",
+        },
+    }))
+}
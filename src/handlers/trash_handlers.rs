@@ -0,0 +1,119 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use crate::middleware::auth::auth_subject;
+use crate::{models::{ApiResponse, ErrorInfo, RestoreTrashRequest}, state::AppState};
+use crate::services::{audit_service, file_service, trash_service};
+use crate::utils::{rate_limit, validation_utils};
+
+pub async fn get_trash(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    state.record_request();
+
+    let module = path.into_inner();
+
+    if !validation_utils::is_valid_module_path(&module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    match trash_service::list_trash(&module).await {
+        Ok(items) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: format!("获取模块 '{}' 的回收站成功", module),
+            data: Some(items),
+        }),
+        Err(e) => {
+            log::error!("读取回收站失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: format!("读取回收站失败: {}", e),
+                data: None,
+            })
+        }
+    }
+}
+
+pub async fn restore_trash_item(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<RestoreTrashRequest>,
+) -> HttpResponse {
+    state.record_request();
+
+    let module = path.into_inner();
+    let request = body.into_inner();
+
+    if !validation_utils::is_valid_module_path(&module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    if request.name.trim().is_empty() || !validation_utils::is_valid_path(&request.name) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "回收站条目名称无效".to_string(),
+            data: None,
+        });
+    }
+
+    let result = trash_service::restore_from_trash(&module, &request.name).await;
+    audit_service::record(
+        "restore",
+        &module,
+        Some(&request.name),
+        None,
+        &rate_limit::client_ip(&req),
+        auth_subject(&req).as_deref(),
+        result.as_ref().err().map(String::as_str),
+    );
+    match result {
+        Ok(restored) => {
+            log::info!("已从回收站恢复: {}/{} -> {}", module, request.name, restored);
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                message: "恢复成功".to_string(),
+                data: Some(restored),
+            })
+        }
+        Err(e) if e == file_service::FILE_EXISTS_ERROR => {
+            state.record_error();
+            HttpResponse::Conflict().json(ApiResponse {
+                success: false,
+                message: format!("恢复失败: {}", e),
+                data: Some(ErrorInfo {
+                    code: "FILE_EXISTS".to_string(),
+                    detail: None,
+                }),
+            })
+        }
+        Err(e) if e == trash_service::TRASH_ITEM_NOT_FOUND => {
+            state.record_error();
+            HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+        Err(e) => {
+            log::error!("恢复回收站条目失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: format!("恢复失败: {}", e),
+                data: None,
+            })
+        }
+    }
+}
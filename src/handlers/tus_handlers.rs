@@ -0,0 +1,345 @@
+use actix_web::{web, HttpRequest, HttpResponse, HttpResponseBuilder};
+use std::collections::HashMap;
+use crate::{models::ApiResponse, state::AppState};
+use crate::services::{file_service, scan_service, system_service, tus_service};
+use crate::handlers::upload_handlers::{acquire_client_upload, track_active_upload, upload_context};
+use crate::utils::{lock_utils, rate_limit};
+
+fn header<'a>(req: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    req.headers().get(name).and_then(|v| v.to_str().ok()).map(str::trim)
+}
+
+// 所有 tus 响应都携带 Tus-Resumable
+fn tus_response(mut builder: HttpResponseBuilder) -> HttpResponseBuilder {
+    builder.insert_header(("Tus-Resumable", tus_service::TUS_VERSION));
+    builder
+}
+
+fn tus_error(builder: HttpResponseBuilder, message: String) -> HttpResponse {
+    tus_response(builder).json(ApiResponse::<()> {
+        success: false,
+        message,
+        data: None,
+    })
+}
+
+// 除 OPTIONS 外的请求必须声明支持的协议版本
+fn check_tus_resumable(req: &HttpRequest) -> Option<HttpResponse> {
+    if header(req, "tus-resumable") == Some(tus_service::TUS_VERSION) {
+        return None;
+    }
+    let mut builder = HttpResponse::PreconditionFailed();
+    builder.insert_header(("Tus-Version", tus_service::TUS_VERSION));
+    Some(tus_error(builder, format!("不支持的 Tus-Resumable 版本（支持 {}）", tus_service::TUS_VERSION)))
+}
+
+// 与其它上传接口相同的前置检查：文件数与 inode 余量、请求频率、单客户端同时上传数
+fn check_upload_limits(req: &HttpRequest) -> Result<lock_utils::ClientUploadGuard, HttpResponse> {
+    if system_service::file_count_limit_exceeded(1) {
+        return Err(tus_error(HttpResponse::InsufficientStorage(), "服务器文件数量已达上限，请清理后重试".to_string()));
+    }
+    if system_service::inode_shortage().is_some() {
+        return Err(tus_error(HttpResponse::InsufficientStorage(), "存储可用 inode 不足，请清理后重试".to_string()));
+    }
+    if let Err(retry_after) = rate_limit::check(&rate_limit::client_ip(req)) {
+        let mut builder = HttpResponse::TooManyRequests();
+        builder.insert_header(("Retry-After", retry_after.as_secs().max(1).to_string()));
+        return Err(tus_error(builder, "请求过于频繁，请稍后重试".to_string()));
+    }
+    acquire_client_upload(req).ok_or_else(|| {
+        tus_error(HttpResponse::TooManyRequests(), "当前客户端同时进行的上传过多，请稍后重试".to_string())
+    })
+}
+
+fn server_busy(e: tokio::sync::AcquireError) -> HttpResponse {
+    log::error!("获取全局并发许可失败: {}", e);
+    tus_error(HttpResponse::ServiceUnavailable(), "服务器繁忙，请稍后重试".to_string())
+}
+
+// 服务端能力查询
+pub async fn tus_options(state: web::Data<AppState>) -> HttpResponse {
+    state.record_request();
+
+    tus_response(HttpResponse::NoContent())
+        .insert_header(("Tus-Version", tus_service::TUS_VERSION))
+        .insert_header(("Tus-Extension", tus_service::TUS_EXTENSIONS))
+        .insert_header(("Tus-Max-Size", crate::config::get_config().max_file_size.to_string()))
+        .finish()
+}
+
+// 创建上传会话，Location 指向后续 HEAD/PATCH 使用的地址
+pub async fn tus_create(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    state.record_request();
+
+    if let Some(response) = check_tus_resumable(&req) {
+        state.record_error();
+        return response;
+    }
+    let _client_guard = match check_upload_limits(&req) {
+        Ok(guard) => guard,
+        Err(response) => {
+            state.record_error();
+            return response;
+        }
+    };
+    let _permit = match state.global_semaphore.acquire().await {
+        Ok(permit) => permit,
+        Err(e) => {
+            state.record_error();
+            return server_busy(e);
+        }
+    };
+
+    let Some(length) = header(&req, "upload-length").and_then(|v| v.parse::<u64>().ok()) else {
+        state.record_error();
+        return tus_error(HttpResponse::BadRequest(), "缺少有效的 Upload-Length".to_string());
+    };
+    if let Some(free_bytes) = system_service::space_shortage(length) {
+        state.record_error();
+        return tus_error(
+            HttpResponse::InsufficientStorage(),
+            format!("存储空间不足: 需要 {} bytes，可用 {} bytes", length, free_bytes),
+        );
+    }
+
+    let module = query.get("module").map(String::as_str);
+    match tus_service::create_upload(length, header(&req, "upload-metadata"), module).await {
+        Ok(upload) => tus_response(HttpResponse::Created())
+            .insert_header(("Location", format!("/api/tus/{}", upload.id)))
+            .finish(),
+        Err(e) if e == tus_service::FILE_TOO_LARGE_ERROR => {
+            state.record_error();
+            tus_error(HttpResponse::PayloadTooLarge(), e)
+        }
+        Err(e) if e == file_service::MODULE_NOT_FOUND_ERROR => {
+            state.record_error();
+            tus_error(HttpResponse::NotFound(), e)
+        }
+        Err(e) if e.starts_with(tus_service::STORAGE_ERROR) => {
+            log::error!("创建 tus 上传失败: {}", e);
+            state.record_error();
+            tus_error(HttpResponse::InternalServerError(), e)
+        }
+        Err(e) => {
+            state.record_error();
+            tus_error(HttpResponse::BadRequest(), e)
+        }
+    }
+}
+
+// 查询已接收的字节数
+pub async fn tus_head(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> HttpResponse {
+    state.record_request();
+
+    if let Some(response) = check_tus_resumable(&req) {
+        state.record_error();
+        return response;
+    }
+
+    match tus_service::get_upload(&path.into_inner()).await {
+        Ok((upload, offset)) => {
+            let mut builder = tus_response(HttpResponse::Ok());
+            builder
+                .insert_header(("Upload-Offset", offset.to_string()))
+                .insert_header(("Upload-Length", upload.length.to_string()))
+                .insert_header(("Cache-Control", "no-store"));
+            if let Some(metadata) = &upload.raw_metadata {
+                builder.insert_header(("Upload-Metadata", metadata.as_str()));
+            }
+            builder.finish()
+        }
+        Err(e) if e == tus_service::UPLOAD_NOT_FOUND_ERROR => {
+            state.record_error();
+            tus_response(HttpResponse::NotFound()).finish()
+        }
+        Err(e) => {
+            log::error!("查询 tus 上传失败: {}", e);
+            state.record_error();
+            tus_response(HttpResponse::InternalServerError()).finish()
+        }
+    }
+}
+
+// 在 Upload-Offset 处追加数据；数据达到 Upload-Length 时文件写入模块目录
+pub async fn tus_patch(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    mut payload: web::Payload,
+) -> HttpResponse {
+    state.record_request();
+
+    if let Some(response) = check_tus_resumable(&req) {
+        state.record_error();
+        return response;
+    }
+    if header(&req, "content-type") != Some("application/offset+octet-stream") {
+        state.record_error();
+        return tus_error(
+            HttpResponse::UnsupportedMediaType(),
+            "Content-Type 必须为 application/offset+octet-stream".to_string(),
+        );
+    }
+    let Some(offset) = header(&req, "upload-offset").and_then(|v| v.parse::<u64>().ok()) else {
+        state.record_error();
+        return tus_error(HttpResponse::BadRequest(), "缺少有效的 Upload-Offset".to_string());
+    };
+
+    let _client_guard = match check_upload_limits(&req) {
+        Ok(guard) => guard,
+        Err(response) => {
+            state.record_error();
+            return response;
+        }
+    };
+    let _permit = match state.global_semaphore.acquire().await {
+        Ok(permit) => permit,
+        Err(e) => {
+            state.record_error();
+            return server_busy(e);
+        }
+    };
+
+    let _active = track_active_upload();
+    let id = path.into_inner();
+    match tus_service::append_chunk(&id, offset, &mut payload, &upload_context(&req)).await {
+        Ok((offset, file_info)) => {
+            if let Some(file_info) = file_info {
+                state.record_upload_size(file_info.size);
+                state.record_upload_activity(file_info.size);
+            }
+            tus_response(HttpResponse::NoContent())
+                .insert_header(("Upload-Offset", offset.to_string()))
+                .finish()
+        }
+        Err(e) if e == tus_service::UPLOAD_NOT_FOUND_ERROR => {
+            state.record_error();
+            tus_response(HttpResponse::NotFound()).finish()
+        }
+        Err(e) if e == tus_service::OFFSET_MISMATCH_ERROR || e == file_service::FILE_EXISTS_ERROR => {
+            state.record_error();
+            tus_error(HttpResponse::Conflict(), e)
+        }
+        Err(e) if e == file_service::UNSAFE_TARGET_PATH_ERROR || e.starts_with(file_service::PATH_TOO_LONG_ERROR) => {
+            state.record_error();
+            tus_error(HttpResponse::BadRequest(), e)
+        }
+        Err(e) if e.starts_with(scan_service::INFECTED_ERROR) => {
+            state.record_error();
+            tus_error(HttpResponse::UnprocessableEntity(), e)
+        }
+        Err(e) if e.starts_with(scan_service::SCAN_FAILED_ERROR) => {
+            state.record_error();
+            tus_error(HttpResponse::ServiceUnavailable(), e)
+        }
+        Err(e) if e == tus_service::LENGTH_EXCEEDED_ERROR => {
+            state.record_error();
+            tus_error(HttpResponse::PayloadTooLarge(), e)
+        }
+        Err(e) if e.starts_with(tus_service::CONTENT_TYPE_MISMATCH_ERROR) => {
+            state.record_error();
+            tus_error(HttpResponse::UnsupportedMediaType(), e)
+        }
+        Err(e) => {
+            log::error!("tus 上传失败 {}: {}", id, e);
+            state.record_error();
+            tus_error(HttpResponse::InternalServerError(), e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    fn tus_header<B>(resp: &actix_web::dev::ServiceResponse<B>, name: &str) -> String {
+        resp.headers().get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string()
+    }
+
+    fn patch(location: &str, offset: usize, data: &[u8]) -> TestRequest {
+        TestRequest::patch()
+            .uri(location)
+            .insert_header(("Tus-Resumable", "1.0.0"))
+            .insert_header(("Upload-Offset", offset.to_string()))
+            .insert_header(("Content-Type", "application/offset+octet-stream"))
+            .set_payload(data.to_vec())
+    }
+
+    #[actix_web::test]
+    async fn completed_uploads_go_through_the_regular_write_path() {
+        let env = TestEnv::with(|c| {
+            c.compress_at_rest = true;
+            c.compress_min_size = 0;
+        });
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let content = b"tus upload content, sent in two parts".repeat(8);
+
+        // filename = notes.txt
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/tus")
+                .insert_header(("Tus-Resumable", "1.0.0"))
+                .insert_header(("Upload-Length", content.len().to_string()))
+                .insert_header(("Upload-Metadata", "filename bm90ZXMudHh0"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 201);
+        let location = tus_header(&resp, "Location");
+
+        let head = TestRequest::default()
+            .method(actix_web::http::Method::HEAD)
+            .uri(&location)
+            .insert_header(("Tus-Resumable", "1.0.0"));
+        let resp = call_service(&app, head.to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(tus_header(&resp, "Upload-Offset"), "0");
+
+        let half = content.len() / 2;
+        let resp = call_service(&app, patch(&location, 0, &content[..half]).to_request()).await;
+        assert_eq!(resp.status(), 204);
+        assert_eq!(tus_header(&resp, "Upload-Offset"), half.to_string());
+        let resp = call_service(&app, patch(&location, half, &content[half..]).to_request()).await;
+        assert_eq!(resp.status(), 204);
+        assert_eq!(tus_header(&resp, "Upload-Offset"), content.len().to_string());
+
+        // 与普通上传一样按配置压缩存储，下载时还原为原始内容
+        let module = env.uploads().join("default");
+        assert!(!module.join("notes.txt").exists());
+        assert!(module.join("notes.txt.zst").exists());
+        let resp = call_service(&app, TestRequest::get().uri("/api/download/default/notes.txt.zst").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap_or_else(|_| panic!("read download body"));
+        assert_eq!(body.as_ref(), content.as_slice());
+    }
+
+    #[actix_web::test]
+    async fn empty_uploads_are_rejected() {
+        let _env = TestEnv::with(|_| {});
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/tus")
+                .insert_header(("Tus-Resumable", "1.0.0"))
+                .insert_header(("Upload-Length", "0"))
+                .insert_header(("Upload-Metadata", "filename bm90ZXMudHh0"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 400);
+    }
+}
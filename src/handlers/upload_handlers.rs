@@ -1,20 +1,42 @@
-use actix_web::{web, HttpResponse, Error};
+use actix_web::{web, HttpRequest, HttpResponse, Error};
 use actix_multipart::Multipart;
 use std::collections::HashMap;
 use std::sync::atomic::Ordering;
 use crate::{
-    models::{ApiResponse, ChunkUploadRequest, ResumeUploadRequest}, 
+    middleware::{auth::auth_subject, request_log::RequestId},
+    models::{ApiResponse, CancelUploadRequest, ChunkStatusQuery, ChunkUploadRequest, ErrorInfo, FileInfo, RecoverUploadRequest, ResumeUploadRequest, UploadContext},
     state::{AppState, ACTIVE_UPLOADS},
-    utils::validation_utils
+    utils::{auth_utils, lock_utils, rate_limit, validation_utils}
 };
-use crate::services::upload_service;
+use actix_web::HttpMessage;
+use crate::services::{file_service, scan_service, system_service, upload_service};
 
 pub async fn upload_file(
     state: web::Data<AppState>,
+    req: HttpRequest,
     payload: Multipart,
     params: web::Query<HashMap<String, String>>,
 ) -> Result<HttpResponse, Error> {
     state.record_request();
+
+    if system_service::file_count_limit_exceeded(1) {
+        state.record_error();
+        return Ok(too_many_files_response());
+    }
+    if let Some(free_inodes) = system_service::inode_shortage() {
+        state.record_error();
+        return Ok(no_inodes_response(free_inodes));
+    }
+
+    if let Err(retry_after) = rate_limit::check(&rate_limit::client_ip(&req)) {
+        state.record_error();
+        return Ok(rate_limited_response(retry_after));
+    }
+
+    let Some(_client_guard) = acquire_client_upload(&req) else {
+        state.record_error();
+        return Ok(too_many_client_uploads_response());
+    };
     
     let _permit = state.global_semaphore.acquire().await
         .map_err(|e| {
@@ -22,21 +44,154 @@ pub async fn upload_file(
             actix_web::error::ErrorServiceUnavailable("服务器繁忙，请稍后重试")
         })?;
 
-    ACTIVE_UPLOADS.fetch_add(1, Ordering::Relaxed);
-    
-    let result = upload_service::handle_file_upload(state.clone(), payload, params).await;
+    let _active = track_active_upload();
     
-    ACTIVE_UPLOADS.fetch_sub(1, Ordering::Relaxed);
+    let result = with_upload_deadline(
+        upload_service::handle_file_upload(state.clone(), payload, params, upload_context(&req))
+    ).await;
     
-    result
+    result.unwrap_or_else(|| {
+        state.record_error();
+        Ok(upload_deadline_response())
+    })
+}
+
+// 以原始请求体上传单个文件（非 multipart），便于 curl --upload-file 等命令行客户端使用
+pub async fn upload_raw(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    payload: web::Payload,
+    path: web::Path<(String, String)>,
+    params: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse, Error> {
+    state.record_request();
+
+    if system_service::file_count_limit_exceeded(1) {
+        state.record_error();
+        return Ok(too_many_files_response());
+    }
+    if let Some(free_inodes) = system_service::inode_shortage() {
+        state.record_error();
+        return Ok(no_inodes_response(free_inodes));
+    }
+
+    if let Err(retry_after) = rate_limit::check(&rate_limit::client_ip(&req)) {
+        state.record_error();
+        return Ok(rate_limited_response(retry_after));
+    }
+
+    let Some(_client_guard) = acquire_client_upload(&req) else {
+        state.record_error();
+        return Ok(too_many_client_uploads_response());
+    };
+
+    let _permit = state.global_semaphore.acquire().await
+        .map_err(|e| {
+            log::error!("获取全局并发许可失败: {}", e);
+            actix_web::error::ErrorServiceUnavailable("服务器繁忙，请稍后重试")
+        })?;
+
+    let _active = track_active_upload();
+
+    let (module, file_path) = path.into_inner();
+    let result = with_upload_deadline(upload_service::handle_raw_upload(
+        state.clone(),
+        payload,
+        &module,
+        &file_path,
+        params,
+        upload_context(&req),
+    ))
+    .await;
+
+    result.unwrap_or_else(|| {
+        state.record_error();
+        Ok(upload_deadline_response())
+    })
+}
+
+pub async fn replace_file(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    payload: web::Payload,
+    path: web::Path<(String, String)>,
+    params: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse, Error> {
+    state.record_request();
+
+    let (module, filename) = path.into_inner();
+    if !validation_utils::is_valid_module_path(&module) || !validation_utils::is_valid_filename(&filename) {
+        state.record_error();
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "文件路径包含非法字符".to_string(),
+            data: None,
+        }));
+    }
+    // create=false 时只允许替换已存在的文件
+    let create = params.get("create").map(|v| v != "false").unwrap_or(true);
+
+    if let Some(free_inodes) = system_service::inode_shortage() {
+        state.record_error();
+        return Ok(no_inodes_response(free_inodes));
+    }
+
+    if let Err(retry_after) = rate_limit::check(&rate_limit::client_ip(&req)) {
+        state.record_error();
+        return Ok(rate_limited_response(retry_after));
+    }
+
+    let Some(_client_guard) = acquire_client_upload(&req) else {
+        state.record_error();
+        return Ok(too_many_client_uploads_response());
+    };
+
+    let _permit = state.global_semaphore.acquire().await
+        .map_err(|e| {
+            log::error!("获取全局并发许可失败: {}", e);
+            actix_web::error::ErrorServiceUnavailable("服务器繁忙，请稍后重试")
+        })?;
+
+    let _active = track_active_upload();
+
+    let result = with_upload_deadline(upload_service::handle_file_replace(
+        state.clone(),
+        payload,
+        &module,
+        &filename,
+        create,
+        upload_context(&req),
+    ))
+    .await;
+
+    result.unwrap_or_else(|| {
+        state.record_error();
+        Ok(upload_deadline_response())
+    })
 }
 
 pub async fn upload_chunk(
     state: web::Data<AppState>,
+    req: HttpRequest,
     payload: Multipart,
     params: web::Query<HashMap<String, String>>,
 ) -> Result<HttpResponse, Error> {
     state.record_request();
+
+    if let Some(free_inodes) = system_service::inode_shortage() {
+        state.record_error();
+        return Ok(no_inodes_response(free_inodes));
+    }
+
+    if let Err(retry_after) = rate_limit::check(&rate_limit::client_ip(&req)) {
+        state.record_error();
+        return Ok(rate_limited_response(retry_after));
+    }
+
+    let Some(_client_guard) = acquire_client_upload(&req) else {
+        state.record_error();
+        return Ok(too_many_client_uploads_response());
+    };
     
     let _permit = state.global_semaphore.acquire().await
         .map_err(|e| {
@@ -44,20 +199,28 @@ pub async fn upload_chunk(
             actix_web::error::ErrorServiceUnavailable("服务器繁忙，请稍后重试")
         })?;
 
-    ACTIVE_UPLOADS.fetch_add(1, Ordering::Relaxed);
+    let _active = track_active_upload();
     
-    let result = upload_service::handle_chunk_upload(state.clone(), payload, params).await;
+    let result = with_upload_deadline(
+        upload_service::handle_chunk_upload(state.clone(), payload, params)
+    ).await;
     
-    ACTIVE_UPLOADS.fetch_sub(1, Ordering::Relaxed);
-    
-    result
+    result.unwrap_or_else(|| {
+        state.record_error();
+        Ok(upload_deadline_response())
+    })
 }
 
 pub async fn merge_chunks(
     state: web::Data<AppState>,
+    req: HttpRequest,
     info: web::Json<ChunkUploadRequest>,
 ) -> HttpResponse {
     state.record_request();
+    if let Err(retry_after) = rate_limit::check(&rate_limit::client_ip(&req)) {
+        state.record_error();
+        return rate_limited_response(retry_after);
+    }
     // 限制并发合并，优先使用专用的 MERGE_SEMAPHORE，若未初始化则退回到全局信号量
     if let Some(sem) = crate::utils::lock_utils::get_merge_semaphore() {
         let _permit = sem.acquire().await
@@ -72,7 +235,16 @@ pub async fn merge_chunks(
                 actix_web::error::ErrorServiceUnavailable("服务器繁忙，请稍后重试")
             });
     }
-    ACTIVE_UPLOADS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    if system_service::file_count_limit_exceeded(1) {
+        state.record_error();
+        return too_many_files_response();
+    }
+    if let Some(free_inodes) = system_service::inode_shortage() {
+        state.record_error();
+        return no_inodes_response(free_inodes);
+    }
+
+    let _active = track_active_upload();
     
     if !validation_utils::is_valid_filename(&info.filename) {
         state.record_error();
@@ -83,16 +255,496 @@ pub async fn merge_chunks(
         });
     }
 
-    match upload_service::merge_chunk_files(state.clone(), info.into_inner()).await {
+    if !upload_service::is_valid_relative_path(info.relative_path.as_deref()) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: upload_service::INVALID_RELATIVE_PATH_ERROR.to_string(),
+            data: None,
+        });
+    }
+
+    if let Err(e) = upload_service::check_total_chunks(info.total_chunks)
+        .and_then(|_| upload_service::conflict_policy_param(info.on_conflict.as_deref()))
+        .and_then(|_| upload_service::hash_algorithm_param(info.hash_algorithm.as_deref()))
+        .and_then(|_| upload_service::upload_session_id(info.upload_id.as_deref(), info.file_hash.as_deref()))
+    {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: e,
+            data: None,
+        });
+    }
+
+    let mut info = info.into_inner();
+    let submodule = info.submodule.as_deref()
+        .map(|s| s.trim_matches('/'))
+        .filter(|s| !s.is_empty());
+    info.module = match upload_service::resolve_target_module(&info.module, submodule) {
+        Ok(module) => module,
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
+    if let Err(e) = upload_service::check_module_exists(&info.module).await {
+        state.record_error();
+        return HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: e,
+            data: None,
+        });
+    }
+
+    let result = match with_upload_deadline(upload_service::merge_chunk_files(state.clone(), info, upload_context(&req))).await {
+        Some(result) => result,
+        None => {
+            state.record_error();
+            return upload_deadline_response();
+        }
+    };
+
+    merge_result_response(&state, result)
+}
+
+fn merge_result_response(state: &AppState, result: Result<FileInfo, String>) -> HttpResponse {
+    match result {
         Ok(file_info) => HttpResponse::Ok().json(ApiResponse {
             success: true,
             message: "文件合并成功".to_string(),
             data: Some(file_info),
         }),
+        Err(e) if e == file_service::FILE_EXISTS_ERROR => {
+            log::warn!("合并目标文件已存在，按 fail 策略拒绝");
+            state.record_error();
+            HttpResponse::Conflict().json(ApiResponse {
+                success: false,
+                message: e,
+                data: Some(ErrorInfo {
+                    code: "FILE_EXISTS".to_string(),
+                    detail: None,
+                }),
+            })
+        }
+        Err(e) if e.starts_with(upload_service::HASH_MISMATCH_ERROR) => {
+            log::warn!("{}", e);
+            state.record_error();
+            HttpResponse::UnprocessableEntity().json(ApiResponse {
+                success: false,
+                message: e,
+                data: Some(ErrorInfo {
+                    code: "HASH_MISMATCH".to_string(),
+                    detail: None,
+                }),
+            })
+        }
+        Err(e) if e.starts_with(upload_service::SIZE_MISMATCH_ERROR) => {
+            log::warn!("{}", e);
+            state.record_error();
+            HttpResponse::UnprocessableEntity().json(ApiResponse {
+                success: false,
+                message: e,
+                data: Some(ErrorInfo {
+                    code: "SIZE_MISMATCH".to_string(),
+                    detail: None,
+                }),
+            })
+        }
+        Err(e) if e.starts_with(upload_service::PART_HASH_MISMATCH_ERROR) => {
+            log::warn!("{}", e);
+            state.record_error();
+            HttpResponse::UnprocessableEntity().json(ApiResponse {
+                success: false,
+                message: e,
+                data: Some(ErrorInfo {
+                    code: "PART_HASH_MISMATCH".to_string(),
+                    detail: None,
+                }),
+            })
+        }
+        Err(e) if e.starts_with(scan_service::INFECTED_ERROR) || e.starts_with(scan_service::SCAN_FAILED_ERROR) => {
+            state.record_error();
+            upload_service::scan_rejected_response(e, None)
+        }
+        Err(e)
+            if e == upload_service::INVALID_RELATIVE_PATH_ERROR
+                || e == file_service::UNSAFE_TARGET_PATH_ERROR
+                || e.starts_with(file_service::PATH_TOO_LONG_ERROR) =>
+        {
+            state.record_error();
+            HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+        Err(e) if e.starts_with(upload_service::CHUNK_SET_MISMATCH_ERROR) => {
+            state.record_error();
+            HttpResponse::Conflict().json(ApiResponse {
+                success: false,
+                message: e,
+                data: Some(ErrorInfo {
+                    code: "CHUNK_SET_MISMATCH".to_string(),
+                    detail: None,
+                }),
+            })
+        }
         Err(e) => {
             log::error!("合并文件失败: {}", e);
             state.record_error();
-            ACTIVE_UPLOADS.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+// 合并请求丢失（如服务重启）后，用保留在临时目录中的完整分片重新执行合并
+pub async fn recover_upload(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    info: web::Json<RecoverUploadRequest>,
+) -> HttpResponse {
+    state.record_request();
+
+    let info = info.into_inner();
+    let relative_path = info.relative_path.as_deref()
+        .map(|p| p.trim_matches('/'))
+        .filter(|p| !p.is_empty())
+        .map(str::to_string);
+
+    if !validation_utils::is_valid_module_path(&info.module)
+        || !validation_utils::is_valid_filename(&info.filename)
+        || relative_path.as_deref().is_some_and(|p| !validation_utils::is_valid_path(p))
+    {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块、文件名或相对路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+    if let Err(e) = upload_service::check_total_chunks(info.total_chunks) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: e,
+            data: None,
+        });
+    }
+    let session_id = match upload_service::hash_algorithm_param(info.hash_algorithm.as_deref())
+        .and_then(|_| upload_service::upload_session_id(info.upload_id.as_deref(), info.file_hash.as_deref()))
+    {
+        Ok(session_id) => session_id,
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
+
+    if let Some(expected) = upload_service::recorded_total_chunks(
+        &info.module,
+        session_id.as_deref(),
+        &info.filename,
+        &relative_path,
+    ).await {
+        if expected != info.total_chunks {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse {
+                success: false,
+                message: format!("分块总数与上传会话不一致: 会话记录 {}, 本次声明 {}", expected, info.total_chunks),
+                data: Some(ErrorInfo {
+                    code: "TOTAL_CHUNKS_MISMATCH".to_string(),
+                    detail: Some(serde_json::json!({
+                        "expected": expected,
+                        "declared": info.total_chunks,
+                    })),
+                }),
+            });
+        }
+    }
+
+    let missing = upload_service::find_missing_chunks(
+        &info.module,
+        session_id.as_deref(),
+        &info.filename,
+        &relative_path,
+        info.total_chunks,
+    ).await;
+    if missing.len() == info.total_chunks {
+        state.record_error();
+        return HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: "未找到可恢复的分片".to_string(),
+            data: None,
+        });
+    }
+    if !missing.is_empty() {
+        state.record_error();
+        return HttpResponse::Conflict().json(ApiResponse {
+            success: false,
+            message: format!("分片不完整，缺少 {} 个分块", missing.len()),
+            data: Some(ErrorInfo {
+                code: "INCOMPLETE_PARTS".to_string(),
+                detail: Some(serde_json::json!({ "missing_chunks": missing })),
+            }),
+        });
+    }
+
+    if system_service::file_count_limit_exceeded(1) {
+        state.record_error();
+        return too_many_files_response();
+    }
+    if let Some(free_inodes) = system_service::inode_shortage() {
+        state.record_error();
+        return no_inodes_response(free_inodes);
+    }
+
+    let _active = track_active_upload();
+    log::info!("恢复合并: {}/{} ({} 个分块)", info.module, info.filename, info.total_chunks);
+
+    let merge_request = ChunkUploadRequest {
+        chunk_number: 0,
+        total_chunks: info.total_chunks,
+        filename: info.filename,
+        module: info.module,
+        chunk_size: 0,
+        relative_path,
+        file_hash: info.file_hash,
+        chunk_hash: None,
+        submodule: None,
+        on_conflict: None,
+        hash_algorithm: info.hash_algorithm,
+        upload_id: info.upload_id,
+        uniform_chunks: false,
+        total_size: None,
+    };
+
+    let result = match with_upload_deadline(upload_service::merge_chunk_files(state.clone(), merge_request, upload_context(&req))).await {
+        Some(result) => result,
+        None => {
+            state.record_error();
+            return upload_deadline_response();
+        }
+    };
+
+    merge_result_response(&state, result)
+}
+
+pub async fn get_recoverable_uploads(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    state.record_request();
+
+    let module = path.into_inner();
+
+    if !validation_utils::is_valid_module_path(&module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    match upload_service::list_recoverable_uploads(&module).await {
+        Ok(uploads) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: format!("发现 {} 个可恢复的上传会话", uploads.len()),
+            data: Some(uploads),
+        }),
+        Err(e) => {
+            log::error!("查询可恢复上传失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+// 按配置的 max_upload_duration 限制请求总处理时间，超时返回 None（future 被丢弃，部分文件由其自身清理）
+async fn with_upload_deadline<F: std::future::Future>(future: F) -> Option<F::Output> {
+    match crate::config::get_config().max_upload_duration {
+        Some(limit) => match tokio::time::timeout(limit, future).await {
+            Ok(output) => Some(output),
+            Err(_) => {
+                log::warn!("上传处理超过时限 {}秒，已中止", limit.as_secs());
+                None
+            }
+        },
+        None => Some(future.await),
+    }
+}
+
+fn upload_deadline_response() -> HttpResponse {
+    HttpResponse::RequestTimeout().json(ApiResponse {
+        success: false,
+        message: "上传处理超过时限，已中止".to_string(),
+        data: Some(ErrorInfo {
+            code: "UPLOAD_DEADLINE_EXCEEDED".to_string(),
+            detail: Some(serde_json::json!({
+                "max_upload_duration_secs": crate::config::get_config()
+                    .max_upload_duration
+                    .map(|d| d.as_secs()),
+            })),
+        }),
+    })
+}
+
+// 登记一个进行中的上传，返回的守卫在 drop 时（包括提前返回与 panic 展开）递减计数
+pub(crate) fn track_active_upload() -> impl Drop {
+    ACTIVE_UPLOADS.fetch_add(1, Ordering::Relaxed);
+    scopeguard::guard((), |_| {
+        ACTIVE_UPLOADS.fetch_sub(1, Ordering::Relaxed);
+    })
+}
+
+// 提取上传来源信息；上传者名称仅在请求携带有效凭据时采信
+pub(crate) fn upload_context(req: &HttpRequest) -> UploadContext {
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    UploadContext {
+        // JWT 认证的调用方优先，其次为携带有效凭据时声明的上传者
+        uploader: auth_subject(req).or_else(|| header("x-uploader").filter(|_| auth_utils::is_authenticated(req))),
+        source_ip: req.peer_addr().map(|addr| addr.ip().to_string()),
+        user_agent: header("user-agent"),
+        request_id: req.extensions().get::<RequestId>().map(|id| id.0.clone()),
+        max_bps: rate_limit::upload_bps_limit(
+            web::Query::<HashMap<String, String>>::from_query(req.query_string())
+                .ok()
+                .and_then(|query| query.get("max_bps").cloned())
+                .as_deref(),
+        ),
+    }
+}
+
+// 按客户端 IP 登记在途上传
+pub(crate) fn acquire_client_upload(req: &HttpRequest) -> Option<lock_utils::ClientUploadGuard> {
+    lock_utils::try_acquire_client_upload(&rate_limit::client_ip(req))
+}
+
+fn rate_limited_response(retry_after: std::time::Duration) -> HttpResponse {
+    let retry_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    HttpResponse::TooManyRequests()
+        .insert_header(("Retry-After", retry_secs.to_string()))
+        .json(ApiResponse {
+            success: false,
+            message: format!("请求过于频繁，请在 {} 秒后重试", retry_secs),
+            data: Some(ErrorInfo {
+                code: "RATE_LIMITED".to_string(),
+                detail: Some(serde_json::json!({
+                    "retry_after": retry_secs,
+                    "rate_limit_per_minute": crate::config::get_config().rate_limit_per_minute,
+                })),
+            }),
+        })
+}
+
+fn too_many_client_uploads_response() -> HttpResponse {
+    HttpResponse::TooManyRequests().json(ApiResponse {
+        success: false,
+        message: "当前客户端同时进行的上传过多，请稍后重试".to_string(),
+        data: Some(ErrorInfo {
+            code: "TOO_MANY_CONCURRENT_UPLOADS".to_string(),
+            detail: Some(serde_json::json!({
+                "max_concurrent_uploads_per_ip": crate::config::get_config().max_concurrent_uploads_per_ip,
+            })),
+        }),
+    })
+}
+
+fn no_inodes_response(free_inodes: u64) -> HttpResponse {
+    HttpResponse::InsufficientStorage().json(ApiResponse {
+        success: false,
+        message: "存储可用 inode 不足，请清理后重试".to_string(),
+        data: Some(ErrorInfo {
+            code: "NO_INODES".to_string(),
+            detail: Some(serde_json::json!({
+                "free_inodes": free_inodes,
+                "min_free_inodes": crate::config::get_config().min_free_inodes,
+            })),
+        }),
+    })
+}
+
+fn too_many_files_response() -> HttpResponse {
+    HttpResponse::InsufficientStorage().json(ApiResponse {
+        success: false,
+        message: "服务器文件数量已达上限，请清理后重试".to_string(),
+        data: Some(ErrorInfo {
+            code: "TOO_MANY_FILES".to_string(),
+            detail: None,
+        }),
+    })
+}
+
+pub async fn cancel_upload(
+    state: web::Data<AppState>,
+    info: web::Json<CancelUploadRequest>,
+) -> HttpResponse {
+    state.record_request();
+
+    let info = info.into_inner();
+    let relative_path = info.relative_path.as_deref()
+        .map(|p| p.trim_matches('/'))
+        .filter(|p| !p.is_empty())
+        .map(str::to_string);
+
+    if !validation_utils::is_valid_module_path(&info.module)
+        || !validation_utils::is_valid_filename(&info.filename)
+        || info.filename.contains('/')
+        || relative_path.as_deref().is_some_and(|p| !validation_utils::is_valid_path(p))
+    {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块、文件名或相对路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    let session_id = match upload_service::upload_session_id(info.upload_id.as_deref(), info.file_hash.as_deref()) {
+        Ok(session_id) => session_id,
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
+
+    match upload_service::cancel_upload(&info.module, session_id.as_deref(), &info.filename, &relative_path).await {
+        Ok(result) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: format!("已取消上传，删除 {} 个分片", result.removed_parts),
+            data: Some(result),
+        }),
+        Err(e) => {
+            log::error!("取消上传失败: {}", e);
+            state.record_error();
             HttpResponse::InternalServerError().json(ApiResponse::<()> {
                 success: false,
                 message: e,
@@ -124,6 +776,96 @@ pub async fn get_upload_progress(
     }
 }
 
+// 以 SSE（text/event-stream）推送上传进度，替代轮询 /upload/progress
+pub async fn stream_upload_progress(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    state.record_request();
+
+    let (module, filename) = path.into_inner();
+    if !validation_utils::is_valid_module_path(&module) || !validation_utils::is_valid_filename(&filename) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块或文件名包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    let stream = upload_service::progress_event_stream(&module, &filename).await;
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(stream)
+}
+
+// 返回分块上传会话中已上传与缺失的分片编号，客户端据此只补传缺失的分块
+pub async fn get_chunk_status(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    query: web::Query<ChunkStatusQuery>,
+) -> HttpResponse {
+    state.record_request();
+
+    let (module, filename) = path.into_inner();
+    let query = query.into_inner();
+    let relative_path = query.relative_path.as_deref()
+        .map(|p| p.trim_matches('/'))
+        .filter(|p| !p.is_empty())
+        .map(str::to_string);
+
+    if !validation_utils::is_valid_module_path(&module)
+        || !validation_utils::is_valid_filename(&filename)
+        || relative_path.as_deref().is_some_and(|p| !validation_utils::is_valid_path(p))
+    {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块、文件名或相对路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+    if let Err(e) = upload_service::check_total_chunks(query.total_chunks) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: e,
+            data: None,
+        });
+    }
+
+    let session_id = match upload_service::upload_session_id(query.upload_id.as_deref(), query.file_hash.as_deref()) {
+        Ok(session_id) => session_id,
+        Err(e) => {
+            state.record_error();
+            return HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            });
+        }
+    };
+
+    match upload_service::chunk_upload_status(&module, session_id.as_deref(), &filename, &relative_path, query.total_chunks).await {
+        Ok(status) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: format!("已上传 {} 个分块，缺少 {} 个", status.uploaded.len(), status.missing.len()),
+            data: Some(status),
+        }),
+        Err(e) => {
+            log::error!("查询分块状态失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
 pub async fn check_file_exists(
     state: web::Data<AppState>,
     info: web::Json<ResumeUploadRequest>,
@@ -136,6 +878,14 @@ pub async fn check_file_exists(
             message: if result.exists { "文件已存在" } else { "文件不存在" }.to_string(),
             data: Some(result),
         }),
+        Err(e) if e == upload_service::INVALID_CHUNK_SIZE_ERROR => {
+            state.record_error();
+            HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
         Err(e) => {
             log::error!("检查文件存在失败: {}", e);
             state.record_error();
@@ -146,4 +896,228 @@ pub async fn check_file_exists(
             })
         }
     }
-}
\ No newline at end of file
+}
+
+pub async fn get_inconsistencies(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    state.record_request();
+
+    let module = path.into_inner();
+
+    if !validation_utils::is_valid_module_path(&module) {
+        state.record_error();
+        return HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: "模块路径包含非法字符".to_string(),
+            data: None,
+        });
+    }
+
+    let cleanup = query.get("cleanup").map(|v| v == "true").unwrap_or(false);
+
+    match upload_service::find_inconsistencies(&module, cleanup).await {
+        Ok(report) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: format!(
+                "发现 {} 组孤立分片, {} 组残留分片",
+                report.orphaned.len(),
+                report.stale.len()
+            ),
+            data: Some(report),
+        }),
+        Err(e) => {
+            log::error!("检查模块一致性失败: {}", e);
+            state.record_error();
+            HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: e,
+                data: None,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::time::{Duration, Instant};
+    use actix_web::dev::Payload;
+    use actix_web::error::PayloadError;
+    use actix_web::test::{call_service, read_body_json, TestRequest};
+    use actix_web::web::Bytes;
+    use futures_util::{Stream, StreamExt};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    #[actix_web::test]
+    async fn slow_body_is_aborted_with_408() {
+        let _env = TestEnv::with(|c| c.max_upload_duration = Some(Duration::from_secs(1)));
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        // 先发送一小段数据，之后请求体不再有数据也不结束
+        let body: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> = Box::pin(
+            futures_util::stream::once(async { Ok(Bytes::from_static(b"partial")) }).chain(futures_util::stream::pending()),
+        );
+        let (req, _) = TestRequest::put()
+            .uri("/api/upload/default/slow.txt")
+            .to_request()
+            .replace_payload(Payload::from(body));
+
+        let started = Instant::now();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 408);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["data"]["code"], "UPLOAD_DEADLINE_EXCEEDED");
+    }
+
+    // 1x1 像素的 PNG
+    const PIXEL_PNG: [u8; 70] = [
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52, 0x00, 0x00,
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4, 0x89, 0x00, 0x00, 0x00,
+        0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x64, 0x60, 0xf8, 0x5f, 0x0f, 0x00, 0x02, 0x87, 0x01, 0x80,
+        0xeb, 0x47, 0xba, 0x92, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[actix_web::test]
+    async fn raw_put_matches_multipart_upload() {
+        let env = TestEnv::with(|c| c.hash_on_upload = true);
+        std::fs::create_dir_all(env.uploads().join("raw")).unwrap();
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "pixel.png", &PIXEL_PNG).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        let mut multipart = body["data"][0].clone();
+
+        let resp = call_service(&app, TestRequest::put().uri("/api/upload/raw/pixel.png").set_payload(PIXEL_PNG.to_vec()).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        let mut raw = body["data"][0].clone();
+
+        assert_eq!(raw["url"], "/uploads/raw/pixel.png");
+        assert_eq!(raw["width"], 1);
+        assert_eq!(raw["file_type"], "image");
+        assert!(raw["file_hash"].is_string());
+        // 除模块、URL 与上传时间外完全一致
+        for info in [&mut multipart, &mut raw] {
+            let info = info.as_object_mut().unwrap();
+            for key in ["module", "url", "upload_time"] {
+                info.remove(key);
+            }
+        }
+        assert_eq!(raw, multipart);
+        assert_eq!(std::fs::read(env.uploads().join("raw/pixel.png")).unwrap(), PIXEL_PNG);
+    }
+
+    #[actix_web::test]
+    async fn active_upload_count_survives_panics_and_early_returns() {
+        use std::sync::atomic::Ordering;
+        use crate::state::ACTIVE_UPLOADS;
+        let _env = TestEnv::with(|_| {});
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let before = ACTIVE_UPLOADS.load(Ordering::Relaxed);
+
+        // 处理过程中 panic：守卫在展开时递减计数
+        let task = tokio::spawn(async move {
+            let _active = super::track_active_upload();
+            assert_eq!(ACTIVE_UPLOADS.load(Ordering::Relaxed), before + 1);
+            tokio::task::yield_now().await;
+            panic!("handler panicked mid-upload");
+        });
+        assert!(task.await.unwrap_err().is_panic());
+        assert_eq!(ACTIVE_UPLOADS.load(Ordering::Relaxed), before);
+
+        // 提前返回错误的上传、分块与合并请求
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=..", "early.txt", b"x").to_request()).await;
+        assert_eq!(resp.status(), 400);
+        let resp = call_service(
+            &app,
+            test_utils::upload_request("/api/upload/chunk?module=default&filename=early.txt&chunk_number=5&total_chunks=1&upload_id=early", "early.txt", b"x").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 400);
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/upload/merge")
+                .set_json(serde_json::json!({ "chunk_number": 0, "total_chunks": 1, "filename": "early.txt", "module": "default", "chunk_size": 1, "upload_id": "never" }))
+                .to_request(),
+        )
+        .await;
+        assert!(resp.status().is_client_error(), "{}", resp.status());
+        assert_eq!(ACTIVE_UPLOADS.load(Ordering::Relaxed), before);
+    }
+
+    #[actix_web::test]
+    async fn oversized_multipart_files_are_rejected_while_streaming() {
+        let env = TestEnv::with(|c| c.max_file_size = 1000);
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let module = env.uploads().join("default");
+
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "limit.txt", &[b'a'; 1000]).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "over.txt", &[b'a'; 1001]).to_request()).await;
+        assert_eq!(resp.status(), 413);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["data"]["code"], "FILE_TOO_LARGE");
+
+        // 超出限制的数据到达后立即中止，不等待请求体结束
+        let (content_type, body) = test_utils::multipart(&[("file", Some("endless.txt"), &[b'a'; 2000])]);
+        let head = Bytes::from(body[..body.len() - 64].to_vec());
+        let body: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> =
+            Box::pin(futures_util::stream::once(async move { Ok(head) }).chain(futures_util::stream::pending()));
+        let (req, _) = TestRequest::post()
+            .uri("/api/upload?module=default")
+            .insert_header(("Content-Type", content_type))
+            .to_request()
+            .replace_payload(Payload::from(body));
+        let resp = tokio::time::timeout(Duration::from_secs(5), call_service(&app, req))
+            .await
+            .expect("oversized upload should be rejected before the body ends");
+        assert_eq!(resp.status(), 413);
+
+        // 只留下限制内的文件，部分写入的文件已清理
+        let mut stored: Vec<String> = std::fs::read_dir(&module).unwrap().flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect();
+        stored.sort();
+        assert_eq!(stored, [".limit.txt.meta.json", "limit.txt"]);
+    }
+
+    #[actix_web::test]
+    async fn files_are_replaced_in_place() {
+        let env = TestEnv::with(|_| {});
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let docs = env.uploads().join("default/docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("notes.txt"), b"first version").unwrap();
+        let replace = |uri: &str, content: &'static str| TestRequest::put().uri(uri).set_payload(content).to_request();
+
+        // 覆盖已存在的文件：名称与 URL 不变，不生成 notes_1.txt
+        let resp = call_service(&app, replace("/api/file/default/docs/notes.txt?create=false", "second")).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!((body["data"]["filename"].as_str(), body["data"]["size"].as_u64()), (Some("notes.txt"), Some(6)));
+        assert!(body["data"]["url"].as_str().unwrap().ends_with("/default/docs/notes.txt"), "{}", body);
+        assert_eq!(std::fs::read(docs.join("notes.txt")).unwrap(), b"second");
+        let mut names: Vec<String> = std::fs::read_dir(&docs).unwrap().flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect();
+        names.sort();
+        assert_eq!(names, [".notes.txt.meta.json", "notes.txt"]);
+
+        // create=false 时文件不存在返回 404，且不会创建
+        let resp = call_service(&app, replace("/api/file/default/docs/missing.txt?create=false", "new")).await;
+        assert_eq!(resp.status(), 404);
+        assert!(!docs.join("missing.txt").exists());
+
+        // 默认允许创建
+        let resp = call_service(&app, replace("/api/file/default/docs/missing.txt", "new")).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(std::fs::read(docs.join("missing.txt")).unwrap(), b"new");
+    }
+}
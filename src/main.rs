@@ -3,9 +3,12 @@ mod models;
 
 mod state;
 mod handlers;
+mod middleware;
 mod utils;
 pub mod services;
-use actix_web::{middleware::Logger, web, App, HttpServer};
+#[cfg(test)]
+mod test_utils;
+use actix_web::{middleware::{from_fn, Logger}, web, App, HttpServer};
 use std::io::Result;
 
 #[actix_web::main]
@@ -14,7 +17,8 @@ async fn main() -> Result<()> {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
     // 初始化配置
-    let config = config::ServerConfig::new();
+    let config = config::init_config(config::ServerConfig::new());
+    config.validate()?;
     config.init_directories().await?;
 
     // 初始化全局并发控制
@@ -25,6 +29,23 @@ async fn main() -> Result<()> {
     // 创建应用状态
     let app_state = state::AppState::new(config.global_max_concurrent);
 
+    // 初始化文件总数缓存
+    if let Err(e) = services::system_service::refresh_file_count().await {
+        log::warn!("初始化文件总数缓存失败: {}", e);
+    }
+
+    // 检查图片自动转换所需的外部工具
+    services::image_service::check_converter_available().await;
+
+    // 启动审计日志写入任务
+    services::audit_service::init_audit_log();
+
+    // 恢复重启前的上传进度记录
+    services::upload_service::init_upload_progress();
+
+    // 吞吐历史的载入与定期持久化
+    tokio::spawn(services::system_service::persist_throughput_history(app_state.clone()));
+
     // 启动后台清理任务
     tokio::spawn(services::cleanup_service::start_background_cleanup());
 
@@ -32,19 +53,42 @@ async fn main() -> Result<()> {
     config.log_config();
     println!("服务器运行在：http://{}:{}", config.address, config.port);
 
+    let shutdown_state = app_state.clone();
     let server = HttpServer::new(move || {
+        let logger = match &config.access_log_format {
+            Some(format) => Logger::new(format),
+            None => Logger::default(),
+        };
+
         App::new()
             .app_data(web::Data::new(app_state.clone()))
-            .wrap(Logger::default())
-            .app_data(web::PayloadConfig::new(config.max_file_size as usize))
+            .wrap(from_fn(middleware::read_only::read_only_guard))
+            .wrap(from_fn(middleware::origin_check::origin_check))
+            .wrap(from_fn(middleware::auth::jwt_auth))
+            .wrap(from_fn(middleware::cors::cors))
+            .wrap(logger)
+            .wrap(from_fn(middleware::request_log::request_context))
             .configure(handlers::configure_routes)
     })
     .bind(format!("{}:{}", config.address, config.port))?
+    // 由下面的关闭流程处理信号，先等待进行中的上传再停止
+    .disable_signals()
+    .shutdown_timeout(config.shutdown_timeout.as_secs())
     .run();
 
     // 设置优雅关闭
     let server_handle = server.handle();
     let shutdown_signal = async {
+        #[cfg(unix)]
+        {
+            let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("Failed to install SIGTERM handler");
+            tokio::select! {
+                result = tokio::signal::ctrl_c() => result.expect("Failed to install CTRL+C handler"),
+                _ = sigterm.recv() => {}
+            }
+        }
+        #[cfg(not(unix))]
         tokio::signal::ctrl_c().await.expect("Failed to install CTRL+C handler");
         log::info!("接收到关闭信号");
     };
@@ -55,8 +99,13 @@ async fn main() -> Result<()> {
         }
         _ = shutdown_signal => {
             log::info!("开始优雅关闭流程");
+            // 先停止接受新连接，等待进行中的上传写完，再停止工作线程
+            server_handle.pause().await;
+            services::cleanup_service::wait_for_active_uploads(config.shutdown_timeout).await;
             server_handle.stop(true).await;
             services::cleanup_service::graceful_shutdown().await;
+            services::upload_service::flush_upload_progress().await;
+            services::system_service::save_throughput_history(&shutdown_state).await;
             log::info!("优雅关闭完成");
         }
     }
@@ -0,0 +1,289 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error, HttpMessage, HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+use crate::{config, models::ApiResponse, utils::{auth_utils, hash_utils}};
+use super::origin_check::is_mutating;
+
+// 通过认证的调用方（JWT 的 sub），保存在请求扩展中供处理器记录操作者
+#[derive(Debug, Clone)]
+pub struct AuthSubject(pub String);
+
+// 请求的认证调用方；未启用 JWT 认证时为 None
+pub fn auth_subject(req: &HttpRequest) -> Option<String> {
+    req.extensions().get::<AuthSubject>().map(|subject| subject.0.clone()).filter(|subject| !subject.is_empty())
+}
+
+// 请求是否携带有效凭据（管理令牌、API Key 或有效的 Bearer JWT）。
+// 不需要认证的读请求不经过 jwt_auth 校验，这里就地校验 JWT
+pub fn is_authenticated_request(req: &HttpRequest) -> bool {
+    if auth_utils::is_authenticated(req) || req.extensions().get::<AuthSubject>().is_some() {
+        return true;
+    }
+    let Some(secret) = &config::get_config().jwt_secret else {
+        return false;
+    };
+    req.headers()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|token| verify_jwt(token.trim(), secret).is_ok())
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+}
+
+#[derive(Deserialize)]
+struct JwtClaims {
+    sub: Option<String>,
+    exp: Option<i64>,
+    nbf: Option<i64>,
+}
+
+// base64url（无填充）解码
+fn base64url_decode(input: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+            b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+            b'-' => Some(62),
+            b'_' => Some(63),
+            _ => None,
+        }
+    };
+    let input = input.trim_end_matches('=').as_bytes();
+    if input.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    for chunk in input.chunks(4) {
+        let mut acc = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            acc |= value(c)? << (18 - 6 * i);
+        }
+        let bytes = acc.to_be_bytes();
+        out.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+    Some(out)
+}
+
+// 校验 HS256 签名与有效期，返回 sub（缺省时为空字符串）
+fn verify_jwt(token: &str, secret: &str) -> Result<String, &'static str> {
+    let mut parts = token.split('.');
+    let (Some(header), Some(payload), Some(signature), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return Err("令牌格式错误");
+    };
+
+    let header: JwtHeader = base64url_decode(header)
+        .and_then(|raw| serde_json::from_slice(&raw).ok())
+        .ok_or("令牌格式错误")?;
+    if header.alg != "HS256" {
+        return Err("不支持的签名算法");
+    }
+
+    let expected = hash_utils::hmac_sha256(secret.as_bytes(), token_head(token).as_bytes());
+    let signature = base64url_decode(signature).ok_or("令牌格式错误")?;
+    if !auth_utils::constant_time_eq(&expected, &signature) {
+        return Err("令牌签名无效");
+    }
+
+    let claims: JwtClaims = base64url_decode(payload)
+        .and_then(|raw| serde_json::from_slice(&raw).ok())
+        .ok_or("令牌格式错误")?;
+    let now = chrono::Utc::now().timestamp();
+    if claims.exp.is_some_and(|exp| now >= exp) {
+        return Err("令牌已过期");
+    }
+    if claims.nbf.is_some_and(|nbf| now < nbf) {
+        return Err("令牌尚未生效");
+    }
+    Ok(claims.sub.unwrap_or_default())
+}
+
+// 签名覆盖的部分: "header.payload"
+fn token_head(token: &str) -> &str {
+    token.rsplit_once('.').map(|(head, _)| head).unwrap_or(token)
+}
+
+fn unauthorized(message: &str) -> HttpResponse {
+    HttpResponse::Unauthorized()
+        .insert_header(("WWW-Authenticate", "Bearer"))
+        .json(ApiResponse::<()> {
+            success: false,
+            message: message.to_string(),
+            data: None,
+        })
+}
+
+// 配置 jwt_secret 后，写操作（以及开启 require_auth_for_reads 时的读操作）需要有效的 Bearer JWT；
+// 管理令牌与 API Key 仍然有效，健康检查始终公开
+pub async fn jwt_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let config = config::get_config();
+    let Some(secret) = &config.jwt_secret else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let path = req.path();
+    let protected_read = config.require_auth_for_reads
+        && (path.starts_with("/api/") || path.starts_with("/uploads/"))
+        && path != "/api/health";
+    if !is_mutating(req.method()) && !protected_read {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    if auth_utils::is_authenticated(req.request()) {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let token = req
+        .headers()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim().to_string());
+    let Some(token) = token else {
+        return Ok(req.into_response(unauthorized("需要认证令牌")).map_into_right_body());
+    };
+
+    match verify_jwt(&token, secret) {
+        Ok(subject) => {
+            log::debug!("JWT 认证通过: {} {} (sub={})", req.method(), req.path(), subject);
+            req.extensions_mut().insert(AuthSubject(subject));
+            Ok(next.call(req).await?.map_into_left_body())
+        }
+        Err(reason) => {
+            log::warn!("拒绝未通过认证的请求: {} {} ({})", req.method(), req.path(), reason);
+            Ok(req.into_response(unauthorized(reason)).map_into_right_body())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    const SECRET: &str = "test-secret";
+
+    fn base64url_encode(data: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        let mut out = String::new();
+        for chunk in data.chunks(3) {
+            let mut buf = [0u8; 3];
+            buf[..chunk.len()].copy_from_slice(chunk);
+            let n = u32::from_be_bytes([0, buf[0], buf[1], buf[2]]);
+            for i in 0..chunk.len() + 1 {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            }
+        }
+        out
+    }
+
+    fn make_jwt(claims: serde_json::Value, secret: &str) -> String {
+        let header = base64url_encode(br#"{"alg":"HS256","typ":"JWT"}"#);
+        let payload = base64url_encode(claims.to_string().as_bytes());
+        let head = format!("{}.{}", header, payload);
+        let signature = base64url_encode(&hash_utils::hmac_sha256(secret.as_bytes(), head.as_bytes()));
+        format!("{}.{}", head, signature)
+    }
+
+    #[test]
+    fn verify_jwt_checks_signature_and_expiry() {
+        let now = chrono::Utc::now().timestamp();
+        let valid = make_jwt(serde_json::json!({ "sub": "alice", "exp": now + 60 }), SECRET);
+        assert_eq!(verify_jwt(&valid, SECRET), Ok("alice".to_string()));
+
+        let expired = make_jwt(serde_json::json!({ "sub": "alice", "exp": now - 1 }), SECRET);
+        assert_eq!(verify_jwt(&expired, SECRET), Err("令牌已过期"));
+
+        let forged = make_jwt(serde_json::json!({ "sub": "alice" }), "other-secret");
+        assert_eq!(verify_jwt(&forged, SECRET), Err("令牌签名无效"));
+
+        let not_yet = make_jwt(serde_json::json!({ "nbf": now + 60 }), SECRET);
+        assert_eq!(verify_jwt(&not_yet, SECRET), Err("令牌尚未生效"));
+    }
+
+    #[actix_web::test]
+    async fn writes_require_a_valid_token() {
+        let _env = TestEnv::with(|c| c.jwt_secret = Some(SECRET.to_string()));
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let now = chrono::Utc::now().timestamp();
+
+        let create = |token: Option<String>, name: &str| {
+            let mut req = TestRequest::post()
+                .uri("/api/modules")
+                .set_json(serde_json::json!({ "name": name }));
+            if let Some(token) = token {
+                req = req.insert_header(("Authorization", format!("Bearer {}", token)));
+            }
+            req.to_request()
+        };
+
+        let resp = call_service(&app, create(None, "m1")).await;
+        assert_eq!(resp.status(), 401);
+
+        let expired = make_jwt(serde_json::json!({ "sub": "alice", "exp": now - 10 }), SECRET);
+        let resp = call_service(&app, create(Some(expired), "m1")).await;
+        assert_eq!(resp.status(), 401);
+
+        let valid = make_jwt(serde_json::json!({ "sub": "alice", "exp": now + 60 }), SECRET);
+        let resp = call_service(&app, create(Some(valid), "m1")).await;
+        assert_eq!(resp.status(), 200);
+
+        // 读操作默认不需要认证
+        let resp = call_service(&app, TestRequest::get().uri("/api/modules").to_request()).await;
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[actix_web::test]
+    async fn source_ip_is_only_shown_to_authenticated_readers() {
+        let _env = TestEnv::with(|c| c.jwt_secret = Some(SECRET.to_string()));
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let valid = make_jwt(serde_json::json!({ "sub": "alice", "exp": chrono::Utc::now().timestamp() + 60 }), SECRET);
+
+        let resp = call_service(
+            &app,
+            TestRequest::put()
+                .uri("/api/upload/default/provenance.txt")
+                .peer_addr("10.1.2.3:4567".parse().unwrap())
+                .insert_header(("Authorization", format!("Bearer {}", valid)))
+                .set_payload("hello")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+
+        let source_ip = |token: Option<String>| {
+            let app = &app;
+            async move {
+                let mut req = TestRequest::get().uri("/api/file/default/provenance.txt");
+                if let Some(token) = token {
+                    req = req.insert_header(("Authorization", format!("Bearer {}", token)));
+                }
+                let resp = call_service(app, req.to_request()).await;
+                assert_eq!(resp.status(), 200);
+                let body: serde_json::Value = actix_web::test::read_body_json(resp).await;
+                assert_eq!(body["data"]["provenance"]["uploader"], "alice");
+                body["data"]["provenance"]["source_ip"].clone()
+            }
+        };
+
+        // GET 不经过 jwt_auth，JWT 在处理器中就地校验
+        assert_eq!(source_ip(Some(valid.clone())).await, "10.1.2.3");
+        assert!(source_ip(None).await.is_null());
+        let forged = make_jwt(serde_json::json!({ "sub": "alice" }), "other-secret");
+        assert!(source_ip(Some(forged)).await.is_null());
+    }
+}
@@ -0,0 +1,206 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header::{HeaderName, HeaderValue}, Method},
+    middleware::Next,
+    Error, HttpResponse,
+};
+use crate::{config, models::ApiResponse};
+
+// 预检结果的缓存时间（秒）
+const PREFLIGHT_MAX_AGE: &str = "3600";
+// 允许浏览器脚本读取的响应头（下载、断点续传和限流相关）
+const EXPOSE_HEADERS: &str = "ETag, Last-Modified, Content-Disposition, Content-Range, Accept-Ranges, Location, \
+    Retry-After, X-Request-Id, X-Skipped-Count, X-Skipped-Files, \
+    Tus-Resumable, Tus-Version, Tus-Extension, Tus-Max-Size, Upload-Offset, Upload-Length, Upload-Metadata";
+
+// 来源是否在允许列表中；以 ":*" 结尾的条目匹配该主机的任意端口（含默认端口）
+fn origin_allowed(origin: &str, allowed_origins: &[String]) -> bool {
+    let origin = origin.trim_end_matches('/');
+    allowed_origins.iter().any(|allowed| {
+        if allowed == "*" {
+            return true;
+        }
+        match allowed.strip_suffix(":*") {
+            Some(host) => match origin.get(..host.len()) {
+                Some(prefix) if prefix.eq_ignore_ascii_case(host) => {
+                    let rest = &origin[host.len()..];
+                    rest.is_empty()
+                        || rest.strip_prefix(':').is_some_and(|port| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()))
+                }
+                _ => false,
+            },
+            None => allowed.eq_ignore_ascii_case(origin),
+        }
+    })
+}
+
+// 预检请求中声明的请求头必须全部被允许
+fn headers_allowed(requested: Option<&str>, allowed_headers: &[String]) -> bool {
+    if allowed_headers.iter().any(|h| h == "*") {
+        return true;
+    }
+    requested.unwrap_or("").split(',').map(str::trim).filter(|h| !h.is_empty()).all(|requested| {
+        allowed_headers.iter().any(|allowed| allowed.eq_ignore_ascii_case(requested))
+    })
+}
+
+fn insert_header(res: &mut ServiceResponse<impl MessageBody>, name: &'static str, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        res.headers_mut().insert(HeaderName::from_static(name), value);
+    }
+}
+
+// 响应内容随 Origin 请求头变化，无论来源是否允许（或没有 Origin）都声明 Vary: Origin，
+// 避免共享缓存把某个来源的响应返回给其他来源；已有的 Vary 值保留
+fn add_vary_origin(res: &mut ServiceResponse<impl MessageBody>) {
+    let varies = res
+        .headers()
+        .get_all(actix_web::http::header::VARY)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|v| v.trim() == "*" || v.trim().eq_ignore_ascii_case("origin"));
+    if !varies {
+        res.headers_mut().append(actix_web::http::header::VARY, HeaderValue::from_static("Origin"));
+    }
+}
+
+// 跨域处理：直接应答预检请求；对允许来源的普通请求补充 Access-Control-* 响应头
+pub async fn cors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let config = config::get_config();
+    let Some(origin) = req.headers().get("origin").and_then(|v| v.to_str().ok()).map(str::to_string) else {
+        let mut res = next.call(req).await?;
+        add_vary_origin(&mut res);
+        return Ok(res.map_into_left_body());
+    };
+    let allowed = origin_allowed(&origin, &config.cors_allowed_origins);
+
+    let request_method = req
+        .headers()
+        .get("access-control-request-method")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    if req.method() == Method::OPTIONS {
+        if let Some(request_method) = request_method {
+            let requested_headers = req.headers().get("access-control-request-headers").and_then(|v| v.to_str().ok());
+            let method_allowed = config.cors_allowed_methods.iter().any(|m| m.eq_ignore_ascii_case(&request_method));
+
+            if !allowed || !method_allowed || !headers_allowed(requested_headers, &config.cors_allowed_headers) {
+                log::warn!("拒绝跨域预检请求: {} {} (来源: {}, 方法: {})", req.method(), req.path(), origin, request_method);
+                let response = HttpResponse::Forbidden().json(ApiResponse::<()> {
+                    success: false,
+                    message: "跨域请求不被允许".to_string(),
+                    data: None,
+                });
+                let mut res = req.into_response(response);
+                add_vary_origin(&mut res);
+                return Ok(res.map_into_right_body());
+            }
+
+            let allow_headers = match requested_headers {
+                Some(headers) if config.cors_allowed_headers.iter().any(|h| h == "*") => headers.to_string(),
+                _ => config.cors_allowed_headers.join(", "),
+            };
+            let response = HttpResponse::NoContent()
+                .insert_header(("Access-Control-Allow-Origin", origin))
+                .insert_header(("Access-Control-Allow-Methods", config.cors_allowed_methods.join(", ")))
+                .insert_header(("Access-Control-Allow-Headers", allow_headers))
+                .insert_header(("Access-Control-Max-Age", PREFLIGHT_MAX_AGE))
+                .insert_header(("Vary", "Origin"))
+                .finish();
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+
+    let mut res = next.call(req).await?;
+    if allowed {
+        insert_header(&mut res, "access-control-allow-origin", &origin);
+        insert_header(&mut res, "access-control-expose-headers", EXPOSE_HEADERS);
+    }
+    add_vary_origin(&mut res);
+    Ok(res.map_into_left_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    fn preflight(origin: &str, method: &str) -> TestRequest {
+        TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/api/upload")
+            .insert_header(("Origin", origin))
+            .insert_header(("Access-Control-Request-Method", method))
+            .insert_header(("Access-Control-Request-Headers", "content-type, x-api-key"))
+    }
+
+    #[test]
+    fn matches_origins_with_port_wildcards() {
+        let allowed = vec!["http://localhost:*".to_string(), "https://app.example.com".to_string()];
+        assert!(origin_allowed("http://localhost", &allowed));
+        assert!(origin_allowed("http://localhost:5173", &allowed));
+        assert!(origin_allowed("https://APP.example.com/", &allowed));
+        assert!(!origin_allowed("http://localhost.evil.com", &allowed));
+        assert!(!origin_allowed("http://localhost:", &allowed));
+        assert!(!origin_allowed("https://app.example.com.evil.com", &allowed));
+    }
+
+    #[actix_web::test]
+    async fn preflight_is_answered_only_for_allowed_origins() {
+        let _env = TestEnv::with(|c| {
+            c.cors_allowed_origins = vec!["https://app.example.com".to_string()];
+            c.cors_allowed_headers = vec!["Content-Type".to_string(), "X-API-Key".to_string()];
+        });
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let resp = call_service(&app, preflight("https://app.example.com", "POST").to_request()).await;
+        assert_eq!(resp.status(), 204);
+        assert_eq!(resp.headers().get("Access-Control-Allow-Origin").unwrap(), "https://app.example.com");
+        assert!(resp.headers().get("Access-Control-Allow-Methods").unwrap().to_str().unwrap().contains("POST"));
+        assert_eq!(resp.headers().get("Access-Control-Max-Age").unwrap(), PREFLIGHT_MAX_AGE);
+        assert_eq!(resp.headers().get("Vary").unwrap(), "Origin");
+
+        let resp = call_service(&app, preflight("https://evil.example.com", "POST").to_request()).await;
+        assert_eq!(resp.status(), 403);
+        assert!(resp.headers().get("Access-Control-Allow-Origin").is_none());
+        assert_eq!(resp.headers().get("Vary").unwrap(), "Origin");
+
+        // 允许的来源但方法不在允许列表中
+        let resp = call_service(&app, preflight("https://app.example.com", "TRACE").to_request()).await;
+        assert_eq!(resp.status(), 403);
+    }
+
+    #[actix_web::test]
+    async fn simple_requests_always_vary_on_origin() {
+        let _env = TestEnv::with(|c| c.cors_allowed_origins = vec!["https://app.example.com".to_string()]);
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let resp = call_service(
+            &app,
+            TestRequest::get().uri("/api/modules").insert_header(("Origin", "https://app.example.com")).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("Access-Control-Allow-Origin").unwrap(), "https://app.example.com");
+        assert!(resp.headers().get("Access-Control-Expose-Headers").unwrap().to_str().unwrap().contains("X-Skipped-Files"));
+        assert_eq!(resp.headers().get_all("Vary").count(), 1);
+
+        for origin in [Some("https://evil.example.com"), None] {
+            let mut req = TestRequest::get().uri("/api/modules");
+            if let Some(origin) = origin {
+                req = req.insert_header(("Origin", origin));
+            }
+            let resp = call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), 200);
+            assert!(resp.headers().get("Access-Control-Allow-Origin").is_none());
+            assert_eq!(resp.headers().get("Vary").unwrap(), "Origin");
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod origin_check;
+pub mod read_only;
+pub mod request_log;
+pub mod auth;
+pub mod static_etag;
+pub mod cors;
@@ -0,0 +1,104 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    Error, HttpResponse,
+};
+use crate::{config, models::ApiResponse, utils::auth_utils};
+
+// 从 Origin 或 Referer 中提取 scheme://host[:port]
+fn request_origin(req: &ServiceRequest) -> Option<String> {
+    let headers = req.headers();
+    if let Some(origin) = headers.get("origin").and_then(|v| v.to_str().ok()) {
+        if origin != "null" {
+            return Some(origin.trim_end_matches('/').to_string());
+        }
+    }
+
+    let referer = headers.get("referer").and_then(|v| v.to_str().ok())?;
+    let scheme_end = referer.find("://")? + 3;
+    let host_end = referer[scheme_end..]
+        .find('/')
+        .map(|i| scheme_end + i)
+        .unwrap_or(referer.len());
+    Some(referer[..host_end].to_string())
+}
+
+pub fn is_mutating(method: &Method) -> bool {
+    matches!(*method, Method::POST | Method::PUT | Method::PATCH | Method::DELETE)
+}
+
+// 启用 enforce_origin 时，写操作必须来自允许的来源；携带有效 API Key 的机器客户端不受限制
+pub async fn origin_check(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let config = config::get_config();
+
+    if config.enforce_origin && is_mutating(req.method()) && !auth_utils::has_valid_api_key(req.headers()) {
+        let origin = request_origin(&req);
+        let allowed = origin.as_ref().is_some_and(|o| {
+            config.allowed_origins.iter().any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(o))
+        });
+
+        if !allowed {
+            log::warn!("拒绝来源不受信任的写请求: {} {} (来源: {:?})", req.method(), req.path(), origin);
+            let response = HttpResponse::Forbidden().json(ApiResponse::<()> {
+                success: false,
+                message: "请求来源不被允许".to_string(),
+                data: None,
+            });
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    #[actix_web::test]
+    async fn rejects_writes_from_untrusted_origins() {
+        let _env = TestEnv::with(|c| {
+            c.enforce_origin = true;
+            c.allowed_origins = vec!["http://app.example.com".to_string()];
+            c.api_keys = vec!["machine-key".to_string()];
+        });
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let create = |name: &str| TestRequest::post().uri("/api/modules").set_json(serde_json::json!({ "name": name }));
+
+        let resp = call_service(&app, create("a").insert_header(("Origin", "http://evil.example.com")).to_request()).await;
+        assert_eq!(resp.status(), 403);
+
+        // 没有 Origin 与 Referer 的写请求同样拒绝
+        let resp = call_service(&app, create("a").to_request()).await;
+        assert_eq!(resp.status(), 403);
+
+        let resp = call_service(&app, create("a").insert_header(("Origin", "http://app.example.com")).to_request()).await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = call_service(
+            &app,
+            create("b").insert_header(("Referer", "http://app.example.com/page?x=1")).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = call_service(&app, create("c").insert_header(("X-API-Key", "machine-key")).to_request()).await;
+        assert_eq!(resp.status(), 200);
+
+        // 读请求不受限制
+        let resp = call_service(
+            &app,
+            TestRequest::get().uri("/api/modules").insert_header(("Origin", "http://evil.example.com")).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+    }
+}
@@ -0,0 +1,88 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+use crate::{models::ApiResponse, state::AppState};
+use super::origin_check::is_mutating;
+
+pub const READ_ONLY_ERROR: &str = "服务器处于只读维护模式，暂不接受写操作";
+
+// 使用 POST 但不修改数据的接口，以及用于关闭只读模式的管理接口
+fn is_exempt(method: &Method, path: &str) -> bool {
+    *method == Method::POST
+        && matches!(path, "/api/admin/readonly" | "/api/download/zip" | "/api/upload/check")
+}
+
+// 只读维护模式下拒绝所有写请求（上传、合并、创建、移动、删除等），读取与下载照常
+pub async fn read_only_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let read_only = req
+        .app_data::<web::Data<AppState>>()
+        .is_some_and(|state| state.is_read_only());
+
+    if read_only && is_mutating(req.method()) && !is_exempt(req.method(), req.path()) {
+        log::info!("只读模式下拒绝写请求: {} {}", req.method(), req.path());
+        let response = HttpResponse::ServiceUnavailable().json(ApiResponse::<()> {
+            success: false,
+            message: READ_ONLY_ERROR.to_string(),
+            data: None,
+        });
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    #[actix_web::test]
+    async fn read_only_mode_rejects_writes_and_keeps_reads() {
+        let env = TestEnv::with(|c| c.admin_token = Some("admin".to_string()));
+        std::fs::write(env.uploads().join("default/existing.txt"), "kept").unwrap();
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let toggle = |enabled: bool, token: Option<&str>| {
+            let mut req = TestRequest::post().uri("/api/admin/readonly").set_json(serde_json::json!({ "enabled": enabled }));
+            if let Some(token) = token {
+                req = req.insert_header(("X-Admin-Token", token.to_string()));
+            }
+            req.to_request()
+        };
+
+        let resp = call_service(&app, toggle(true, None)).await;
+        assert_eq!(resp.status(), 401);
+        let resp = call_service(&app, toggle(true, Some("admin"))).await;
+        assert_eq!(resp.status(), 200);
+        assert!(state.is_read_only());
+
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "new.txt", b"data").to_request()).await;
+        assert_eq!(resp.status(), 503);
+        let resp = call_service(&app, TestRequest::delete().uri("/api/file/default/existing.txt").to_request()).await;
+        assert_eq!(resp.status(), 503);
+        let resp = call_service(&app, TestRequest::post().uri("/api/modules").set_json(serde_json::json!({ "name": "m" })).to_request()).await;
+        assert_eq!(resp.status(), 503);
+        assert!(!env.uploads().join("default/new.txt").exists());
+        assert!(env.uploads().join("default/existing.txt").exists());
+
+        let resp = call_service(&app, TestRequest::get().uri("/api/modules").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let resp = call_service(&app, TestRequest::get().uri("/api/files/default").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let resp = call_service(&app, TestRequest::get().uri("/api/download/default/existing.txt").to_request()).await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = call_service(&app, toggle(false, Some("admin"))).await;
+        assert_eq!(resp.status(), 200);
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "new.txt", b"data").to_request()).await;
+        assert_eq!(resp.status(), 200);
+    }
+}
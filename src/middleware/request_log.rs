@@ -0,0 +1,58 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    Error, HttpMessage,
+};
+use std::time::Instant;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// 保存在请求扩展中的请求 ID，供处理器读取
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+// 为每个请求分配请求 ID，并在超过慢请求阈值时输出 WARN 日志
+pub async fn request_context(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    // 优先沿用客户端传入的请求 ID（仅接受长度合理的字母数字与 '-'）
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 128 && v.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    let start_time = Instant::now();
+
+    let mut res = next.call(req).await?;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+
+    if let Some(threshold_ms) = crate::config::get_config().slow_request_threshold_ms {
+        let elapsed_ms = start_time.elapsed().as_millis();
+        if elapsed_ms >= threshold_ms as u128 {
+            log::warn!(
+                "慢请求: {} {} 耗时 {}ms (状态 {}, request_id={})",
+                method,
+                path,
+                elapsed_ms,
+                res.status().as_u16(),
+                request_id
+            );
+        }
+    }
+
+    Ok(res)
+}
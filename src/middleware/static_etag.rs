@@ -0,0 +1,61 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    Error, HttpResponse,
+};
+use percent_encoding::percent_decode_str;
+use std::path::PathBuf;
+use crate::config;
+use crate::services::download_service;
+use crate::utils::file_utils;
+
+// 为 /uploads 静态文件服务提供与 /api/download 一致的 ETag 与 Content-Type，并处理 If-None-Match 条件请求
+pub async fn static_file_etag(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(path) = resolve_static_path(req.path()) else {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    };
+    let metadata = match tokio::fs::metadata(&path).await {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return next.call(req).await.map(ServiceResponse::map_into_left_body),
+    };
+
+    let etag = file_utils::file_etag(&metadata);
+    let last_modified = download_service::last_modified_header(&metadata);
+    let if_none_match = req.headers().get("if-none-match").and_then(|v| v.to_str().ok());
+    if download_service::etag_matches(if_none_match, &etag) {
+        let mut response = HttpResponse::NotModified();
+        response.insert_header(("ETag", etag));
+        if let Some(last_modified) = last_modified {
+            response.insert_header(("Last-Modified", last_modified));
+        }
+        return Ok(req.into_response(response.finish()).map_into_right_body());
+    }
+
+    let mut res = next.call(req).await?;
+    if res.status().is_success() {
+        if let Ok(value) = HeaderValue::from_str(&etag) {
+            res.headers_mut().insert(HeaderName::from_static("etag"), value);
+        }
+        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        res.headers_mut().insert(
+            HeaderName::from_static("content-type"),
+            HeaderValue::from_static(file_utils::mime_for_extension(ext)),
+        );
+    }
+    Ok(res.map_into_left_body())
+}
+
+// 将 /uploads/{module}/... 映射到存储目录中的文件；路径不合法时交给静态文件服务自行处理
+fn resolve_static_path(request_path: &str) -> Option<PathBuf> {
+    let relative = request_path.strip_prefix("/uploads/")?;
+    let relative = percent_decode_str(relative).decode_utf8().ok()?;
+    if relative.split('/').any(|seg| seg == "..") || relative.contains('\\') {
+        return None;
+    }
+    Some(PathBuf::from(config::uploads_root()).join(relative.trim_start_matches('/')))
+}
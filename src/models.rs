@@ -1,10 +1,47 @@
+use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
+use crate::config::HashAlgorithm;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Module {
     pub name: String,
 }
 
+// 重命名模块的请求体
+#[derive(Debug, Deserialize)]
+pub struct RenameModuleRequest {
+    pub new_name: String,
+}
+
+// 切换只读维护模式的请求体（POST /api/admin/readonly）
+#[derive(Debug, Deserialize)]
+pub struct ReadOnlyRequest {
+    pub enabled: bool,
+}
+
+// 创建模块的结果；模块已存在时 already_exists 为 true，便于客户端安全重试
+#[derive(Debug, Serialize)]
+pub struct ModuleCreated {
+    pub name: String,
+    pub already_exists: bool,
+}
+
+// 批量创建模块的请求体（POST /api/modules/batch）
+#[derive(Debug, Deserialize)]
+pub struct BatchCreateModulesRequest {
+    pub names: Vec<String>,
+}
+
+// 批量创建中单个名称的结果，status 为 created / exists / duplicate / invalid / failed
+#[derive(Debug, Serialize)]
+pub struct ModuleBatchResult {
+    pub name: String,
+    pub status: &'static str,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
     pub success: bool,
@@ -12,6 +49,14 @@ pub struct ApiResponse<T> {
     pub data: Option<T>,
 }
 
+// 错误详情：code 为便于客户端识别的错误码
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorInfo {
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileInfo {
     pub filename: String,
@@ -22,14 +67,252 @@ pub struct FileInfo {
     pub file_type: String,
     pub relative_path: Option<String>,
     pub file_hash: Option<String>,
+    // 是否以压缩形式存储（此时 size 为原始大小，需通过 /api/download 获取原始内容）
+    #[serde(default)]
+    pub compressed: bool,
+    // 图片宽高（从文件头读取，非图片为空）
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    // 上传时随 multipart 的 metadata 字段提交的自定义键值（标签、描述等）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+// 上传时被跳过的文件及原因
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub filename: String,
+    pub reason: String,
+    pub detected: String,
+    pub allowed_extensions: Vec<String>,
+}
+
+// 上传失败的文件及原因（包括被跳过的文件与写入出错的文件）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailedFile {
+    pub filename: String,
+    pub reason: String,
+}
+
+// 随文件持久化的元数据（sidecar）
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FileMeta {
+    #[serde(default)]
+    pub file_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<FileProvenance>,
+    // 压缩存储时的原始大小，存在即表示文件已压缩
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+// 模块校验清单中的一项
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub relative_path: Option<String>,
+    pub filename: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+// 上传来源信息（谁、从哪里上传）
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FileProvenance {
+    pub uploader: Option<String>,
+    pub source_ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub original_filename: String,
+}
+
+// 上传请求的上下文，由处理器从 HTTP 请求中提取后传给服务层
+#[derive(Debug, Clone, Default)]
+pub struct UploadContext {
+    pub uploader: Option<String>,
+    pub source_ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    // 本次上传的写入速率上限（字节/秒），None 表示不限制
+    pub max_bps: Option<u64>,
+}
+
+impl UploadContext {
+    pub fn provenance(&self, original_filename: &str) -> FileProvenance {
+        FileProvenance {
+            uploader: self.uploader.clone(),
+            source_ip: self.source_ip.clone(),
+            user_agent: self.user_agent.clone(),
+            request_id: self.request_id.clone(),
+            original_filename: original_filename.to_string(),
+        }
+    }
+}
+
+// 单个文件的详细信息
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileDetails {
+    #[serde(flatten)]
+    pub info: FileInfo,
+    pub provenance: Option<FileProvenance>,
+    // 文件系统记录的创建时间与最后修改时间（平台不支持时为 None）
+    pub created: Option<String>,
+    pub last_modified: Option<String>,
+    // 请求时指定 ?hash= 才会即时计算的内容摘要
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_algorithm: Option<HashAlgorithm>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub computed_hash: Option<String>,
+    // 与下载接口一致的 ETag，可用于条件请求
+    pub etag: String,
+    #[serde(skip)]
+    pub last_modified_header: Option<String>,
+}
+
+// 分页后的文件列表
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FilePage {
+    pub files: Vec<FileInfo>,
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+// 跨模块搜索结果，truncated 表示命中数超过上限、结果已截断
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    pub files: Vec<FileInfo>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileChanges {
+    pub files: Vec<FileInfo>,
+    // since 之后被删除或移走、且当前不存在的路径（取自审计日志，未配置审计日志时为空）
+    #[serde(default)]
+    pub deleted: Vec<String>,
+    pub cursor: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileMove {
+    pub src: String,
+    pub dst: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveFilesRequest {
+    pub module: String,
+    pub moves: Vec<FileMove>,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveResult {
+    pub src: String,
+    pub dst: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// 单个文件的移动/重命名，可跨模块
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveFileRequest {
+    pub from_module: String,
+    pub from_path: String,
+    pub to_module: String,
+    pub to_path: String,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchDeleteRequest {
+    pub module: String,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteResult {
+    pub file: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// 删除文件夹时移除（或移入回收站）的文件路径（相对模块目录）
+#[derive(Debug, Serialize)]
+pub struct FolderDeleteResult {
+    pub deleted: Vec<String>,
+}
+
+// 强制删除模块后仍残留的路径（相对模块目录）
+#[derive(Debug, Serialize)]
+pub struct ModuleDeleteResult {
+    pub remaining: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ZipDownloadRequest {
+    pub module: String,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DirectoryListing {
+    pub folders: Vec<String>,
+    pub files: Vec<FileInfo>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ModuleInfo {
     pub name: String,
-    pub file_count: usize,
+    // 列表请求 with_stats=false 时不统计，为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_count: Option<usize>,
     pub created_time: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+}
+
+// 分页后的模块列表
+#[derive(Debug, Serialize)]
+pub struct ModulePage {
+    pub modules: Vec<ModuleInfo>,
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+// 模块内按文件类型（image、video、document ...）汇总的数量与占用字节数
+#[derive(Debug, Serialize)]
+pub struct ModuleStats {
+    pub module: String,
+    pub file_count: usize,
     pub total_size: u64,
+    pub by_type: HashMap<String, TypeStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TypeStats {
+    pub count: usize,
+    pub size: u64,
+}
+
+// 模块目录树中的一个节点（模块或各级子模块），file_count 为该目录下直接包含的文件数
+#[derive(Debug, Serialize)]
+pub struct ModuleTreeNode {
+    pub name: String,
+    pub file_count: usize,
+    // 达到最大深度或节点数上限，子目录未（全部）展开
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    pub children: Vec<ModuleTreeNode>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,6 +325,23 @@ pub struct ChunkUploadRequest {
     pub relative_path: Option<String>,
     pub file_hash: Option<String>,
     pub chunk_hash: Option<String>,
+    #[serde(default)]
+    pub submodule: Option<String>,
+    // 同名文件处理策略: rename(suffix) / overwrite / fail(reject)，缺省使用服务端配置
+    #[serde(default)]
+    pub on_conflict: Option<String>,
+    // 校验 file_hash 使用的摘要算法: sha256 / md5，缺省使用服务端配置
+    #[serde(default)]
+    pub hash_algorithm: Option<String>,
+    // 分片会话 id，需与上传分块时一致；缺省时使用 file_hash
+    #[serde(default)]
+    pub upload_id: Option<String>,
+    // 声明除最后一块外所有分块都恰好为 chunk_size，允许服务端并行合并（大小不符时自动退回顺序合并）
+    #[serde(default)]
+    pub uniform_chunks: bool,
+    // 声明的文件总大小（字节），提供时合并结果的大小必须与之一致
+    #[serde(default)]
+    pub total_size: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -54,6 +354,7 @@ pub struct ChunkUploadResponse {
     pub next_chunk: Option<usize>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FolderUploadRequest {
     pub module: String,
@@ -61,6 +362,7 @@ pub struct FolderUploadRequest {
     pub files: Vec<FolderFileInfo>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FolderFileInfo {
     pub filename: String,
@@ -88,4 +390,115 @@ pub struct ResumeUploadRequest {
     pub module: String,
     pub file_hash: String,
     pub total_size: u64,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub upload_id: Option<String>,
+    // 客户端的分片大小；提供时响应中给出预期分片数与缺失的分片编号
+    #[serde(default)]
+    pub chunk_size: Option<u64>,
+}
+
+// 重新触发合并：用于合并请求丢失（如服务重启）后分片仍完整保留的会话
+#[derive(Debug, Deserialize)]
+pub struct RecoverUploadRequest {
+    pub module: String,
+    pub filename: String,
+    pub total_chunks: usize,
+    pub relative_path: Option<String>,
+    pub file_hash: Option<String>,
+    #[serde(default)]
+    pub hash_algorithm: Option<String>,
+    #[serde(default)]
+    pub upload_id: Option<String>,
+}
+
+// 取消进行中的分块上传：删除该会话的全部分片并清除上传进度
+#[derive(Debug, Deserialize)]
+pub struct CancelUploadRequest {
+    pub module: String,
+    pub filename: String,
+    pub relative_path: Option<String>,
+    #[serde(default)]
+    pub upload_id: Option<String>,
+    #[serde(default)]
+    pub file_hash: Option<String>,
+}
+
+// 查询分块上传会话的分片状态（GET /api/upload/status/{module}/{filename}）
+#[derive(Debug, Deserialize)]
+pub struct ChunkStatusQuery {
+    pub total_chunks: usize,
+    pub relative_path: Option<String>,
+    #[serde(default)]
+    pub upload_id: Option<String>,
+    #[serde(default)]
+    pub file_hash: Option<String>,
+}
+
+// tus 协议的上传会话，保存在 {temp_root}/.tus/{id}.tus.json，已接收的数据在 {id}.tus
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TusUpload {
+    pub id: String,
+    pub module: String,
+    pub filename: String,
+    pub relative_path: Option<String>,
+    // Upload-Length，数据达到该长度时写入模块目录
+    pub length: u64,
+    // 原始 Upload-Metadata 头，HEAD 请求时原样返回
+    pub raw_metadata: Option<String>,
+    // 除 filename/module 等保留键以外的元数据，完成后写入文件 sidecar
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CancelUploadResult {
+    pub removed_parts: usize,
+    pub freed_bytes: u64,
+}
+
+// 分块上传会话中已上传与缺失的分片编号（大小为 0 的分片视为损坏，计入 missing）
+#[derive(Debug, Serialize)]
+pub struct ChunkUploadStatus {
+    pub uploaded: Vec<usize>,
+    pub missing: Vec<usize>,
+    pub complete: bool,
+}
+
+// 分片齐全、可以直接恢复合并的会话（name 为分片前缀，含扁平化的相对路径）
+#[derive(Debug, Serialize)]
+pub struct RecoverableUpload {
+    pub name: String,
+    pub total_chunks: usize,
+    pub total_size: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PartSetInfo {
+    pub name: String,
+    pub chunks: Vec<usize>,
+    pub total_size: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModuleInconsistencies {
+    pub module: String,
+    pub orphaned: Vec<PartSetInfo>,
+    pub stale: Vec<PartSetInfo>,
+    pub cleaned_parts: usize,
+    pub freed_bytes: u64,
+}
+
+// 回收站中的条目（name 为相对 .trash/{module} 的路径，恢复时原样传回）
+#[derive(Debug, Serialize)]
+pub struct TrashItem {
+    pub name: String,
+    pub original_name: String,
+    pub relative_path: Option<String>,
+    pub deleted_at: String,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreTrashRequest {
+    pub name: String,
+}
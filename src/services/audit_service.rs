@@ -0,0 +1,286 @@
+// 审计日志：每次创建/上传/移动/删除操作追加一行 NDJSON（由单个后台任务写入，处理请求的线程不等待磁盘 I/O）
+use std::io::BufRead;
+use std::sync::RwLock;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::fs::{self as tokio_fs, File, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use crate::config;
+
+static AUDIT_SENDER: RwLock<Option<UnboundedSender<AuditEntry>>> = RwLock::new(None);
+
+#[derive(Debug, Serialize)]
+struct AuditEntry {
+    timestamp: String,
+    operation: &'static str,
+    module: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    client_ip: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actor: Option<String>,
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// 配置了审计日志路径时启动写入任务；未启动时 record 直接忽略。
+// 重复调用时替换原有通道，旧的写入任务在通道关闭后写完剩余记录并退出
+pub fn init_audit_log() {
+    let config = config::get_config();
+    let sender = config.audit_log_path.clone().map(|path| {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run_writer(path, config.audit_log_max_size, receiver));
+        sender
+    });
+    *AUDIT_SENDER.write().unwrap_or_else(|e| e.into_inner()) = sender;
+}
+
+// 记录一次操作；actor 为通过认证的调用方（未认证时为 None），error 为 None 表示成功
+pub fn record(
+    operation: &'static str,
+    module: &str,
+    path: Option<&str>,
+    size: Option<u64>,
+    client_ip: &str,
+    actor: Option<&str>,
+    error: Option<&str>,
+) {
+    send(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        operation,
+        module: module.to_string(),
+        path: path.map(str::to_string),
+        target: None,
+        size,
+        client_ip: client_ip.to_string(),
+        actor: actor.map(str::to_string),
+        success: error.is_none(),
+        error: error.map(str::to_string),
+    });
+}
+
+// 记录一次移动/重命名，target 为目标位置
+pub fn record_move(
+    operation: &'static str,
+    module: &str,
+    path: Option<&str>,
+    target: &str,
+    client_ip: &str,
+    actor: Option<&str>,
+    error: Option<&str>,
+) {
+    send(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        operation,
+        module: module.to_string(),
+        path: path.map(str::to_string),
+        target: Some(target.to_string()),
+        size: None,
+        client_ip: client_ip.to_string(),
+        actor: actor.map(str::to_string),
+        success: error.is_none(),
+        error: error.map(str::to_string),
+    });
+}
+
+// 使路径不再存在于原位置的操作
+const REMOVING_OPERATIONS: [&str; 3] = ["delete", "delete_folder", "move"];
+
+// 从审计日志（含轮转的上一份）中找出 since 之后 module 内成功删除或移走的路径（相对模块目录，按时间顺序去重）；
+// 未配置审计日志时返回空列表。写入任务异步落盘，刚完成的操作可能要稍后才出现
+pub async fn removed_since(module: &str, since: DateTime<Utc>) -> Vec<String> {
+    let Some(path) = config::get_config().audit_log_path.clone() else {
+        return Vec::new();
+    };
+    let module = module.to_string();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut removed: Vec<String> = Vec::new();
+        for log_path in [format!("{}.1", path), path] {
+            let Ok(file) = std::fs::File::open(&log_path) else { continue };
+            for line in std::io::BufReader::new(file).lines().map_while(Result::ok) {
+                let Ok(entry) = serde_json::from_str::<serde_json::Value>(&line) else { continue };
+                if let Some(removed_path) = removed_path(&entry, &module, since) {
+                    if !removed.contains(&removed_path) {
+                        removed.push(removed_path);
+                    }
+                }
+            }
+        }
+        removed
+    })
+    .await;
+    result.unwrap_or_else(|e| {
+        log::warn!("读取审计日志失败: {}", e);
+        Vec::new()
+    })
+}
+
+// 审计记录表示 since 之后 module（或其子目录）内成功移除的路径时，返回相对 module 的路径
+fn removed_path(entry: &serde_json::Value, module: &str, since: DateTime<Utc>) -> Option<String> {
+    let operation = entry.get("operation")?.as_str()?;
+    if !REMOVING_OPERATIONS.contains(&operation) || entry.get("success")?.as_bool() != Some(true) {
+        return None;
+    }
+    let timestamp = DateTime::parse_from_rfc3339(entry.get("timestamp")?.as_str()?).ok()?;
+    if timestamp <= since {
+        return None;
+    }
+    let entry_module = entry.get("module")?.as_str()?;
+    let path = entry.get("path")?.as_str()?.trim_matches('/');
+    if entry_module == module {
+        Some(path.to_string())
+    } else {
+        let submodule = entry_module.strip_prefix(module)?.strip_prefix('/')?;
+        Some(format!("{}/{}", submodule, path))
+    }
+}
+
+fn send(entry: AuditEntry) {
+    if let Some(sender) = AUDIT_SENDER.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        if sender.send(entry).is_err() {
+            log::warn!("审计日志写入任务已停止，丢弃记录");
+        }
+    }
+}
+
+async fn open_log(path: &str) -> std::io::Result<(BufWriter<File>, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path).await?;
+    let size = file.metadata().await?.len();
+    Ok((BufWriter::new(file), size))
+}
+
+// 逐条写入，通道暂时取空时刷新到磁盘；文件超过 max_size 后轮转为 <path>.1
+async fn run_writer(path: String, max_size: u64, mut receiver: UnboundedReceiver<AuditEntry>) {
+    let mut log_file = match open_log(&path).await {
+        Ok(opened) => Some(opened),
+        Err(e) => {
+            log::error!("打开审计日志失败 {}: {}", path, e);
+            None
+        }
+    };
+
+    while let Some(entry) = receiver.recv().await {
+        let mut pending = vec![entry];
+        while let Ok(entry) = receiver.try_recv() {
+            pending.push(entry);
+        }
+
+        if log_file.is_none() {
+            log_file = open_log(&path).await.ok();
+        }
+        let Some((writer, size)) = log_file.as_mut() else {
+            log::warn!("审计日志不可用，丢弃 {} 条记录", pending.len());
+            continue;
+        };
+
+        for entry in pending {
+            let mut line = match serde_json::to_vec(&entry) {
+                Ok(line) => line,
+                Err(e) => {
+                    log::warn!("序列化审计记录失败: {}", e);
+                    continue;
+                }
+            };
+            line.push(b'\n');
+            if let Err(e) = writer.write_all(&line).await {
+                log::warn!("写入审计日志失败: {}", e);
+            }
+            *size += line.len() as u64;
+        }
+        if let Err(e) = writer.flush().await {
+            log::warn!("刷新审计日志失败: {}", e);
+        }
+
+        if *size >= max_size {
+            let rotated = format!("{}.1", path);
+            if let Err(e) = tokio_fs::rename(&path, &rotated).await {
+                log::warn!("轮转审计日志失败: {}", e);
+            } else {
+                log::info!("审计日志已轮转: {}", rotated);
+            }
+            log_file = match open_log(&path).await {
+                Ok(opened) => Some(opened),
+                Err(e) => {
+                    log::error!("打开审计日志失败 {}: {}", path, e);
+                    None
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use actix_web::test::{call_service, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+    use super::*;
+
+    #[actix_web::test]
+    async fn uploads_and_deletes_are_audited() {
+        let audit_log = std::env::temp_dir().join(format!("upload-audit-{}.log", uuid::Uuid::new_v4()));
+        let audit_path = audit_log.to_string_lossy().to_string();
+        let env = TestEnv::with(|c| c.audit_log_path = Some(audit_path));
+        init_audit_log();
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let resp = call_service(
+            &app,
+            TestRequest::put()
+                .uri("/api/upload/default/audited.txt")
+                .peer_addr("10.0.0.7:4000".parse().unwrap())
+                .set_payload("audited")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        let resp = call_service(
+            &app,
+            TestRequest::delete().uri("/api/file/default/audited.txt").peer_addr("10.0.0.7:4000".parse().unwrap()).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+
+        // 写入任务异步落盘
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        let lines = loop {
+            let content = std::fs::read_to_string(&audit_log).unwrap_or_default();
+            let lines: Vec<serde_json::Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+            if lines.len() >= 2 || tokio::time::Instant::now() > deadline {
+                break lines;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        };
+        assert_eq!(lines.len(), 2, "{:?}", lines);
+
+        let shape = |line: &serde_json::Value| {
+            (
+                line["operation"].as_str().unwrap().to_string(),
+                line["module"].as_str().unwrap().to_string(),
+                line["path"].as_str().unwrap().to_string(),
+                line["client_ip"].as_str().unwrap().to_string(),
+                line["success"].as_bool().unwrap(),
+            )
+        };
+        let expected = |operation: &str| (operation.to_string(), "default".to_string(), "audited.txt".to_string(), "10.0.0.7".to_string(), true);
+        assert_eq!(shape(&lines[0]), expected("upload"));
+        assert_eq!(shape(&lines[1]), expected("delete"));
+        assert_eq!(lines[0]["size"], 7);
+        for line in &lines {
+            assert!(DateTime::parse_from_rfc3339(line["timestamp"].as_str().unwrap()).is_ok());
+            assert!(line.get("error").is_none());
+        }
+
+        // 停止本测试的写入任务，其余测试不记录审计日志
+        *AUDIT_SENDER.write().unwrap() = None;
+        drop(env);
+        let _ = std::fs::remove_file(audit_log);
+    }
+}
@@ -1,26 +1,56 @@
 use std::time::Duration;
 use std::fs;
-use crate::utils::lock_utils;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::atomic::Ordering;
+use regex::Regex;
+use crate::config;
+use crate::services::upload_service;
+use crate::state::ACTIVE_UPLOADS;
+use crate::utils::{file_utils, lock_utils};
 
 pub async fn start_background_cleanup() {
-    let mut cleanup_interval = tokio::time::interval(Duration::from_secs(1800)); // 30分钟
+    let config = crate::config::get_config();
+    let mut lock_cleanup_interval = tokio::time::interval(config.lock_cleanup_interval);
+    let mut temp_cleanup_interval = tokio::time::interval(config.temp_file_cleanup_interval);
     
     loop {
-        cleanup_interval.tick().await;
-        
-        log::info!("执行后台清理任务...");
-        
-        // 清理过期的文件锁
-        let locks_cleaned = lock_utils::cleanup_file_locks().await;
-    // 清理过期的上传进度记录（6小时）
-    let progress_cleaned = crate::services::upload_service::cleanup_expired_progress(std::time::Duration::from_secs(6 * 3600)).await;
-        
-        // 清理临时文件
-        let (files_cleaned, size_freed) = cleanup_temp_files_internal().await
-            .unwrap_or((0, 0));
-        
-    log::info!("清理完成 - 文件锁: {}, 已清理上传进度: {}, 临时文件: {} (释放 {} bytes)", 
-          locks_cleaned, progress_cleaned, files_cleaned, size_freed);
+        tokio::select! {
+            _ = lock_cleanup_interval.tick() => {
+                log::info!("执行后台清理任务...");
+                
+                // 清理过期的文件锁
+                let locks_cleaned = lock_utils::cleanup_file_locks().await;
+                // 清理空闲的限流令牌桶
+                crate::utils::rate_limit::cleanup_idle_buckets();
+                // 清理过期的上传进度记录（6小时）
+                let progress_cleaned = crate::services::upload_service::cleanup_expired_progress(std::time::Duration::from_secs(6 * 3600)).await;
+                
+                log::info!("清理完成 - 文件锁: {}, 已清理上传进度: {}", locks_cleaned, progress_cleaned);
+            }
+            _ = temp_cleanup_interval.tick() => {
+                // 清理临时文件
+                let (files_cleaned, size_freed) = cleanup_temp_files_internal().await
+                    .unwrap_or((0, 0));
+                
+                log::info!("临时文件清理完成 - 临时文件: {} (释放 {} bytes)", files_cleaned, size_freed);
+
+                // 清理超过保留期的回收站条目（需在内容存储清理之前，释放其对存储条目的引用）
+                match crate::services::trash_service::purge_expired_trash(config.trash_retention).await {
+                    Ok((removed, freed)) if removed > 0 => log::info!("回收站清理完成 - 条目: {} (释放 {} bytes)", removed, freed),
+                    Ok(_) => {}
+                    Err(e) => log::warn!("回收站清理失败: {}", e),
+                }
+
+                // 清理内容存储中已无上传文件引用的条目
+                if config.dedup_enabled {
+                    match crate::services::file_service::cleanup_content_store().await {
+                        Ok((removed, freed)) => log::info!("内容存储清理完成 - 条目: {} (释放 {} bytes)", removed, freed),
+                        Err(e) => log::warn!("内容存储清理失败: {}", e),
+                    }
+                }
+            }
+        }
     }
 }
 
@@ -28,40 +58,56 @@ pub async fn cleanup_temp_files() -> Result<(usize, u64), String> {
     cleanup_temp_files_internal().await
 }
 
+static TEMP_FILE_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+// 只匹配服务自身生成的临时文件：分片 `<name>.part<N>` 及其摘要、原子写入的 `<name>.tmp.<uuid>`、会话清单和 tus 会话，
+// 避免误删 `notes.partial.txt` 这类恰好包含 `.part` 的正常文件
+fn is_temp_file(file_name: &str) -> bool {
+    let pattern = TEMP_FILE_PATTERN.get_or_init(|| {
+        Regex::new(r"(\.part\d+(\.sha256)?|\.tmp\.[0-9a-f-]+|\.manifest|\.tus(\.json)?)$").unwrap()
+    });
+    pattern.is_match(file_name)
+}
+
+// 删除目录中超过保留时长的分片、临时文件和会话清单，并递归进入分片会话子目录（清空后删除）
+fn cleanup_temp_dir(dir: &Path, max_age: Duration, cleaned_count: &mut usize, total_size: &mut u64) {
+    let Ok(files) = fs::read_dir(dir) else { return };
+    for file_entry in files.flatten() {
+        let Ok(file_name) = file_entry.file_name().into_string() else { continue };
+        if file_entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+            if upload_service::is_session_dir(&file_name) {
+                cleanup_temp_dir(&file_entry.path(), max_age, cleaned_count, total_size);
+                // 会话仍有未过期的分片时目录非空，删除失败，忽略
+                let _ = fs::remove_dir(file_entry.path());
+            }
+            continue;
+        }
+        if let Ok(metadata) = file_entry.metadata() {
+            let age = file_utils::file_timestamp(&metadata).elapsed().unwrap_or_default();
+            if age > max_age && is_temp_file(&file_name) {
+                if let Err(e) = fs::remove_file(file_entry.path()) {
+                    log::warn!("清理临时文件失败 {}: {}", file_name, e);
+                } else {
+                    *cleaned_count += 1;
+                    *total_size += metadata.len();
+                    log::debug!("清理临时文件: {}", file_name);
+                }
+            }
+        }
+    }
+}
+
 async fn cleanup_temp_files_internal() -> Result<(usize, u64), String> {
     tokio::task::spawn_blocking(|| -> Result<(usize, u64), String> {
-        let temp_dir = "./temp";
+        let temp_dir = config::temp_root();
+        let max_age = config::get_config().temp_file_max_age;
         let mut cleaned_count = 0usize;
         let mut total_size = 0u64;
 
         if let Ok(entries) = fs::read_dir(temp_dir) {
             for entry in entries.flatten() {
-                if let Ok(file_type) = entry.file_type() {
-                    if file_type.is_dir() {
-                        let module_temp_dir = entry.path();
-                        if let Ok(files) = fs::read_dir(&module_temp_dir) {
-                            for file_entry in files.flatten() {
-                                if let Ok(metadata) = file_entry.metadata() {
-                                    if let Ok(created) = metadata.created() {
-                                        let age = created.elapsed().unwrap_or_default();
-                                        if age > Duration::from_secs(24 * 3600) {
-                                            if let Ok(file_name) = file_entry.file_name().into_string() {
-                                                if file_name.ends_with(".part") || file_name.contains(".part") || file_name.contains(".tmp.") {
-                                                    if let Err(e) = fs::remove_file(file_entry.path()) {
-                                                        log::warn!("清理临时文件失败 {}: {}", file_name, e);
-                                                    } else {
-                                                        cleaned_count += 1;
-                                                        total_size += metadata.len();
-                                                        log::debug!("清理临时文件: {}", file_name);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+                if entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+                    cleanup_temp_dir(&entry.path(), max_age, &mut cleaned_count, &mut total_size);
                 }
             }
         }
@@ -70,6 +116,23 @@ async fn cleanup_temp_files_internal() -> Result<(usize, u64), String> {
     }).await.map_err(|e| format!("清理任务失败: {}", e))?
 }
 
+// 等待进行中的上传（单文件、分块、合并）结束，超时返回仍未完成的数量
+pub async fn wait_for_active_uploads(timeout: Duration) -> u64 {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut active = ACTIVE_UPLOADS.load(Ordering::Relaxed);
+    if active > 0 {
+        log::info!("等待 {} 个进行中的上传完成（最长 {} 秒）...", active, timeout.as_secs());
+    }
+    while active > 0 && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        active = ACTIVE_UPLOADS.load(Ordering::Relaxed);
+    }
+    if active > 0 {
+        log::warn!("等待上传完成超时，仍有 {} 个上传进行中", active);
+    }
+    active
+}
+
 pub async fn graceful_shutdown() {
     log::info!("接收到关闭信号，开始优雅关闭...");
     
@@ -81,4 +144,85 @@ pub async fn graceful_shutdown() {
     let _ = cleanup_temp_files_internal().await;
     
     log::info!("优雅关闭完成 - 清理文件锁: {}", locks_cleaned);
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::Pin;
+    use actix_web::dev::Payload;
+    use actix_web::error::PayloadError;
+    use actix_web::test::{call_service, TestRequest};
+    use actix_web::web::Bytes;
+    use futures_util::{Stream, StreamExt};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    #[test]
+    fn only_service_temp_files_are_cleaned() {
+        let uuid = uuid::Uuid::new_v4();
+        assert!(is_temp_file("x.part3"));
+        assert!(is_temp_file("x.part3.sha256"));
+        assert!(is_temp_file(&format!("x.tmp.{}", uuid)));
+        assert!(!is_temp_file("notes.partial.txt"));
+        assert!(!is_temp_file("x.part"));
+        assert!(!is_temp_file("report.tmp.txt"));
+
+        let env = TestEnv::with(|_| {});
+        let dir = env.root.join("temp");
+        for name in ["notes.partial.txt".to_string(), "x.part3".to_string(), format!("x.tmp.{}", uuid)] {
+            fs::write(dir.join(name), b"data").unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(20));
+
+        let (mut cleaned, mut size) = (0, 0);
+        cleanup_temp_dir(&dir, Duration::ZERO, &mut cleaned, &mut size);
+        assert_eq!((cleaned, size), (2, 8));
+        let remaining: Vec<String> = fs::read_dir(&dir).unwrap().flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect();
+        assert_eq!(remaining, ["notes.partial.txt"]);
+    }
+
+    #[actix_web::test]
+    async fn shutdown_waits_for_in_flight_uploads() {
+        let env = TestEnv::with(|_| {});
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        assert_eq!(ACTIVE_UPLOADS.load(Ordering::Relaxed), 0);
+
+        // 请求体先发送一半，收到信号后再发送剩余部分
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let body: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> = Box::pin(
+            futures_util::stream::once(async { Ok(Bytes::from_static(b"first half, ")) }).chain(futures_util::stream::once(async move {
+                let _ = released.await;
+                Ok(Bytes::from_static(b"second half"))
+            })),
+        );
+        let (req, _) = TestRequest::put()
+            .uri("/api/upload/default/inflight.txt")
+            .to_request()
+            .replace_payload(Payload::from(body));
+
+        let upload = call_service(&app, req);
+        let shutdown = async {
+            while ACTIVE_UPLOADS.load(Ordering::Relaxed) == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            let started = tokio::time::Instant::now();
+            let finish_upload = async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                let _ = release.send(());
+            };
+            let (remaining, ()) = futures_util::join!(wait_for_active_uploads(Duration::from_secs(10)), finish_upload);
+            (remaining, started.elapsed())
+        };
+        let (resp, (remaining, waited)) = futures_util::join!(upload, shutdown);
+        assert_eq!(resp.status(), 200);
+        assert_eq!(remaining, 0);
+        assert!(waited >= Duration::from_millis(300), "{:?}", waited);
+        assert_eq!(fs::read(env.uploads().join("default/inflight.txt")).unwrap(), b"first half, second half");
+
+        // 超时后返回仍在进行中的数量，不会无限等待
+        let _active = crate::handlers::upload_handlers::track_active_upload();
+        let started = tokio::time::Instant::now();
+        assert_eq!(wait_for_active_uploads(Duration::from_millis(200)).await, 1);
+        assert!(started.elapsed() >= Duration::from_millis(200) && started.elapsed() < Duration::from_secs(5));
+    }
+}
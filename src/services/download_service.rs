@@ -0,0 +1,452 @@
+use actix_web::http::header::HttpDate;
+use actix_web::web;
+use futures_util::stream::Stream;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc;
+use crate::config;
+use crate::utils::{file_utils, validation_utils, zip_utils::ZipStreamWriter};
+
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+// 响应头中的文件名编码: 控制字符、分隔符与非 ASCII 字符使用百分号编码
+const HEADER_ENCODE_SET: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+    .add(b',')
+    .add(b'%')
+    .add(b' ')
+    .add(b'"');
+
+// 待打包的文件
+struct ZipSource {
+    name: String,
+    path: PathBuf,
+    size: u64,
+    modified: chrono::NaiveDateTime,
+}
+
+// 将模块内指定的文件打包为 ZIP 流式输出，返回 (数据流, 被跳过的文件)；
+// 不存在的文件不会导致整个下载失败，而是被跳过并写入归档注释
+pub async fn stream_files_as_zip(
+    module: &str,
+    files: Vec<String>,
+) -> Result<(impl Stream<Item = Result<web::Bytes, actix_web::Error>>, Vec<String>), String> {
+    let module_path = PathBuf::from(format!("{}/{}", config::uploads_root(), module));
+    if !module_path.is_dir() {
+        return Err(format!("模块 '{}' 不存在", module));
+    }
+
+    let mut sources = Vec::new();
+    let mut skipped = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for name in files {
+        let name = name.trim_matches('/').to_string();
+        if !validation_utils::is_valid_module_path(&name) {
+            return Err(format!("文件路径包含非法字符: {}", name));
+        }
+        if !seen.insert(name.clone()) {
+            continue;
+        }
+
+        let path = module_path.join(&name);
+        let is_sidecar = path
+            .file_name()
+            .is_some_and(|n| file_utils::is_sidecar_file(&n.to_string_lossy()));
+        match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_file() && !is_sidecar => sources.push(zip_source(name, path, &metadata)),
+            _ => skipped.push(name),
+        }
+    }
+
+    let stream = spawn_zip_stream(sources, skipped.clone());
+    Ok((stream, skipped))
+}
+
+fn zip_source(name: String, path: PathBuf, metadata: &std::fs::Metadata) -> ZipSource {
+    let modified = metadata
+        .modified()
+        .map(|t| chrono::DateTime::<chrono::Local>::from(t).naive_local())
+        .unwrap_or_else(|_| chrono::Local::now().naive_local());
+    ZipSource { name, path, size: metadata.len(), modified }
+}
+
+// 递归收集目录下的文件（跳过 sidecar 元数据），条目名为相对 base 的路径
+fn collect_zip_sources(base: &Path, current: &Path, sources: &mut Vec<ZipSource>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(current)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        if file_type.is_dir() {
+            collect_zip_sources(base, &path, sources)?;
+        } else if file_type.is_file() && !file_utils::is_sidecar_file(&entry.file_name().to_string_lossy()) {
+            let name = path
+                .strip_prefix(base)
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            sources.push(zip_source(name, path, &entry.metadata()?));
+        }
+    }
+    Ok(())
+}
+
+// 将整个模块（或模块内的文件夹）打包为 ZIP 流式输出；空目录输出只含结束记录的合法空归档
+pub async fn stream_directory_as_zip(
+    module: &str,
+    folder: Option<&str>,
+) -> Result<impl Stream<Item = Result<web::Bytes, actix_web::Error>>, String> {
+    let module_path = PathBuf::from(format!("{}/{}", config::uploads_root(), module));
+    if !module_path.is_dir() {
+        return Err(format!("模块 '{}' 不存在", module));
+    }
+    let root = match folder {
+        Some(folder) => module_path.join(folder),
+        None => module_path,
+    };
+    if !root.is_dir() {
+        return Err(format!("文件夹 '{}' 不存在", folder.unwrap_or_default()));
+    }
+
+    let mut sources = tokio::task::spawn_blocking(move || -> std::io::Result<Vec<ZipSource>> {
+        let mut sources = Vec::new();
+        collect_zip_sources(&root, &root, &mut sources)?;
+        Ok(sources)
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))?
+    .map_err(|e| format!("读取目录失败: {}", e))?;
+    sources.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(spawn_zip_stream(sources, Vec::new()))
+}
+
+// 在后台任务中逐个写入文件，通过有界通道输出，数据随写随发，不在内存中缓存整个归档
+fn spawn_zip_stream(
+    sources: Vec<ZipSource>,
+    skipped: Vec<String>,
+) -> impl Stream<Item = Result<web::Bytes, actix_web::Error>> {
+    let (tx, rx) = mpsc::channel::<Result<web::Bytes, std::io::Error>>(16);
+    tokio::spawn(async move {
+        if let Err(e) = write_zip(sources, skipped, &tx).await {
+            log::error!("ZIP 打包失败: {}", e);
+            let _ = tx.send(Err(e)).await;
+        }
+    });
+
+    futures_util::stream::unfold(rx, |mut rx| async move {
+        let item = rx.recv().await?;
+        Some((item.map_err(actix_web::error::ErrorInternalServerError), rx))
+    })
+}
+
+async fn write_zip(
+    sources: Vec<ZipSource>,
+    mut skipped: Vec<String>,
+    tx: &mpsc::Sender<Result<web::Bytes, std::io::Error>>,
+) -> std::io::Result<()> {
+    let closed = || std::io::Error::new(std::io::ErrorKind::BrokenPipe, "客户端已断开");
+    let mut writer = ZipStreamWriter::new();
+
+    for source in sources {
+        // 文件在校验后被删除时跳过，此时尚未写出任何数据
+        let file = match tokio::fs::File::open(&source.path).await {
+            Ok(file) => file,
+            Err(e) => {
+                log::warn!("打包时文件不可读，跳过 {}: {}", source.path.display(), e);
+                skipped.push(source.name);
+                continue;
+            }
+        };
+
+        let header = writer.start_entry(&source.name, source.size, source.modified);
+        tx.send(Ok(web::Bytes::from(header))).await.map_err(|_| closed())?;
+
+        // 只输出登记时的大小，保证与文件头一致
+        let mut reader = file.take(source.size);
+        let mut hasher = crc32fast::Hasher::new();
+        let mut written = 0u64;
+        let mut buf = vec![0u8; READ_BUFFER_SIZE];
+        loop {
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            written += n as u64;
+            tx.send(Ok(web::Bytes::copy_from_slice(&buf[..n]))).await.map_err(|_| closed())?;
+        }
+        if written != source.size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("文件在打包过程中被截断: {}", source.path.display()),
+            ));
+        }
+
+        let descriptor = writer.finish_entry(hasher.finalize());
+        tx.send(Ok(web::Bytes::from(descriptor))).await.map_err(|_| closed())?;
+    }
+
+    let comment = if skipped.is_empty() {
+        String::new()
+    } else {
+        format!("skipped: {}", skipped.join(", "))
+    };
+    tx.send(Ok(web::Bytes::from(writer.finish(&comment)))).await.map_err(|_| closed())?;
+    Ok(())
+}
+
+// 用于响应头的文件列表编码（百分号编码，逗号分隔）
+pub fn encode_skipped_header(skipped: &[String]) -> String {
+    skipped
+        .iter()
+        .map(|name| percent_encoding::utf8_percent_encode(name, HEADER_ENCODE_SET).to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// 下载文件名: 模块路径的最后一段
+pub fn archive_name(module: &str) -> String {
+    let base = Path::new(module)
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "download".to_string());
+    format!("{}.zip", base)
+}
+
+// Range 请求头解析结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeRequest {
+    // 没有 Range 头（或不是 bytes 单位），返回整个文件
+    Full,
+    // 闭区间 [start, end]
+    Partial { start: u64, end: u64 },
+    // 范围无法满足或包含多个范围（暂不支持 multipart/byteranges），返回 416
+    Unsatisfiable,
+}
+
+// 解析单个 bytes 范围: "a-b"、"a-"、"-n"（最后 n 字节）
+pub fn parse_range(header: Option<&str>, size: u64) -> RangeRequest {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return RangeRequest::Full;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Unsatisfiable;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeRequest::Unsatisfiable;
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = match (start.is_empty(), end.is_empty()) {
+        (false, _) => {
+            let Ok(start) = start.parse::<u64>() else { return RangeRequest::Unsatisfiable };
+            let end = if end.is_empty() {
+                Some(size.saturating_sub(1))
+            } else {
+                end.parse::<u64>().ok().map(|end| end.min(size.saturating_sub(1)))
+            };
+            end.filter(|end| start <= *end && start < size).map(|end| (start, end))
+        }
+        (true, false) => {
+            let Ok(suffix) = end.parse::<u64>() else { return RangeRequest::Unsatisfiable };
+            (suffix > 0 && size > 0).then(|| (size.saturating_sub(suffix), size - 1))
+        }
+        (true, true) => None,
+    };
+    match range {
+        Some((start, end)) => RangeRequest::Partial { start, end },
+        None => RangeRequest::Unsatisfiable,
+    }
+}
+
+// 判断 If-None-Match 是否命中当前 ETag（支持 "*" 与逗号分隔的列表，按弱比较忽略 W/ 前缀）
+pub fn etag_matches(if_none_match: Option<&str>, etag: &str) -> bool {
+    let Some(header) = if_none_match else { return false };
+    let etag = etag.trim_start_matches("W/");
+    header
+        .split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+// 文件修改时间格式化为 Last-Modified 响应头使用的 HTTP 日期
+pub fn last_modified_header(metadata: &std::fs::Metadata) -> Option<String> {
+    metadata.modified().ok().map(|t| HttpDate::from(t).to_string())
+}
+
+// 定位模块内可下载的文件（不允许路径穿越，不暴露元数据 sidecar），返回路径与元数据
+pub async fn resolve_download(module: &str, filename: &str) -> Result<Option<(PathBuf, std::fs::Metadata)>, String> {
+    let filename = filename.trim_matches('/');
+    if !validation_utils::is_valid_module_name(module) || !validation_utils::is_valid_module_path(filename) {
+        return Err("文件路径包含非法字符".to_string());
+    }
+    let path = PathBuf::from(format!("{}/{}", config::uploads_root(), module)).join(filename);
+    if path.file_name().is_some_and(|n| file_utils::is_sidecar_file(&n.to_string_lossy())) {
+        return Ok(None);
+    }
+    match tokio::fs::metadata(&path).await {
+        Ok(metadata) if metadata.is_file() => Ok(Some((path, metadata))),
+        _ => Ok(None),
+    }
+}
+
+// 流式解压 zstd 压缩存储的文件，输出原始内容
+pub async fn stream_decompressed(
+    path: &Path,
+) -> std::io::Result<impl Stream<Item = Result<web::Bytes, actix_web::Error>>> {
+    use std::io::Write;
+
+    let file = tokio::fs::File::open(path).await?;
+    let decoder = zstd::stream::write::Decoder::new(Vec::new())?;
+    Ok(futures_util::stream::unfold(Some((file, decoder)), |state| async move {
+        let (mut file, mut decoder) = state?;
+        let mut buf = vec![0u8; READ_BUFFER_SIZE];
+        loop {
+            let result = match file.read(&mut buf).await {
+                // 读完后结束解码，输出剩余数据
+                Ok(0) => {
+                    return match decoder.flush().map(|_| decoder.into_inner()) {
+                        Ok(rest) if rest.is_empty() => None,
+                        Ok(rest) => Some((Ok(web::Bytes::from(rest)), None)),
+                        Err(e) => Some((Err(actix_web::error::ErrorInternalServerError(e)), None)),
+                    };
+                }
+                Ok(n) => decoder.write_all(&buf[..n]).and_then(|_| decoder.flush()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                return Some((Err(actix_web::error::ErrorInternalServerError(e)), None));
+            }
+            let output = std::mem::take(decoder.get_mut());
+            if !output.is_empty() {
+                return Some((Ok(web::Bytes::from(output)), Some((file, decoder))));
+            }
+        }
+    }))
+}
+
+// 从 start 开始流式读取 len 字节
+pub async fn stream_file_range(
+    path: &Path,
+    start: u64,
+    len: u64,
+) -> std::io::Result<impl Stream<Item = Result<web::Bytes, actix_web::Error>>> {
+    use tokio::io::AsyncSeekExt;
+
+    let mut file = tokio::fs::File::open(path).await?;
+    file.seek(std::io::SeekFrom::Start(start)).await?;
+    let reader = file.take(len);
+    Ok(futures_util::stream::unfold(reader, |mut reader| async move {
+        let mut buf = vec![0u8; READ_BUFFER_SIZE];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(web::Bytes::from(buf)), reader))
+            }
+            Err(e) => Some((Err(actix_web::error::ErrorInternalServerError(e)), reader)),
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    #[test]
+    fn parses_byte_ranges() {
+        assert_eq!(parse_range(None, 100), RangeRequest::Full);
+        assert_eq!(parse_range(Some("bytes=10-19"), 100), RangeRequest::Partial { start: 10, end: 19 });
+        assert_eq!(parse_range(Some("bytes=90-"), 100), RangeRequest::Partial { start: 90, end: 99 });
+        assert_eq!(parse_range(Some("bytes=-5"), 100), RangeRequest::Partial { start: 95, end: 99 });
+        assert_eq!(parse_range(Some("bytes=50-500"), 100), RangeRequest::Partial { start: 50, end: 99 });
+        assert_eq!(parse_range(Some("bytes=100-"), 100), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), RangeRequest::Unsatisfiable);
+    }
+
+    #[actix_web::test]
+    async fn middle_range_returns_the_requested_slice() {
+        let env = TestEnv::with(|_| {});
+        let content: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+        std::fs::write(env.uploads().join("default").join("video.bin"), &content).unwrap();
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let resp = call_service(
+            &app,
+            TestRequest::get().uri("/api/download/default/video.bin").insert_header(("Range", "bytes=1000-1999")).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 206);
+        assert_eq!(resp.headers().get("Content-Range").unwrap(), "bytes 1000-1999/4096");
+        let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap_or_else(|_| panic!("failed to read body"));
+        assert_eq!(&body[..], &content[1000..2000]);
+
+        let resp = call_service(
+            &app,
+            TestRequest::get().uri("/api/download/default/video.bin").insert_header(("Range", "bytes=5000-")).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 416);
+        assert_eq!(resp.headers().get("Content-Range").unwrap(), "bytes */4096");
+    }
+
+    // 读出归档中的 (文件名, 内容)，按文件名排序
+    fn zip_entries(body: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(body)).unwrap();
+        let mut entries: Vec<(String, Vec<u8>)> = (0..archive.len())
+            .map(|i| {
+                let mut entry = archive.by_index(i).unwrap();
+                let mut content = Vec::new();
+                std::io::Read::read_to_end(&mut entry, &mut content).unwrap();
+                (entry.name().to_string(), content)
+            })
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    #[actix_web::test]
+    async fn module_archives_match_the_files_on_disk() {
+        let env = TestEnv::with(|_| {});
+        let module = env.uploads().join("gallery");
+        let files: [(&str, &[u8]); 3] = [("cover.jpg", b"cover"), ("2024/a.txt", b"alpha"), ("2024/deep/b.txt", b"")];
+        for (path, content) in files {
+            std::fs::create_dir_all(module.join(path).parent().unwrap()).unwrap();
+            std::fs::write(module.join(path), content).unwrap();
+        }
+        std::fs::write(module.join(".cover.jpg.meta.json"), b"{}").unwrap();
+        std::fs::create_dir_all(env.uploads().join("empty")).unwrap();
+
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let get = |uri: &str| TestRequest::get().uri(uri).to_request();
+
+        let resp = call_service(&app, get("/api/modules/gallery/archive")).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("Content-Type").unwrap(), "application/zip");
+        assert_eq!(resp.headers().get("Content-Disposition").unwrap(), "attachment; filename=\"gallery.zip\"");
+        let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap_or_else(|_| panic!("failed to read body"));
+        let mut expected: Vec<(String, Vec<u8>)> = files.iter().map(|(p, c)| (p.to_string(), c.to_vec())).collect();
+        expected.sort();
+        assert_eq!(zip_entries(&body), expected);
+
+        let resp = call_service(&app, get("/api/folder/gallery/2024/archive")).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("Content-Disposition").unwrap(), "attachment; filename=\"2024.zip\"");
+        let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap_or_else(|_| panic!("failed to read body"));
+        assert_eq!(zip_entries(&body), [("a.txt".to_string(), b"alpha".to_vec()), ("deep/b.txt".to_string(), Vec::new())]);
+
+        // 空模块得到合法的空归档
+        let resp = call_service(&app, get("/api/modules/empty/archive")).await;
+        assert_eq!(resp.status(), 200);
+        let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap_or_else(|_| panic!("failed to read body"));
+        assert!(zip_entries(&body).is_empty());
+
+        for uri in ["/api/folder/gallery/../default/archive", "/api/folder/gallery/2024%2F..%2F..%2Fdefault/archive"] {
+            let resp = call_service(&app, get(uri)).await;
+            assert_eq!(resp.status(), 400, "{}", uri);
+        }
+        let resp = call_service(&app, get("/api/modules/missing/archive")).await;
+        assert_eq!(resp.status(), 404);
+    }
+}
@@ -1,64 +1,361 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use actix_web::web;
+use futures_util::stream::{FuturesUnordered, Stream, StreamExt};
 use tokio::fs as tokio_fs;
-use crate::models::{FileInfo, ModuleInfo};
-use crate::utils::file_utils;
+use tokio::sync::{mpsc, Semaphore};
+use crate::config::{self, ConflictPolicy};
+use crate::services::{audit_service, download_service, system_service, trash_service};
+use crate::models::{DirectoryListing, FileChanges, FileDetails, FileInfo, FilePage, FileMove, ModuleInfo, ModulePage, ModuleStats, ModuleTreeNode, MoveResult, SearchResult, DeleteResult, ModuleBatchResult, TypeStats};
+use crate::state::ACTIVE_SCANS;
+use crate::utils::{file_utils, lock_utils, validation_utils};
+use crate::utils::file_utils::FileFilter;
 
-pub async fn create_module_directory(module_name: &str) -> Result<(), String> {
-    let module_path = format!("./uploads/{}", module_name);
-    
+// 同名策略为 fail 且目标已存在时返回的错误信息（处理器据此返回 409）
+pub const FILE_EXISTS_ERROR: &str = "目标文件已存在";
+// 写入目标经由符号链接指向模块目录之外，或要覆盖的目标不是普通文件（处理器据此返回 400）
+pub const UNSAFE_TARGET_PATH_ERROR: &str = "目标路径不安全";
+// 存储路径（绝对路径）超过 max_path_bytes 时的错误前缀（处理器据此返回 400）
+pub const PATH_TOO_LONG_ERROR: &str = "存储路径过长";
+// 移动的源文件不存在（处理器据此返回 404）
+pub const SOURCE_NOT_FOUND_ERROR: &str = "源文件不存在";
+// 重命名模块时的源模块不存在 / 目标模块已存在（处理器据此返回 404 / 409）
+pub const MODULE_NOT_FOUND_ERROR: &str = "模块不存在";
+pub const MODULE_EXISTS_ERROR: &str = "目标模块已存在";
+
+// 批量移动时同时执行的重命名数量
+const BULK_MOVE_CONCURRENCY: usize = 8;
+// 批量删除时同时执行的删除数量
+const BULK_DELETE_CONCURRENCY: usize = 8;
+// 批量创建模块时同时创建的目录数量
+const BULK_CREATE_CONCURRENCY: usize = 8;
+// 强制删除模块时的重试次数与首次退避间隔（之后每次翻倍）
+const FORCE_DELETE_RETRIES: u32 = 3;
+const FORCE_DELETE_BACKOFF: std::time::Duration = std::time::Duration::from_millis(200);
+
+// 创建模块目录，返回模块此前是否已存在。同名模块的并发创建串行执行，避免检查与创建之间的竞争
+pub async fn create_module_directory(module_name: &str) -> Result<bool, String> {
+    let module_path = format!("{}/{}", config::uploads_root(), module_name);
+
+    let lock = module_lock(module_name).await;
+    let _guard = lock.write().await;
+
+    let already_exists = tokio_fs::metadata(&module_path).await.is_ok_and(|m| m.is_dir());
     tokio_fs::create_dir_all(&module_path).await
         .map_err(|e| format!("创建模块目录失败: {}", e))?;
 
-    let temp_dir = format!("./temp/{}", module_name);
+    let temp_dir = format!("{}/{}", config::temp_root(), module_name);
     let _ = tokio_fs::create_dir_all(&temp_dir).await;
 
-    Ok(())
+    Ok(already_exists)
+}
+
+// 批量创建模块：每个名称单独校验与创建，重复名称与已存在的模块只标记状态，结果按输入顺序返回
+pub async fn create_module_directories(names: Vec<String>) -> Vec<ModuleBatchResult> {
+    let mut seen = std::collections::HashSet::new();
+    let entries: Vec<(String, Option<(&'static str, String)>)> = names
+        .into_iter()
+        .map(|name| {
+            let name = name.trim().to_string();
+            let rejected = if name.is_empty() {
+                Some(("invalid", "模块名称不能为空".to_string()))
+            } else if !validation_utils::is_valid_module_name(&name) {
+                Some(("invalid", "模块名称包含非法字符".to_string()))
+            } else if !seen.insert(name.clone()) {
+                Some(("duplicate", "请求中重复的模块名称".to_string()))
+            } else {
+                None
+            };
+            (name, rejected)
+        })
+        .collect();
+
+    let mut results: Vec<(usize, ModuleBatchResult)> = futures_util::stream::iter(entries.into_iter().enumerate())
+        .map(|(index, (name, rejected))| async move {
+            let result = match rejected {
+                Some((status, error)) => ModuleBatchResult {
+                    name,
+                    status,
+                    success: status == "duplicate",
+                    error: Some(error),
+                },
+                None => match create_module_directory(&name).await {
+                    Ok(already_exists) => ModuleBatchResult {
+                        name,
+                        status: if already_exists { "exists" } else { "created" },
+                        success: true,
+                        error: None,
+                    },
+                    Err(e) => ModuleBatchResult {
+                        name,
+                        status: "failed",
+                        success: false,
+                        error: Some(e),
+                    },
+                },
+            };
+            (index, result)
+        })
+        .buffer_unordered(BULK_CREATE_CONCURRENCY)
+        .collect()
+        .await;
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
 }
 
 pub async fn create_submodule_directory(module_name: &str, submodule_name: &str) -> Result<(), String> {
     // 创建 uploads/{module_name}/{submodule_name}
-    let sub_path = format!("./uploads/{}/{}", module_name, submodule_name);
+    let sub_path = format!("{}/{}/{}", config::uploads_root(), module_name, submodule_name);
     tokio_fs::create_dir_all(&sub_path).await
         .map_err(|e| format!("创建子模块目录失败: {}", e))?;
 
     // 同步创建 temp 子目录
-    let temp_sub = format!("./temp/{}/{}", module_name, submodule_name);
+    let temp_sub = format!("{}/{}/{}", config::temp_root(), module_name, submodule_name);
     let _ = tokio_fs::create_dir_all(&temp_sub).await;
 
     Ok(())
 }
 
-pub async fn get_all_modules_info() -> Result<Vec<ModuleInfo>, String> {
-    let uploads_dir = "./uploads";
-    
-    let modules_info = tokio::task::spawn_blocking(move || -> Result<Vec<ModuleInfo>, String> {
-        let mut modules_info = Vec::new();
-        
+// 模块列表的分页、排序与过滤参数（page 从 1 开始）
+#[derive(Debug, Clone)]
+pub struct ModuleListOptions {
+    pub page: usize,
+    pub page_size: usize,
+    pub sort_by: ModuleSortKey,
+    pub descending: bool,
+    // 模块名包含该子串（不区分大小写）
+    pub name_filter: Option<String>,
+    // 为 false 时不递归统计文件数与大小，快速返回列表
+    pub with_stats: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleSortKey {
+    Name,
+    Size,
+    Count,
+    Created,
+}
+
+impl Default for ModuleListOptions {
+    fn default() -> Self {
+        Self {
+            page: 1,
+            page_size: DEFAULT_PAGE_SIZE,
+            sort_by: ModuleSortKey::Name,
+            descending: false,
+            name_filter: None,
+            with_stats: true,
+        }
+    }
+}
+
+impl ModuleListOptions {
+    // 从查询参数解析: page, page_size, sort_by=name|size|count|created, order=asc|desc, name_filter, with_stats
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let param = |key: &str| query.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+        if let Some(raw) = param("page") {
+            options.page = raw.parse::<usize>().ok().filter(|p| *p >= 1)
+                .ok_or_else(|| format!("page 参数需为正整数: {}", raw))?;
+        }
+        if let Some(raw) = param("page_size") {
+            let page_size = raw.parse::<usize>().ok().filter(|p| *p >= 1)
+                .ok_or_else(|| format!("page_size 参数需为正整数: {}", raw))?;
+            options.page_size = page_size.min(MAX_PAGE_SIZE);
+        }
+        if let Some(raw) = param("sort_by") {
+            options.sort_by = match raw.to_lowercase().as_str() {
+                "name" => ModuleSortKey::Name,
+                "size" => ModuleSortKey::Size,
+                "count" => ModuleSortKey::Count,
+                "created" => ModuleSortKey::Created,
+                _ => return Err(format!("无效的 sort_by 参数: {}（可选 name / size / count / created）", raw)),
+            };
+        }
+        if let Some(raw) = param("order") {
+            options.descending = match raw.to_lowercase().as_str() {
+                "asc" => false,
+                "desc" => true,
+                _ => return Err(format!("无效的 order 参数: {}（可选 asc / desc）", raw)),
+            };
+        }
+        options.name_filter = param("name_filter").map(str::to_lowercase);
+        if let Some(raw) = param("with_stats") {
+            options.with_stats = match raw.to_lowercase().as_str() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return Err(format!("无效的 with_stats 参数: {}", raw)),
+            };
+        }
+        if !options.with_stats && matches!(options.sort_by, ModuleSortKey::Size | ModuleSortKey::Count) {
+            return Err("按 size / count 排序需要统计信息，不能与 with_stats=false 同时使用".to_string());
+        }
+        Ok(options)
+    }
+}
+
+pub async fn get_all_modules_info(options: ModuleListOptions) -> Result<ModulePage, String> {
+    let uploads_dir = config::uploads_root();
+    let name_filter = options.name_filter.clone();
+
+    let mut modules = tokio::task::spawn_blocking(move || -> Result<Vec<(ModuleInfo, PathBuf)>, String> {
+        let mut modules = Vec::new();
+
         let entries = fs::read_dir(uploads_dir)
             .map_err(|e| format!("读取上传目录失败: {}", e))?;
-            
+
         for entry in entries {
             let entry = entry.map_err(|e| format!("读取目录项失败: {}", e))?;
-            if let Ok(file_type) = entry.file_type() {
-                if file_type.is_dir() {
-                    let name = entry.file_name().to_string_lossy().to_string();
-                    if name != "." && name != ".." {
-                        let module_info = file_utils::get_module_info(&entry)
-                            .map_err(|e| format!("获取模块信息失败: {}", e))?;
-                        modules_info.push(module_info);
-                    }
-                }
+            if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == "." || name == ".." || file_utils::is_reserved_dir(&name) {
+                continue;
             }
+            if name_filter.as_ref().is_some_and(|filter| !name.to_lowercase().contains(filter)) {
+                continue;
+            }
+            let path = entry.path();
+            modules.push((ModuleInfo {
+                created_time: file_utils::module_created_time(&path),
+                name,
+                file_count: None,
+                total_size: None,
+            }, path));
         }
-        Ok(modules_info)
+        Ok(modules)
     }).await.map_err(|e| format!("阻塞任务失败: {}", e))??;
 
-    Ok(modules_info)
+    // 按大小或文件数排序时需要先统计全部模块；其余情况只统计当前页的模块
+    let semaphore = Arc::new(Semaphore::new(config::get_config().max_scan_concurrency.max(1)));
+    let needs_all_stats = matches!(options.sort_by, ModuleSortKey::Size | ModuleSortKey::Count);
+    if needs_all_stats {
+        fill_module_stats(&mut modules, &semaphore).await;
+    }
+
+    modules.sort_by(|(a, _), (b, _)| {
+        let ordering = match options.sort_by {
+            ModuleSortKey::Name => a.name.cmp(&b.name),
+            ModuleSortKey::Size => a.total_size.cmp(&b.total_size),
+            ModuleSortKey::Count => a.file_count.cmp(&b.file_count),
+            ModuleSortKey::Created => a.created_time.cmp(&b.created_time),
+        };
+        let ordering = if options.descending { ordering.reverse() } else { ordering };
+        ordering.then_with(|| a.name.cmp(&b.name))
+    });
+
+    let total = modules.len();
+    // 页码超出范围时返回空列表
+    let start = (options.page - 1).saturating_mul(options.page_size).min(total);
+    let end = start.saturating_add(options.page_size).min(total);
+    let mut page: Vec<(ModuleInfo, PathBuf)> = modules.drain(start..end).collect();
+    if options.with_stats && !needs_all_stats {
+        fill_module_stats(&mut page, &semaphore).await;
+    }
+
+    Ok(ModulePage {
+        modules: page.into_iter().map(|(info, _)| info).collect(),
+        total,
+        page: options.page,
+        page_size: options.page_size,
+    })
+}
+
+// 统计各模块的文件数与总大小，同时遍历的模块数受 semaphore 限制
+async fn fill_module_stats(modules: &mut [(ModuleInfo, PathBuf)], semaphore: &Arc<Semaphore>) {
+    let stats = futures_util::future::join_all(modules.iter().map(|(info, path)| {
+        let (name, path) = (info.name.clone(), path.clone());
+        run_scan(semaphore.clone(), move || file_utils::module_file_stats(&name, &path))
+    }))
+    .await;
+    for ((info, _), stats) in modules.iter_mut().zip(stats) {
+        match stats {
+            Ok((file_count, total_size)) => {
+                info.file_count = Some(file_count);
+                info.total_size = Some(total_size);
+            }
+            Err(e) => log::warn!("统计模块 '{}' 失败: {}", info.name, e),
+        }
+    }
+}
+
+// 所有模块及其各级子模块组成的目录树，嵌套层数受 max_walk_depth 限制，
+// 子目录节点总数受 max_tree_nodes 限制；同时遍历的模块数受 max_scan_concurrency 限制
+pub async fn get_module_tree() -> Result<Vec<ModuleTreeNode>, String> {
+    let uploads_dir = config::uploads_root();
+    let config = config::get_config();
+    let max_depth = config.max_walk_depth;
+
+    let module_dirs = tokio::task::spawn_blocking(move || -> Result<Vec<(PathBuf, String)>, String> {
+        let mut module_dirs = Vec::new();
+        let entries = fs::read_dir(uploads_dir)
+            .map_err(|e| format!("读取上传目录失败: {}", e))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("读取目录项失败: {}", e))?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) || file_utils::is_reserved_dir(&name) {
+                continue;
+            }
+            module_dirs.push((entry.path(), name));
+        }
+        // 按名称顺序展开，节点数上限耗尽时靠前的模块保持完整
+        module_dirs.sort_by(|a, b| a.1.cmp(&b.1));
+        Ok(module_dirs)
+    }).await.map_err(|e| format!("阻塞任务失败: {}", e))??;
+
+    let semaphore = Arc::new(Semaphore::new(config.max_scan_concurrency.max(1)));
+    let node_budget = Arc::new(AtomicUsize::new(config.max_tree_nodes));
+    let trees = futures_util::future::join_all(module_dirs.into_iter().map(|(path, name)| {
+        let node_budget = node_budget.clone();
+        run_scan(semaphore.clone(), move || file_utils::build_module_tree(&path, name, 1, max_depth, &node_budget))
+    }))
+    .await;
+
+    trees
+        .into_iter()
+        .map(|tree| tree?.map_err(|e| format!("读取模块目录失败: {}", e)))
+        .collect()
+}
+
+// 统计模块内（含子目录）的文件总数、总大小及按类型的分布；模块不存在时返回 None
+pub async fn get_module_stats(module: &str) -> Result<Option<ModuleStats>, String> {
+    let module = module.to_string();
+    tokio::task::spawn_blocking(move || -> Result<Option<ModuleStats>, String> {
+        let module_path = PathBuf::from(format!("{}/{}", config::uploads_root(), module));
+        if !module_path.is_dir() {
+            return Ok(None);
+        }
+
+        let mut by_type: HashMap<String, (usize, u64)> = HashMap::new();
+        file_utils::collect_type_stats(&module_path, &mut by_type)
+            .map_err(|e| format!("统计模块文件失败: {}", e))?;
+
+        let file_count = by_type.values().map(|(count, _)| count).sum();
+        let total_size = by_type.values().map(|(_, size)| size).sum();
+        Ok(Some(ModuleStats {
+            module,
+            file_count,
+            total_size,
+            by_type: by_type
+                .into_iter()
+                .map(|(file_type, (count, size))| (file_type, TypeStats { count, size }))
+                .collect(),
+        }))
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))?
 }
 
 pub async fn get_submodules(module: &str) -> Result<Vec<String>, String> {
-    let module_path = format!("./uploads/{}", module);
+    let module_path = format!("{}/{}", config::uploads_root(), module);
 
     let submodules = tokio::task::spawn_blocking(move || -> Result<Vec<String>, String> {
         let mut subs = Vec::new();
@@ -81,86 +378,1395 @@ pub async fn get_submodules(module: &str) -> Result<Vec<String>, String> {
     Ok(submodules)
 }
 
-pub async fn get_module_files(module: &str) -> Result<Vec<FileInfo>, String> {
-    let module_path = format!("./uploads/{}", module);
+// 文件列表每页默认数量与上限
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSortKey {
+    Name,
+    Size,
+    Time,
+}
+
+// 文件列表的分页与排序参数（page 从 1 开始）
+#[derive(Debug, Clone, Copy)]
+pub struct FileListOptions {
+    pub page: usize,
+    pub page_size: usize,
+    pub sort_by: FileSortKey,
+    pub descending: bool,
+}
+
+impl Default for FileListOptions {
+    fn default() -> Self {
+        Self {
+            page: 1,
+            page_size: DEFAULT_PAGE_SIZE,
+            sort_by: FileSortKey::Time,
+            descending: true,
+        }
+    }
+}
+
+impl FileListOptions {
+    // 从查询参数解析: page, page_size（超过上限时截断为上限）, sort_by=name|size|time, order=asc|desc
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let param = |key: &str| query.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+        if let Some(raw) = param("page") {
+            options.page = raw.parse::<usize>().ok().filter(|p| *p >= 1)
+                .ok_or_else(|| format!("page 参数需为正整数: {}", raw))?;
+        }
+        if let Some(raw) = param("page_size") {
+            let page_size = raw.parse::<usize>().ok().filter(|p| *p >= 1)
+                .ok_or_else(|| format!("page_size 参数需为正整数: {}", raw))?;
+            options.page_size = page_size.min(MAX_PAGE_SIZE);
+        }
+        if let Some(raw) = param("sort_by") {
+            options.sort_by = match raw.to_lowercase().as_str() {
+                "name" => FileSortKey::Name,
+                "size" => FileSortKey::Size,
+                "time" => FileSortKey::Time,
+                _ => return Err(format!("无效的 sort_by 参数: {}（可选 name / size / time）", raw)),
+            };
+        }
+        if let Some(raw) = param("order") {
+            options.descending = match raw.to_lowercase().as_str() {
+                "asc" => false,
+                "desc" => true,
+                _ => return Err(format!("无效的 order 参数: {}（可选 asc / desc）", raw)),
+            };
+        }
+        Ok(options)
+    }
+}
+
+pub async fn get_module_files(
+    module: &str,
+    filter: FileFilter,
+    options: FileListOptions,
+) -> Result<FilePage, String> {
+    let module_path = format!("{}/{}", config::uploads_root(), module);
     
     if !Path::new(&module_path).exists() {
         return Err(format!("模块 '{}' 不存在", module));
     }
 
-    // Move owned data into the blocking closure to avoid borrowing non-'static references
-    let module_path_owned = module_path.clone();
+    let semaphore = Arc::new(Semaphore::new(config::get_config().max_scan_concurrency.max(1)));
+    let files = collect_files_concurrent(PathBuf::from(module_path), module, Arc::new(filter), semaphore)
+        .await
+        .map_err(|e| format!("收集文件失败: {}", e))?;
+
+    // 排序与分页在阻塞线程中完成，大模块排序不占用异步工作线程
+    tokio::task::spawn_blocking(move || {
+        let mut files = files;
+        files.sort_by(|a, b| {
+            let ordering = match options.sort_by {
+                FileSortKey::Name => a.filename.cmp(&b.filename),
+                FileSortKey::Size => a.size.cmp(&b.size),
+                FileSortKey::Time => a.upload_time.cmp(&b.upload_time),
+            };
+            let ordering = if options.descending { ordering.reverse() } else { ordering };
+            // 主键相同时按路径排序，保证翻页结果稳定
+            ordering
+                .then_with(|| a.relative_path.cmp(&b.relative_path))
+                .then_with(|| a.filename.cmp(&b.filename))
+        });
+
+        let total = files.len();
+        // 页码超出范围时返回空列表
+        let start = (options.page - 1).saturating_mul(options.page_size).min(total);
+        let end = start.saturating_add(options.page_size).min(total);
+        let files = files.drain(start..end).collect();
+
+        FilePage {
+            files,
+            total,
+            page: options.page,
+            page_size: options.page_size,
+        }
+    }).await.map_err(|e| format!("阻塞任务失败: {}", e))
+}
+
+// 获取模块内某个目录的直接内容（不递归），目录不存在时返回 Ok(None)
+pub async fn list_directory(module: &str, folder: &str) -> Result<Option<DirectoryListing>, String> {
+    let module_path = PathBuf::from(format!("{}/{}", config::uploads_root(), module));
+    let folder = folder.trim_matches('/').to_string();
     let module_owned = module.to_string();
-    let files = tokio::task::spawn_blocking(move || -> Result<Vec<FileInfo>, String> {
-        let mut files: Vec<FileInfo> = Vec::new();
-        let base = PathBuf::from(module_path_owned);
-        file_utils::collect_files_recursive(&base, "", &mut files, &module_owned)
-            .map_err(|e| format!("收集文件失败: {}", e))?;
-        
-        files.sort_by(|a, b| b.upload_time.cmp(&a.upload_time));
+
+    tokio::task::spawn_blocking(move || -> Result<Option<DirectoryListing>, String> {
+        let full_path = if folder.is_empty() {
+            module_path.clone()
+        } else {
+            module_path.join(&folder)
+        };
+        if !full_path.is_dir() {
+            return Ok(None);
+        }
+
+        let (mut files, subdirs) = file_utils::read_directory_level(&module_path, &folder, &module_owned, &FileFilter::default())
+            .map_err(|e| format!("读取目录失败: {}", e))?;
+
+        let mut folders: Vec<String> = subdirs
+            .iter()
+            .map(|p| p.rsplit('/').next().unwrap_or(p).to_string())
+            .collect();
+        folders.sort();
+        files.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+        Ok(Some(DirectoryListing { folders, files }))
+    }).await.map_err(|e| format!("阻塞任务失败: {}", e))?
+}
+
+// 获取模块中自 since 之后修改过且匹配过滤条件的文件（since 为 None 时不限制修改时间）
+pub async fn get_module_changes(
+    module: &str,
+    since: Option<DateTime<Utc>>,
+    mut filter: FileFilter,
+) -> Result<FileChanges, String> {
+    let module_path = format!("{}/{}", config::uploads_root(), module);
+
+    if !Path::new(&module_path).exists() {
+        return Err(format!("模块 '{}' 不存在", module));
+    }
+
+    // 在扫描前记录游标，避免遗漏扫描期间发生的修改
+    let cursor = Utc::now();
+    let semaphore = Arc::new(Semaphore::new(config::get_config().max_scan_concurrency.max(1)));
+    if let Some(since) = since {
+        filter.modified_after = Some(since.into());
+    }
+    let mut files = collect_files_concurrent(PathBuf::from(&module_path), module, Arc::new(filter), semaphore)
+        .await
+        .map_err(|e| format!("收集文件失败: {}", e))?;
+
+    files.sort_by(|a, b| b.upload_time.cmp(&a.upload_time));
+
+    // 首次同步没有需要告知的删除；之后被重新创建的路径已包含在 files 中
+    let mut deleted = match since {
+        Some(since) => audit_service::removed_since(module, since).await,
+        None => Vec::new(),
+    };
+    deleted.retain(|path| !Path::new(&module_path).join(path).exists());
+
+    Ok(FileChanges {
+        files,
+        deleted,
+        cursor: cursor.to_rfc3339(),
+    })
+}
+
+// 按文件名子串（及过滤器中的其余条件）搜索文件；module 为 None 时搜索全部模块。
+// 命中数达到 limit 后停止遍历，返回结果与是否截断
+pub async fn search_files(
+    filter: FileFilter,
+    module: Option<String>,
+    limit: usize,
+) -> Result<SearchResult, String> {
+    tokio::task::spawn_blocking(move || -> Result<SearchResult, String> {
+        let uploads = Path::new(config::uploads_root());
+        let modules = match module {
+            Some(module) => {
+                if !uploads.join(&module).is_dir() {
+                    return Err(format!("模块 '{}' 不存在", module));
+                }
+                vec![module]
+            }
+            None => {
+                let mut modules: Vec<String> = fs::read_dir(uploads)
+                    .map_err(|e| format!("读取上传目录失败: {}", e))?
+                    .flatten()
+                    .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .filter(|name| !file_utils::is_reserved_dir(name))
+                    .collect();
+                modules.sort();
+                modules
+            }
+        };
+
+        let mut files = Vec::new();
+        let mut truncated = false;
+        'modules: for module in &modules {
+            let base_path = uploads.join(module);
+            let mut pending = vec![String::new()];
+            while let Some(current) = pending.pop() {
+                let (level_files, subdirs) = match file_utils::read_directory_level(&base_path, &current, module, &filter) {
+                    Ok(level) => level,
+                    Err(e) => {
+                        log::warn!("搜索时读取目录失败 {}/{}: {}", module, current, e);
+                        continue;
+                    }
+                };
+                for file in level_files {
+                    if files.len() >= limit {
+                        truncated = true;
+                        break 'modules;
+                    }
+                    files.push(file);
+                }
+                pending.extend(subdirs);
+            }
+        }
+
+        files.sort_by(|a, b| {
+            a.module
+                .cmp(&b.module)
+                .then_with(|| a.relative_path.cmp(&b.relative_path))
+                .then_with(|| a.filename.cmp(&b.filename))
+        });
+        Ok(SearchResult { files, truncated })
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))?
+}
+
+// 并发遍历目录，将发现的文件逐个发送到通道中；每层目录的读取都需先获取信号量许可，
+// 以此限制单个请求对磁盘 IO 的并发占用。遍历出错时发送错误并停止，接收端关闭时提前结束
+pub async fn walk_files_concurrent(
+    base_path: PathBuf,
+    module: &str,
+    filter: Arc<FileFilter>,
+    semaphore: Arc<Semaphore>,
+    tx: mpsc::Sender<std::io::Result<FileInfo>>,
+) {
+    let base_path = Arc::new(base_path);
+    let module = Arc::new(module.to_string());
+    let mut pending = FuturesUnordered::new();
+
+    pending.push(scan_directory_level(
+        base_path.clone(), String::new(), module.clone(), filter.clone(), semaphore.clone(),
+    ));
+
+    while let Some(result) = pending.next().await {
+        let (level_files, subdirs) = match result {
+            Ok(level) => level,
+            Err(e) => {
+                let _ = tx.send(Err(e)).await;
+                return;
+            }
+        };
+        for file in level_files {
+            if tx.send(Ok(file)).await.is_err() {
+                return;
+            }
+        }
+        for subdir in subdirs {
+            pending.push(scan_directory_level(
+                base_path.clone(), subdir, module.clone(), filter.clone(), semaphore.clone(),
+            ));
+        }
+    }
+}
+
+// 并发遍历目录并收集全部文件信息
+pub async fn collect_files_concurrent(
+    base_path: PathBuf,
+    module: &str,
+    filter: Arc<FileFilter>,
+    semaphore: Arc<Semaphore>,
+) -> std::io::Result<Vec<FileInfo>> {
+    let (tx, mut rx) = mpsc::channel(1024);
+    let walker = walk_files_concurrent(base_path, module, filter, semaphore, tx);
+    let collector = async move {
+        let mut files = Vec::new();
+        while let Some(file) = rx.recv().await {
+            files.push(file?);
+        }
         Ok(files)
-    }).await.map_err(|e| format!("阻塞任务失败: {}", e))??;
+    };
 
-    Ok(files)
+    let ((), files) = tokio::join!(walker, collector);
+    files
+}
+
+// 以 NDJSON 流的形式输出模块文件列表：遍历任务与响应体之间使用有界通道，
+// 服务端内存占用不随模块大小增长
+pub fn stream_module_files(
+    module: &str,
+    filter: FileFilter,
+) -> Result<impl Stream<Item = Result<web::Bytes, actix_web::Error>>, String> {
+    let module_path = format!("{}/{}", config::uploads_root(), module);
+
+    if !Path::new(&module_path).exists() {
+        return Err(format!("模块 '{}' 不存在", module));
+    }
+
+    let (tx, rx) = mpsc::channel(256);
+    let semaphore = Arc::new(Semaphore::new(config::get_config().max_scan_concurrency.max(1)));
+    let module_owned = module.to_string();
+    tokio::spawn(async move {
+        walk_files_concurrent(PathBuf::from(module_path), &module_owned, Arc::new(filter), semaphore, tx).await;
+    });
+
+    Ok(futures_util::stream::unfold(rx, |mut rx| async move {
+        let item = rx.recv().await?;
+        let line = match item {
+            Ok(file) => serde_json::to_vec(&file)
+                .map(|mut line| {
+                    line.push(b'\n');
+                    web::Bytes::from(line)
+                })
+                .map_err(actix_web::error::ErrorInternalServerError),
+            Err(e) => {
+                log::error!("流式列出文件失败: {}", e);
+                Err(actix_web::error::ErrorInternalServerError(e))
+            }
+        };
+        Some((line, rx))
+    }))
+}
+
+async fn scan_directory_level(
+    base_path: Arc<PathBuf>,
+    current_path: String,
+    module: Arc<String>,
+    filter: Arc<FileFilter>,
+    semaphore: Arc<Semaphore>,
+) -> std::io::Result<(Vec<FileInfo>, Vec<String>)> {
+    run_scan(semaphore, move || file_utils::read_directory_level(&base_path, &current_path, &module, &filter))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+// 获取扫描许可后在阻塞线程中执行一次目录读取，并计入当前活跃扫描数
+async fn run_scan<T: Send + 'static>(
+    semaphore: Arc<Semaphore>,
+    scan: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, String> {
+    let _permit = semaphore.acquire_owned().await
+        .map_err(|e| format!("获取扫描许可失败: {}", e))?;
+
+    ACTIVE_SCANS.fetch_add(1, Ordering::Relaxed);
+    let _guard = scopeguard::guard((), |_| {
+        ACTIVE_SCANS.fetch_sub(1, Ordering::Relaxed);
+    });
+
+    tokio::task::spawn_blocking(scan)
+        .await
+        .map_err(|e| format!("阻塞任务失败: {}", e))
+}
+
+// 校验完整存储路径的字节数不超过 max_path_bytes
+pub fn check_path_length(path: &Path) -> Result<(), String> {
+    let max_path_bytes = config::get_config().max_path_bytes;
+    if validation_utils::is_valid_path_length(path, max_path_bytes) {
+        Ok(())
+    } else {
+        Err(format!("{}，超过 {} 字节限制: {}", PATH_TOO_LONG_ERROR, max_path_bytes, path.display()))
+    }
 }
 
 pub async fn build_file_path(
     module: &str,
     original_filename: &str,
     relative_path: &Option<String>,
+    on_conflict: ConflictPolicy,
 ) -> Result<String, String> {
-    let module_path = format!("./uploads/{}", module);
-    
+    let module_path = format!("{}/{}", config::uploads_root(), module);
+
+    // 构建初始文件路径
+    let initial_path = match relative_path {
+        Some(rel_path) => Path::new(&module_path).join(rel_path).join(original_filename),
+        None => Path::new(&module_path).join(original_filename),
+    };
+
+    // 写入前校验完整存储路径长度，避免在写入时才得到晦涩的系统错误
+    check_path_length(&initial_path)?;
+
     // 确保模块目录存在
     tokio_fs::create_dir_all(&module_path).await
         .map_err(|e| format!("创建模块目录失败: {}", e))?;
 
-    // 构建初始文件路径
-    let initial_filepath = if let Some(rel_path) = relative_path {
-        let full_path = Path::new(&module_path).join(rel_path).join(original_filename);
-        if let Some(parent) = full_path.parent() {
-            tokio_fs::create_dir_all(parent).await
-                .map_err(|e| format!("创建子目录失败: {}", e))?;
+    // 创建子目录前后都校验写入位置仍在模块目录内，避免经由符号链接在存储目录之外创建目录或写入文件
+    check_upload_target(module, &initial_path, on_conflict == ConflictPolicy::Overwrite)?;
+    if let Some(parent) = initial_path.parent() {
+        tokio_fs::create_dir_all(parent).await
+            .map_err(|e| format!("创建子目录失败: {}", e))?;
+    }
+    check_upload_target(module, &initial_path, on_conflict == ConflictPolicy::Overwrite)?;
+    let initial_filepath = initial_path.to_string_lossy().to_string();
+
+    // 提前拒绝已存在的目标；并发写入同名文件的情况由创建文件时的 create_new 兜底。
+    // Rename 策略的最终文件名同样在创建文件时确定（见 upload_service::open_upload_target）
+    if on_conflict == ConflictPolicy::Fail && initial_path.exists() {
+        return Err(FILE_EXISTS_ERROR.to_string());
+    }
+
+    Ok(initial_filepath)
+}
+
+// 上传目标必须位于其顶层模块目录之内（子模块与相对路径中的目录可能是指向别处的符号链接）；
+// overwrite 为 true 时已存在的目标必须是普通文件，不替换符号链接、设备文件等特殊文件
+pub fn check_upload_target(module: &str, target: &Path, overwrite: bool) -> Result<(), String> {
+    let top_module = module.split('/').next().unwrap_or(module);
+    let module_root = Path::new(config::uploads_root()).join(top_module);
+    match file_utils::is_within_root(&module_root, target) {
+        Ok(true) => {}
+        Ok(false) => {
+            log::warn!("拒绝写入模块目录之外的路径: {}", target.display());
+            return Err(UNSAFE_TARGET_PATH_ERROR.to_string());
         }
-        full_path.to_string_lossy().to_string()
-    } else {
-        format!("{}/{}", module_path, original_filename)
+        Err(e) => {
+            log::warn!("无法解析写入路径 {}: {}", target.display(), e);
+            return Err(UNSAFE_TARGET_PATH_ERROR.to_string());
+        }
+    }
+
+    if overwrite {
+        if let Ok(metadata) = fs::symlink_metadata(target) {
+            if !metadata.file_type().is_file() {
+                log::warn!("拒绝覆盖非普通文件: {}", target.display());
+                return Err(UNSAFE_TARGET_PATH_ERROR.to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+// 内容寻址存储中某个摘要对应的路径
+fn content_store_path(hash: &str) -> PathBuf {
+    Path::new(config::uploads_root()).join(file_utils::CONTENT_STORE_DIR).join(hash)
+}
+
+// 将已写入的文件接入内容寻址存储：
+// 相同内容已存在时，用存储中的副本硬链接替换 final_path（返回 true）；
+// 否则把 final_path 硬链接进存储，供后续相同内容复用（返回 false）
+pub async fn link_or_store(hash: &str, final_path: &str) -> Result<bool, String> {
+    if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("无效的内容摘要: {}", hash));
+    }
+    let hash = hash.to_lowercase();
+
+    // 同一摘要的存储/链接操作串行执行
+    let lock = lock_utils::get_file_lock(&format!("{}_{}", file_utils::CONTENT_STORE_DIR, hash)).await;
+    let _guard = lock.lock().await;
+
+    let final_path = final_path.to_string();
+    tokio::task::spawn_blocking(move || -> Result<bool, String> {
+        let store_path = content_store_path(&hash);
+        if store_path.exists() {
+            let final_meta = fs::metadata(&final_path).map_err(|e| format!("读取文件信息失败: {}", e))?;
+            let store_meta = fs::metadata(&store_path).map_err(|e| format!("读取存储文件信息失败: {}", e))?;
+            if file_utils::same_inode(&final_meta, &store_meta) {
+                return Ok(true);
+            }
+            // 先链接到临时名再原子替换，避免替换过程中目标文件缺失
+            let link_tmp = format!("{}.link.{}", final_path, uuid::Uuid::new_v4());
+            fs::hard_link(&store_path, &link_tmp).map_err(|e| format!("创建硬链接失败: {}", e))?;
+            if let Err(e) = fs::rename(&link_tmp, &final_path) {
+                let _ = fs::remove_file(&link_tmp);
+                return Err(format!("替换为硬链接失败: {}", e));
+            }
+            Ok(true)
+        } else {
+            if let Some(parent) = store_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("创建内容存储目录失败: {}", e))?;
+            }
+            fs::hard_link(&final_path, &store_path).map_err(|e| format!("写入内容存储失败: {}", e))?;
+            Ok(false)
+        }
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))?
+}
+
+// 计算已存储文件的 SHA-256，失败时仅记录警告
+pub async fn sha256_stored_file(final_path: &str) -> Option<String> {
+    let path = PathBuf::from(final_path);
+    let hashed = tokio::task::spawn_blocking(move || file_utils::hash_file(&path, config::HashAlgorithm::Sha256)).await;
+    match hashed {
+        Ok(Ok(hash)) => Some(hash),
+        Ok(Err(e)) => {
+            log::warn!("计算文件摘要失败 {}: {}", final_path, e);
+            None
+        }
+        Err(e) => {
+            log::warn!("计算文件摘要失败 {}: {}", final_path, e);
+            None
+        }
+    }
+}
+
+// 去重模式下计算文件的 SHA-256（已知时直接复用）并接入内容寻址存储，返回摘要；失败时仅记录警告
+pub async fn dedup_stored_file(final_path: &str, known_sha256: Option<String>) -> Option<String> {
+    if !config::get_config().dedup_enabled {
+        return known_sha256;
+    }
+    let hash = match known_sha256 {
+        Some(hash) => hash.to_lowercase(),
+        None => sha256_stored_file(final_path).await?,
     };
 
-    // 生成唯一文件名
-    let final_filepath = file_utils::generate_unique_filename(original_filename, &initial_filepath);
-    
-    Ok(final_filepath)
+    match link_or_store(&hash, final_path).await {
+        Ok(true) => log::info!("内容去重: {} 已链接到 {}", final_path, hash),
+        Ok(false) => log::debug!("内容去重: {} 存入内容存储 {}", final_path, hash),
+        Err(e) => log::warn!("内容去重失败 {}: {}", final_path, e),
+    }
+    Some(hash)
+}
+
+// 清理内容存储中已不被任何上传文件引用（链接数为 1）的条目，返回 (删除数量, 释放字节数)
+#[cfg(unix)]
+pub async fn cleanup_content_store() -> Result<(usize, u64), String> {
+    use std::os::unix::fs::MetadataExt;
+
+    tokio::task::spawn_blocking(|| -> Result<(usize, u64), String> {
+        let store_dir = Path::new(config::uploads_root()).join(file_utils::CONTENT_STORE_DIR);
+        let entries = match fs::read_dir(&store_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((0, 0)),
+            Err(e) => return Err(format!("读取内容存储目录失败: {}", e)),
+        };
+
+        let mut removed = 0;
+        let mut freed = 0;
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else { continue };
+            if metadata.is_file() && metadata.nlink() == 1 && fs::remove_file(entry.path()).is_ok() {
+                removed += 1;
+                freed += metadata.len();
+            }
+        }
+        Ok((removed, freed))
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))?
+}
+
+#[cfg(not(unix))]
+pub async fn cleanup_content_store() -> Result<(usize, u64), String> {
+    Ok((0, 0))
+}
+
+// 获取单个文件的详细信息（含上传来源），文件不存在时返回 None
+// 获取单个文件的详细信息；指定 hash_algorithm 时即时计算内容摘要（压缩存储的文件按原始内容计算）
+pub async fn get_file_info(
+    module: &str,
+    filename: &str,
+    hash_algorithm: Option<config::HashAlgorithm>,
+) -> Result<Option<FileDetails>, String> {
+    let module = module.to_string();
+    let filename = filename.to_string();
+    tokio::task::spawn_blocking(move || -> Result<Option<FileDetails>, String> {
+        let file_path = PathBuf::from(format!("{}/{}/{}", config::uploads_root(), module, filename));
+        let metadata = match fs::metadata(&file_path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => return Ok(None),
+        };
+        if file_path.file_name().is_some_and(|n| file_utils::is_sidecar_file(&n.to_string_lossy())) {
+            return Ok(None);
+        }
+
+        let file_meta = file_utils::read_file_meta(&file_path);
+        let info = file_utils::build_file_info(&file_path, &metadata, &module, None, file_meta.as_ref());
+        let computed_hash = match hash_algorithm {
+            Some(algorithm) => Some(
+                file_utils::hash_original_content(&file_path, algorithm, info.compressed)
+                    .map(|(hash, _)| hash)
+                    .map_err(|e| format!("计算文件摘要失败: {}", e))?,
+            ),
+            None => None,
+        };
+        let format_time = |time: std::io::Result<std::time::SystemTime>| {
+            time.ok().map(|t| DateTime::<Utc>::from(t).format("%Y-%m-%d %H:%M:%S").to_string())
+        };
+
+        Ok(Some(FileDetails {
+            info,
+            provenance: file_meta.and_then(|meta| meta.provenance),
+            created: format_time(Ok(file_utils::file_timestamp(&metadata))),
+            last_modified: format_time(metadata.modified()),
+            hash_algorithm,
+            computed_hash,
+            etag: file_utils::file_etag(&metadata),
+            last_modified_header: download_service::last_modified_header(&metadata),
+        }))
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))?
 }
 
 pub async fn delete_file(module: &str, filename: &str) -> Result<(), String> {
-    let file_path = format!("./uploads/{}/{}", module, filename);
-    
+    // 软删除模式下移入回收站
+    if config::get_config().soft_delete {
+        let trash_name = trash_service::move_to_trash(module, filename).await?;
+        log::info!("文件已移入回收站: {}/{} -> {}", module, filename, trash_name);
+        return Ok(());
+    }
+
+    let file_path = format!("{}/{}/{}", config::uploads_root(), module, filename);
+
     tokio_fs::remove_file(&file_path).await
         .map_err(|e| format!("删除文件失败: {}", e))?;
+    file_utils::remove_file_meta(Path::new(&file_path));
+
+    system_service::record_files_removed(1);
         
     Ok(())
 }
 
-pub async fn delete_folder(module: &str, folder_path: &str) -> Result<(), String> {
-    let full_path = format!("./uploads/{}/{}", module, folder_path);
-    
+// 批量删除模块内的文件：每一项单独校验与执行，失败不影响其他项，结果按输入顺序返回
+pub async fn delete_files(module: &str, files: Vec<String>) -> Vec<DeleteResult> {
+    let mut results: Vec<(usize, DeleteResult)> = futures_util::stream::iter(files.into_iter().enumerate())
+        .map(|(index, file)| async move {
+            let valid = validation_utils::is_valid_filename(&file)
+                && validation_utils::is_valid_path(&file)
+                && validation_utils::is_valid_module_path(&file);
+            let result = if valid {
+                delete_file(module, &file).await
+            } else {
+                Err("文件路径包含非法字符".to_string())
+            };
+            (index, DeleteResult {
+                file,
+                success: result.is_ok(),
+                error: result.err(),
+            })
+        })
+        .buffer_unordered(BULK_DELETE_CONCURRENCY)
+        .collect()
+        .await;
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// 重命名失败于跨文件系统时，复制到目标目录的临时文件再原子替换，最后删除源文件
+async fn rename_or_copy(src: &Path, dst: &Path) -> std::io::Result<()> {
+    match tokio_fs::rename(src, dst).await {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            let tmp = PathBuf::from(format!("{}.tmp.{}", dst.display(), uuid::Uuid::new_v4()));
+            if let Err(e) = tokio_fs::copy(src, &tmp).await {
+                let _ = tokio_fs::remove_file(&tmp).await;
+                return Err(e);
+            }
+            if let Err(e) = tokio_fs::rename(&tmp, dst).await {
+                let _ = tokio_fs::remove_file(&tmp).await;
+                return Err(e);
+            }
+            tokio_fs::remove_file(src).await
+        }
+        result => result,
+    }
+}
+
+// 移动/重命名单个文件（可跨模块），单个移动与批量移动共用，返回目标路径。
+// 同时持有源与目标的文件锁（按键排序加锁避免死锁），防止与同名上传合并并发；
+// 跨文件系统时退回复制，元数据文件随文件一起移动
+async fn relocate_file(
+    from_module: &str,
+    from_path: &str,
+    to_module: &str,
+    to_path: &str,
+    overwrite: bool,
+) -> Result<PathBuf, String> {
+    let src_path = PathBuf::from(format!("{}/{}/{}", config::uploads_root(), from_module, from_path));
+    let dst_path = PathBuf::from(format!("{}/{}/{}", config::uploads_root(), to_module, to_path));
+    if src_path == dst_path {
+        return Err("源路径与目标路径相同".to_string());
+    }
+    check_path_length(&dst_path)?;
+
+    let mut keys = [format!("{}_{}", from_module, from_path), format!("{}_{}", to_module, to_path)];
+    keys.sort();
+    let first_lock = lock_utils::get_file_lock(&keys[0]).await;
+    let second_lock = lock_utils::get_file_lock(&keys[1]).await;
+    let _first = first_lock.lock().await;
+    let _second = second_lock.lock().await;
+
+    match tokio_fs::metadata(&src_path).await {
+        Ok(metadata) if metadata.is_file() => {}
+        Ok(_) => return Err("源路径不是文件".to_string()),
+        Err(_) => return Err(SOURCE_NOT_FOUND_ERROR.to_string()),
+    }
+
+    let replaces_existing = tokio_fs::try_exists(&dst_path).await.unwrap_or(false);
+    if replaces_existing && !overwrite {
+        return Err(FILE_EXISTS_ERROR.to_string());
+    }
+
+    if let Some(parent) = dst_path.parent() {
+        tokio_fs::create_dir_all(parent).await
+            .map_err(|e| format!("创建目标目录失败: {}", e))?;
+    }
+
+    rename_or_copy(&src_path, &dst_path).await
+        .map_err(|e| format!("移动文件失败: {}", e))?;
+
+    // 元数据随文件一起移动
+    let src_meta = file_utils::sidecar_path(&src_path);
+    let dst_meta = file_utils::sidecar_path(&dst_path);
+    if tokio_fs::try_exists(&src_meta).await.unwrap_or(false) {
+        if let Err(e) = rename_or_copy(&src_meta, &dst_meta).await {
+            log::warn!("移动文件元数据失败 {}: {}", src_meta.display(), e);
+        }
+    } else if replaces_existing {
+        let _ = tokio_fs::remove_file(&dst_meta).await;
+    }
+
+    if replaces_existing {
+        system_service::record_files_removed(1);
+    }
+
+    log::info!("文件移动成功: {}/{} -> {}/{}", from_module, from_path, to_module, to_path);
+    Ok(dst_path)
+}
+
+// 移动/重命名单个文件（可跨模块），返回移动后的文件信息
+pub async fn move_file(
+    from_module: &str,
+    from_path: &str,
+    to_module: &str,
+    to_path: &str,
+    overwrite: bool,
+) -> Result<FileInfo, String> {
+    let dst_path = relocate_file(from_module, from_path, to_module, to_path, overwrite).await?;
+
+    let to_module = to_module.to_string();
+    let relative_path = Path::new(to_path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .filter(|p| !p.is_empty());
+    tokio::task::spawn_blocking(move || {
+        let metadata = fs::metadata(&dst_path).map_err(|e| format!("获取文件元数据失败: {}", e))?;
+        let file_meta = file_utils::read_file_meta(&dst_path);
+        Ok(file_utils::build_file_info(&dst_path, &metadata, &to_module, relative_path, file_meta.as_ref()))
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))?
+}
+
+// 检查批量移动的目标冲突：已存在的目标或同一批次内重复的目标
+pub async fn check_move_conflicts(module: &str, moves: &[FileMove]) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    let mut conflicts = Vec::new();
+    for file_move in moves {
+        let dst_path = format!("{}/{}/{}", config::uploads_root(), module, file_move.dst);
+        if !seen.insert(file_move.dst.clone()) || tokio_fs::try_exists(&dst_path).await.unwrap_or(false) {
+            conflicts.push(file_move.dst.clone());
+        }
+    }
+    conflicts
+}
+
+// 批量移动模块内的文件，按输入顺序返回每一项的结果
+pub async fn move_files(module: &str, moves: Vec<FileMove>, overwrite: bool) -> Vec<MoveResult> {
+    futures_util::stream::iter(moves)
+        .map(|file_move| async move {
+            let result = relocate_file(module, &file_move.src, module, &file_move.dst, overwrite).await;
+            MoveResult {
+                src: file_move.src,
+                dst: file_move.dst,
+                success: result.is_ok(),
+                error: result.err(),
+            }
+        })
+        .buffered(BULK_MOVE_CONCURRENCY)
+        .collect()
+        .await
+}
+
+// 删除（或移入回收站）文件夹，返回其中被移除的文件路径（相对模块目录）
+pub async fn delete_folder(module: &str, folder_path: &str) -> Result<Vec<String>, String> {
+    let deleted = list_folder_files(module, folder_path).await;
+
+    if config::get_config().soft_delete {
+        let trash_name = trash_service::move_to_trash(module, folder_path).await?;
+        log::info!("文件夹已移入回收站: {}/{} -> {}", module, folder_path, trash_name);
+        return Ok(deleted);
+    }
+
+    let full_path = format!("{}/{}/{}", config::uploads_root(), module, folder_path);
+
     tokio_fs::remove_dir_all(&full_path).await
         .map_err(|e| format!("删除文件夹失败: {}", e))?;
+
+    // 删除的文件数未知，后台全量刷新文件总数缓存
+    tokio::spawn(system_service::refresh_file_count());
         
-    Ok(())
+    Ok(deleted)
+}
+
+// 文件夹内全部文件相对模块目录的路径（按路径排序）；遍历中止时返回已找到的部分
+async fn list_folder_files(module: &str, folder_path: &str) -> Vec<String> {
+    let module_path = PathBuf::from(format!("{}/{}", config::uploads_root(), module));
+    let folder = module_path.join(folder_path.trim_matches('/'));
+    let listed = tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        let walked = file_utils::walk_files(&folder, config::get_config().max_walk_depth, |path, _, _| {
+            if let Ok(relative) = path.strip_prefix(&module_path) {
+                files.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+        });
+        if let Err(e) = walked {
+            log::warn!("列出待删除文件夹 {} 的内容时遍历中止，结果可能不完整: {}", folder.display(), e);
+        }
+        files.sort();
+        files
+    })
+    .await;
+    listed.unwrap_or_else(|e| {
+        log::warn!("列出待删除文件夹内容失败: {}", e);
+        Vec::new()
+    })
+}
+
+// 模块级读写锁（按顶层模块名）：上传、合并在确定并写入目标文件期间持有读锁，
+// 创建与重命名模块持有写锁，写入不会落在重命名前的旧模块目录中
+pub async fn module_lock(module: &str) -> Arc<tokio::sync::RwLock<()>> {
+    let top_level = module.split('/').next().unwrap_or(module);
+    lock_utils::get_rw_lock(&format!(".module_{}", top_level)).await
 }
 
-pub async fn delete_module(module: &str) -> Result<(), String> {
-    let module_path = format!("./uploads/{}", module);
-    let temp_dir = format!("./temp/{}", module);
+// 重命名模块目录及对应的临时目录，返回新模块的信息
+pub async fn rename_module(module: &str, new_name: &str) -> Result<ModuleInfo, String> {
+    // 按固定顺序获取两个模块锁，避免交叉重命名时死锁
+    let (first, second) = if module <= new_name { (module, new_name) } else { (new_name, module) };
+    let first_lock = module_lock(first).await;
+    let second_lock = module_lock(second).await;
+    let _first_guard = first_lock.write().await;
+    let _second_guard = second_lock.write().await;
+
+    let module_path = PathBuf::from(format!("{}/{}", config::uploads_root(), module));
+    let new_path = PathBuf::from(format!("{}/{}", config::uploads_root(), new_name));
+    if !tokio_fs::metadata(&module_path).await.is_ok_and(|m| m.is_dir()) {
+        return Err(MODULE_NOT_FOUND_ERROR.to_string());
+    }
+    if tokio_fs::try_exists(&new_path).await.unwrap_or(true) {
+        return Err(MODULE_EXISTS_ERROR.to_string());
+    }
+
+    tokio_fs::rename(&module_path, &new_path).await
+        .map_err(|e| format!("重命名模块目录失败: {}", e))?;
+
+    // 未合并的分片随模块一起迁移，便于继续上传；目标临时目录已存在时保留原目录
+    let temp_dir = PathBuf::from(format!("{}/{}", config::temp_root(), module));
+    let new_temp_dir = PathBuf::from(format!("{}/{}", config::temp_root(), new_name));
+    if tokio_fs::try_exists(&temp_dir).await.unwrap_or(false) {
+        if tokio_fs::try_exists(&new_temp_dir).await.unwrap_or(true) {
+            log::warn!("临时目录已存在，未迁移分片: {}", new_temp_dir.display());
+        } else if let Err(e) = tokio_fs::rename(&temp_dir, &new_temp_dir).await {
+            log::warn!("迁移临时目录失败 {}: {}", temp_dir.display(), e);
+        }
+    }
+
+    let name = new_name.to_string();
+    tokio::task::spawn_blocking(move || file_utils::module_info_at(name, &new_path))
+        .await
+        .map_err(|e| format!("阻塞任务失败: {}", e))
+}
+
+// 删除模块；force 时删除失败会退避重试，仍失败则删除其余能删除的内容，返回未能删除的路径（相对模块目录）
+pub async fn delete_module(module: &str, force: bool) -> Result<Vec<String>, String> {
+    let module_path = format!("{}/{}", config::uploads_root(), module);
+    let temp_dir = format!("{}/{}", config::temp_root(), module);
 
     // 删除模块目录
-    tokio_fs::remove_dir_all(&module_path).await
-        .map_err(|e| format!("删除模块目录失败: {}", e))?;
+    let remaining = match tokio_fs::remove_dir_all(&module_path).await {
+        Ok(()) => Vec::new(),
+        Err(e) if !force => return Err(format!("删除模块目录失败: {}", e)),
+        Err(e) => {
+            log::warn!("删除模块目录失败，开始重试 {}: {}", module_path, e);
+            force_remove_dir(module_path).await?
+        }
+    };
 
     // 尝试删除临时目录（可选）
     let _ = tokio_fs::remove_dir_all(&temp_dir).await;
+
+    tokio::spawn(system_service::refresh_file_count());
         
-    Ok(())
-}
\ No newline at end of file
+    Ok(remaining)
+}
+
+async fn force_remove_dir(path: String) -> Result<Vec<String>, String> {
+    let mut delay = FORCE_DELETE_BACKOFF;
+    for attempt in 1..=FORCE_DELETE_RETRIES {
+        tokio::time::sleep(delay).await;
+        match tokio_fs::remove_dir_all(&path).await {
+            Ok(()) => return Ok(Vec::new()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => log::warn!("第 {} 次重试删除失败 {}: {}", attempt, path, e),
+        }
+        delay *= 2;
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let root = PathBuf::from(path);
+        let mut remaining = Vec::new();
+        remove_tree_best_effort(&root, &root, &mut remaining);
+        remaining
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))
+}
+
+// 逐项删除目录内容，无法删除的条目记入 remaining；返回该目录是否已被完全删除
+fn remove_tree_best_effort(root: &Path, dir: &Path, remaining: &mut Vec<String>) -> bool {
+    let relative = |path: &Path| {
+        let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy().to_string();
+        if relative.is_empty() { ".".to_string() } else { relative }
+    };
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return true,
+        Err(_) => {
+            remaining.push(relative(dir));
+            return false;
+        }
+    };
+
+    let mut all_removed = true;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // file_type 不跟随符号链接，链接本身按文件删除
+        let removed = if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            remove_tree_best_effort(root, &path, remaining)
+        } else {
+            match fs::remove_file(&path) {
+                Ok(()) => true,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+                Err(_) => {
+                    remaining.push(relative(&path));
+                    false
+                }
+            }
+        };
+        all_removed &= removed;
+    }
+
+    // 子项有残留时目录本身必然无法删除，只报告残留的子项
+    if !all_removed {
+        return false;
+    }
+    match fs::remove_dir(dir) {
+        Ok(()) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+        Err(_) => {
+            remaining.push(relative(dir));
+            false
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, read_body_json, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    fn move_request(from_module: &str, from_path: &str, to_module: &str, to_path: &str, overwrite: bool) -> TestRequest {
+        TestRequest::post().uri("/api/file/move").set_json(serde_json::json!({
+            "from_module": from_module,
+            "from_path": from_path,
+            "to_module": to_module,
+            "to_path": to_path,
+            "overwrite": overwrite,
+        }))
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn uploads_through_symlinks_out_of_the_module_are_refused() {
+        let env = TestEnv::with(|_| {});
+        let outside = env.root.join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, env.uploads().join("default/escape")).unwrap();
+
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let resp = call_service(
+            &app,
+            TestRequest::put().uri("/api/upload/default/escape/evil.txt").set_payload("owned").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 400);
+        assert!(!outside.join("evil.txt").exists());
+
+        // 模块内的普通子目录不受影响
+        let resp = call_service(
+            &app,
+            TestRequest::put().uri("/api/upload/default/docs/ok.txt").set_payload("fine").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        assert!(env.uploads().join("default/docs/ok.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn overwrite_refuses_to_replace_a_symlink() {
+        let env = TestEnv::with(|_| {});
+        let outside = env.root.join("secret.txt");
+        std::fs::write(&outside, "secret").unwrap();
+        let link = env.uploads().join("default/link.txt");
+        std::os::unix::fs::symlink(&outside, &link).unwrap();
+
+        assert_eq!(check_upload_target("default", &link, true), Err(UNSAFE_TARGET_PATH_ERROR.to_string()));
+        let regular = env.uploads().join("default/regular.txt");
+        std::fs::write(&regular, "x").unwrap();
+        assert_eq!(check_upload_target("default", &regular, true), Ok(()));
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn identical_uploads_share_one_inode() {
+        use std::os::unix::fs::MetadataExt;
+        let env = TestEnv::with(|c| c.dedup_enabled = true);
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let content = b"same bytes in two files".repeat(100);
+        for name in ["a.txt", "b.txt"] {
+            let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", name, &content).to_request()).await;
+            assert_eq!(resp.status(), 200);
+        }
+        let other = call_service(&app, test_utils::upload_request("/api/upload?module=default", "c.txt", b"different").to_request()).await;
+        assert_eq!(other.status(), 200);
+
+        let a = std::fs::metadata(env.uploads().join("default/a.txt")).unwrap();
+        let b = std::fs::metadata(env.uploads().join("default/b.txt")).unwrap();
+        let c = std::fs::metadata(env.uploads().join("default/c.txt")).unwrap();
+        assert_eq!(a.ino(), b.ino());
+        assert_ne!(a.ino(), c.ino());
+        // 两个上传文件加内容存储中的一份
+        assert_eq!(a.nlink(), 3);
+    }
+
+    #[actix_web::test]
+    async fn module_tree_and_listing_stay_within_scan_bounds() {
+        let env = TestEnv::with(|c| {
+            c.max_tree_nodes = 2;
+            c.max_scan_concurrency = 1;
+        });
+        for dir in ["alpha/a1/deep", "alpha/a2", "beta/b1"] {
+            std::fs::create_dir_all(env.uploads().join(dir)).unwrap();
+        }
+        std::fs::write(env.uploads().join("beta/b1/file.txt"), b"content").unwrap();
+
+        // 按名称顺序展开，节点数用尽后其余子目录不再展开
+        let tree = get_module_tree().await.unwrap();
+        let names: Vec<&str> = tree.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, ["alpha", "beta", "default"]);
+        fn count(node: &ModuleTreeNode) -> usize {
+            node.children.iter().map(|child| 1 + count(child)).sum()
+        }
+        assert_eq!(tree.iter().map(count).sum::<usize>(), 2);
+        assert!(tree.iter().any(|node| node.truncated || node.children.iter().any(|child| child.truncated)));
+
+        let page = get_all_modules_info(ModuleListOptions::default()).await.unwrap();
+        let beta = page.modules.iter().find(|m| m.name == "beta").unwrap();
+        assert_eq!((beta.file_count, beta.total_size), (Some(1), Some(7)));
+    }
+
+    #[actix_web::test]
+    async fn removed_paths_are_reported_by_folder_delete_and_changes() {
+        let audit_log = std::env::temp_dir().join(format!("upload-audit-{}.log", uuid::Uuid::new_v4()));
+        let audit_path = audit_log.to_string_lossy().to_string();
+        let env = TestEnv::with(|c| c.audit_log_path = Some(audit_path));
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        for file in ["docs/a.txt", "docs/sub/b.txt", "keep.txt"] {
+            let resp = call_service(&app, TestRequest::put().uri(&format!("/api/upload/default/{}", file)).set_payload("x").to_request()).await;
+            assert_eq!(resp.status(), 200);
+        }
+
+        let resp = call_service(&app, TestRequest::delete().uri("/api/folder/default/docs").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body["data"]["deleted"], serde_json::json!(["docs/a.txt", "docs/sub/b.txt"]));
+
+        // 删除记录取自审计日志：早于 since、失败的与已重新创建的路径不会出现
+        let since = Utc::now() - chrono::Duration::minutes(1);
+        let entry = |minutes: i64, module: &str, path: &str, success: bool| {
+            serde_json::json!({
+                "timestamp": (since + chrono::Duration::minutes(minutes)).to_rfc3339(),
+                "operation": "delete",
+                "module": module,
+                "path": path,
+                "client_ip": "127.0.0.1",
+                "success": success,
+            })
+            .to_string()
+        };
+        let lines = [
+            entry(-1, "default", "old.txt", true),
+            entry(1, "default", "gone.txt", true),
+            entry(1, "default/docs", "c.txt", true),
+            entry(1, "default", "failed.txt", false),
+            entry(1, "default", "keep.txt", true),
+            entry(1, "other", "elsewhere.txt", true),
+        ];
+        std::fs::write(&audit_log, lines.join("\n")).unwrap();
+
+        let uri = format!("/api/files/default/changes?since={}", since.to_rfc3339().replace('+', "%2B"));
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body["data"]["deleted"], serde_json::json!(["gone.txt", "docs/c.txt"]));
+
+        drop(env);
+        let _ = std::fs::remove_file(audit_log);
+    }
+
+    #[actix_web::test]
+    async fn files_move_within_and_across_modules_with_their_metadata() {
+        let env = TestEnv::with(|_| {});
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let default = env.uploads().join("default");
+        std::fs::write(default.join("draft.txt"), b"draft").unwrap();
+        std::fs::write(file_utils::sidecar_path(&default.join("draft.txt")), b"{}").unwrap();
+
+        // 同模块内重命名到子目录（自动创建父目录），元数据随之移动
+        let resp = call_service(&app, move_request("default", "draft.txt", "default", "notes/final.txt", false).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["data"]["filename"], "final.txt");
+        assert_eq!(body["data"]["module"], "default");
+        assert_eq!(std::fs::read(default.join("notes/final.txt")).unwrap(), b"draft");
+        assert!(file_utils::sidecar_path(&default.join("notes/final.txt")).exists());
+        assert!(!default.join("draft.txt").exists());
+        assert!(!file_utils::sidecar_path(&default.join("draft.txt")).exists());
+
+        // 跨模块移动
+        let resp = call_service(&app, move_request("default", "notes/final.txt", "archive", "2024/final.txt", false).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let archived = env.uploads().join("archive/2024/final.txt");
+        assert_eq!(std::fs::read(&archived).unwrap(), b"draft");
+        assert!(file_utils::sidecar_path(&archived).exists());
+        assert!(!default.join("notes/final.txt").exists());
+
+        // 源文件不存在
+        let resp = call_service(&app, move_request("default", "notes/final.txt", "default", "again.txt", false).to_request()).await;
+        assert_eq!(resp.status(), 404);
+
+        // 批量移动走同一逻辑，元数据同样随文件移动
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/files/move")
+                .set_json(serde_json::json!({ "module": "archive", "moves": [{ "src": "2024/final.txt", "dst": "final.txt" }] }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        assert!(env.uploads().join("archive/final.txt").exists());
+        assert!(file_utils::sidecar_path(&env.uploads().join("archive/final.txt")).exists());
+        assert!(!file_utils::sidecar_path(&archived).exists());
+    }
+
+    #[actix_web::test]
+    async fn moving_onto_an_existing_file_needs_overwrite() {
+        let env = TestEnv::with(|_| {});
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let default = env.uploads().join("default");
+        std::fs::write(default.join("new.txt"), b"new").unwrap();
+        std::fs::write(default.join("old.txt"), b"old").unwrap();
+
+        let resp = call_service(&app, move_request("default", "new.txt", "default", "old.txt", false).to_request()).await;
+        assert_eq!(resp.status(), 409);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["data"]["code"], "DESTINATION_EXISTS");
+        assert_eq!(std::fs::read(default.join("old.txt")).unwrap(), b"old");
+        assert!(default.join("new.txt").exists());
+
+        let resp = call_service(&app, move_request("default", "new.txt", "default", "old.txt", true).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(std::fs::read(default.join("old.txt")).unwrap(), b"new");
+        assert!(!default.join("new.txt").exists());
+    }
+
+    fn bulk_move_request(moves: serde_json::Value, overwrite: bool) -> TestRequest {
+        TestRequest::post()
+            .uri("/api/files/move")
+            .set_json(serde_json::json!({ "module": "default", "moves": moves, "overwrite": overwrite }))
+    }
+
+    #[actix_web::test]
+    async fn bulk_moves_report_per_item_results() {
+        let env = TestEnv::with(|_| {});
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let default = env.uploads().join("default");
+        for name in ["a.txt", "b.txt", "taken.txt"] {
+            std::fs::write(default.join(name), name).unwrap();
+        }
+
+        // 部分失败：缺失的源文件单独报错，其余照常移动，结果按输入顺序返回
+        let moves = serde_json::json!([
+            { "src": "a.txt", "dst": "sorted/a.txt" },
+            { "src": "missing.txt", "dst": "sorted/missing.txt" },
+            { "src": "b.txt", "dst": "sorted/b.txt" },
+        ]);
+        let resp = call_service(&app, bulk_move_request(moves, false).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["success"], false);
+        let results = body["data"].as_array().unwrap();
+        let outcomes: Vec<(&str, bool)> = results.iter().map(|r| (r["src"].as_str().unwrap(), r["success"].as_bool().unwrap())).collect();
+        assert_eq!(outcomes, [("a.txt", true), ("missing.txt", false), ("b.txt", true)]);
+        assert_eq!(results[1]["error"], SOURCE_NOT_FOUND_ERROR);
+        assert!(results[0].get("error").is_none());
+        assert_eq!(std::fs::read(default.join("sorted/a.txt")).unwrap(), b"a.txt");
+        assert_eq!(std::fs::read(default.join("sorted/b.txt")).unwrap(), b"b.txt");
+
+        // 目标冲突或路径非法时整个请求不执行
+        let moves = serde_json::json!([
+            { "src": "sorted/a.txt", "dst": "a.txt" },
+            { "src": "sorted/b.txt", "dst": "taken.txt" },
+        ]);
+        let resp = call_service(&app, bulk_move_request(moves.clone(), false).to_request()).await;
+        assert_eq!(resp.status(), 409);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["data"]["detail"]["paths"], serde_json::json!(["taken.txt"]));
+        assert!(default.join("sorted/a.txt").exists());
+
+        let resp = call_service(&app, bulk_move_request(serde_json::json!([{ "src": "sorted/a.txt", "dst": "../a.txt" }]), false).to_request()).await;
+        assert_eq!(resp.status(), 400);
+        assert!(default.join("sorted/a.txt").exists());
+
+        // overwrite 时覆盖已存在的目标
+        let resp = call_service(&app, bulk_move_request(moves, true).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(std::fs::read(default.join("taken.txt")).unwrap(), b"b.txt");
+        assert_eq!(std::fs::read(default.join("a.txt")).unwrap(), b"a.txt");
+    }
+
+    #[actix_web::test]
+    async fn module_files_are_sorted_and_paginated() {
+        let env = TestEnv::with(|_| {});
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let default = env.uploads().join("default");
+        // 上传时间精确到秒：b 最早、c 其次、a 最新；大小 a < c < b
+        for (i, (name, content)) in [("b.txt", "bbb"), ("c.txt", "cc"), ("a.txt", "a")].into_iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+            }
+            std::fs::write(default.join(name), content).unwrap();
+        }
+
+        let list = |query: &'static str| {
+            let app = &app;
+            async move {
+                let resp = call_service(app, TestRequest::get().uri(&format!("/api/files/default?{}", query)).to_request()).await;
+                let status = resp.status();
+                let body: serde_json::Value = read_body_json(resp).await;
+                (status, body["data"].clone())
+            }
+        };
+        let names = |page: &serde_json::Value| -> Vec<String> {
+            page["files"].as_array().unwrap().iter().map(|f| f["filename"].as_str().unwrap().to_string()).collect()
+        };
+
+        for (query, expected) in [
+            ("", ["a.txt", "c.txt", "b.txt"]),
+            ("sort_by=time&order=asc", ["b.txt", "c.txt", "a.txt"]),
+            ("sort_by=name", ["c.txt", "b.txt", "a.txt"]),
+            ("sort_by=name&order=asc", ["a.txt", "b.txt", "c.txt"]),
+            ("sort_by=size&order=asc", ["a.txt", "c.txt", "b.txt"]),
+            ("sort_by=size&order=desc", ["b.txt", "c.txt", "a.txt"]),
+        ] {
+            let (status, page) = list(query).await;
+            assert_eq!(status, 200, "{}", query);
+            assert_eq!(names(&page), expected, "{}", query);
+            assert_eq!(page["total"], 3);
+        }
+
+        let (_, page) = list("sort_by=name&order=asc&page=2&page_size=2").await;
+        assert_eq!(names(&page), ["c.txt"]);
+        assert_eq!((page["page"].as_u64(), page["page_size"].as_u64()), (Some(2), Some(2)));
+
+        // 超出范围的页返回空列表而不是错误；page_size 超过上限时截断
+        let (status, page) = list("page=99&page_size=5000").await;
+        assert_eq!(status, 200);
+        assert!(names(&page).is_empty());
+        assert_eq!(page["total"], 3);
+        assert_eq!(page["page_size"], 1000);
+
+        for query in ["sort_by=owner", "order=up", "page=0", "page_size=abc"] {
+            assert_eq!(list(query).await.0, 400, "{}", query);
+        }
+    }
+
+    #[actix_web::test]
+    async fn batch_delete_reports_partial_success() {
+        let env = TestEnv::with(|_| {});
+        let module = env.uploads().join("default");
+        std::fs::create_dir_all(module.join("sub")).unwrap();
+        std::fs::write(module.join("a.txt"), b"a").unwrap();
+        std::fs::write(module.join("sub/b.txt"), b"b").unwrap();
+        std::fs::write(env.uploads().join("secret.txt"), b"secret").unwrap();
+
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let files = ["a.txt", "../secret.txt", "sub/b.txt", "sub/../../secret.txt", "missing.txt"];
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/files/batch-delete")
+                .set_json(serde_json::json!({ "module": "default", "files": files }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["success"], false);
+        let results = body["data"].as_array().unwrap();
+        let outcome: Vec<(&str, bool)> = results.iter().map(|r| (r["file"].as_str().unwrap(), r["success"].as_bool().unwrap())).collect();
+        assert_eq!(
+            outcome,
+            [("a.txt", true), ("../secret.txt", false), ("sub/b.txt", true), ("sub/../../secret.txt", false), ("missing.txt", false)]
+        );
+        assert!(results.iter().all(|r| r["success"] == true || r["error"].is_string()));
+
+        assert!(!module.join("a.txt").exists());
+        assert!(!module.join("sub/b.txt").exists());
+        assert_eq!(std::fs::read(env.uploads().join("secret.txt")).unwrap(), b"secret");
+    }
+
+    #[actix_web::test]
+    async fn file_details_are_served_for_single_files() {
+        let env = TestEnv::with(|_| {});
+        let module = env.uploads().join("default");
+        std::fs::create_dir_all(module.join("docs")).unwrap();
+        std::fs::write(module.join("docs/report.pdf"), b"%PDF-1.4 report").unwrap();
+        std::fs::write(env.uploads().join("secret.txt"), b"secret").unwrap();
+
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let get = |uri: &str| TestRequest::get().uri(uri).to_request();
+
+        let resp = call_service(&app, get("/api/file/default/docs/report.pdf?hash=sha256")).await;
+        assert_eq!(resp.status(), 200);
+        assert!(resp.headers().contains_key("ETag"));
+        let body: serde_json::Value = read_body_json(resp).await;
+        let data = &body["data"];
+        assert_eq!(data["filename"], "report.pdf");
+        assert_eq!(data["size"], 15);
+        assert_eq!(data["file_type"], "document");
+        assert!(data["url"].as_str().unwrap().ends_with("/default/docs/report.pdf"), "{}", body);
+        assert!(data["last_modified"].is_string() && data["upload_time"].is_string(), "{}", body);
+        assert_eq!(data["hash_algorithm"], "sha256");
+        let mut hasher = crate::utils::hash_utils::FileHasher::new(config::HashAlgorithm::Sha256);
+        hasher.update(b"%PDF-1.4 report");
+        assert_eq!(data["computed_hash"], hasher.finalize_hex());
+
+        // 未请求时不计算摘要
+        let resp = call_service(&app, get("/api/file/default/docs/report.pdf")).await;
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert!(body["data"].get("computed_hash").is_none());
+
+        let resp = call_service(&app, get("/api/file/default/docs/missing.pdf")).await;
+        assert_eq!(resp.status(), 404);
+
+        for uri in ["/api/file/default/../secret.txt", "/api/file/default/docs/..%2F..%2Fsecret.txt", "/api/file/default/docs/report.pdf?hash=crc"] {
+            let resp = call_service(&app, get(uri)).await;
+            assert_eq!(resp.status(), 400, "{}", uri);
+        }
+    }
+}
@@ -0,0 +1,493 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use tokio::process::Command;
+use crate::config::{self, ConflictPolicy};
+use crate::services::upload_service;
+
+// 移除元数据的图片格式
+const STRIPPABLE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+// 元数据移除在内存中完成，超过该大小的图片保持原样
+const MAX_STRIP_SIZE: u64 = 256 * 1024 * 1024;
+
+// 参与自动转换的源图片格式（GIF/SVG 等动图或矢量图保持原样）
+const CONVERTIBLE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png"];
+
+// 需要外部工具的目标格式及其版本参数（需在运行环境中另行安装并位于 PATH 中）；
+// webp 由 image crate 在进程内编码，不需要外部工具
+fn converter_command(target: &str) -> Option<(&'static str, &'static str)> {
+    match target {
+        "avif" => Some(("avifenc", "--version")),
+        _ => None,
+    }
+}
+
+// 启动时检查配置的转换工具能否运行；缺失时只记录警告，上传的图片将保持原格式
+pub async fn check_converter_available() {
+    let Some(target) = config::get_config().image_convert_to.as_ref().map(|t| t.to_lowercase()) else {
+        return;
+    };
+    let Some((program, version_arg)) = converter_command(&target) else {
+        return;
+    };
+    if let Err(e) = Command::new(program).arg(version_arg).output().await {
+        log::warn!("已配置图片自动转换为 {}，但无法运行 {}: {}（转换将被跳过，图片保持原格式）", target, program, e);
+    }
+}
+
+// 按配置将图片转换为目标格式，成功时返回转换后的文件路径；
+// 未配置、格式不支持或转换失败时返回 None，原文件保持不变
+pub async fn convert_image_if_configured(filepath: &str, extension: &str) -> Option<String> {
+    let config = config::get_config();
+    let target = config.image_convert_to.as_ref()?.to_lowercase();
+    let extension = extension.to_lowercase();
+
+    if extension == target || !CONVERTIBLE_EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+
+    let path_owned = filepath.to_string();
+    let animated = tokio::task::spawn_blocking(move || is_animated_png(&path_owned))
+        .await
+        .unwrap_or(false);
+    if animated {
+        log::info!("跳过动图转换: {}", filepath);
+        return None;
+    }
+
+    if target != "webp" && converter_command(&target).is_none() {
+        log::warn!("不支持的图片转换目标格式: {}", target);
+        return None;
+    }
+
+    // 与上传相同，以 create_new 原子地占用输出文件名（编码结果随后覆盖该占位文件）
+    let path = Path::new(filepath);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+    let converted_name = format!("{}.{}", stem, target);
+    let initial_path = path.with_file_name(&converted_name);
+    let output_path = match upload_service::claim_target_path(
+        &initial_path.to_string_lossy(),
+        &converted_name,
+        ConflictPolicy::Rename,
+    )
+    .await
+    {
+        Ok((output_path, _)) => output_path,
+        Err(e) => {
+            log::warn!("无法占用转换输出文件名，保留原文件 {}: {}", filepath, e);
+            return None;
+        }
+    };
+
+    let result = if target == "webp" {
+        let (input, output) = (filepath.to_string(), output_path.clone());
+        tokio::task::spawn_blocking(move || encode_webp(Path::new(&input), Path::new(&output)))
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()))
+    } else {
+        run_converter(&target, config.image_convert_quality, filepath, &output_path).await
+    };
+    // 编码失败时只剩空的占位文件
+    let converted_size = tokio::fs::metadata(&output_path).await.map(|m| m.len()).unwrap_or(0);
+    match result {
+        Ok(()) if converted_size > 0 => {
+            // 转换结果反而更大时（如已高度压缩的小图）保留原图
+            if let Ok(original) = tokio::fs::metadata(filepath).await.map(|m| m.len()) {
+                if converted_size > original {
+                    log::info!("转换后体积更大 ({} > {} bytes)，保留原图: {}", converted_size, original, filepath);
+                    let _ = tokio::fs::remove_file(&output_path).await;
+                    return None;
+                }
+            }
+            if let Err(e) = tokio::fs::remove_file(filepath).await {
+                log::warn!("删除转换前的原图失败 {}: {}", filepath, e);
+            }
+            log::info!("图片转换成功: {} -> {}", filepath, output_path);
+            Some(output_path)
+        }
+        Ok(()) => {
+            log::warn!("图片转换未产生输出，保留原文件: {}", filepath);
+            let _ = tokio::fs::remove_file(&output_path).await;
+            None
+        }
+        Err(e) => {
+            log::warn!("图片转换失败，保留原文件 {}: {}", filepath, e);
+            let _ = tokio::fs::remove_file(&output_path).await;
+            None
+        }
+    }
+}
+
+// 解码原图并无损编码为 WebP，写入 output（覆盖占位文件）
+fn encode_webp(input: &Path, output: &Path) -> Result<(), String> {
+    let image = image::ImageReader::open(input)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("读取图片失败: {}", e))?
+        .decode()
+        .map_err(|e| format!("解码图片失败: {}", e))?;
+    // WebP 编码器只接受 8 位的灰度/RGB(A) 像素
+    let image = match image.color() {
+        image::ColorType::L8 | image::ColorType::La8 | image::ColorType::Rgb8 | image::ColorType::Rgba8 => image,
+        _ => image::DynamicImage::ImageRgba8(image.to_rgba8()),
+    };
+
+    let file = std::fs::File::create(output).map_err(|e| format!("创建输出文件失败: {}", e))?;
+    let mut writer = std::io::BufWriter::new(file);
+    image
+        .write_with_encoder(image::codecs::webp::WebPEncoder::new_lossless(&mut writer))
+        .map_err(|e| format!("编码 WebP 失败: {}", e))?;
+    let file = writer.into_inner().map_err(|e| format!("写入输出文件失败: {}", e))?;
+    if config::get_config().fsync_policy.sync_files() {
+        file.sync_all().map_err(|e| format!("写入输出文件失败: {}", e))?;
+    }
+    Ok(())
+}
+
+// 调用外部工具完成转换（avif）
+async fn run_converter(target: &str, quality: u8, input: &str, output: &str) -> Result<(), String> {
+    let (program, _) = converter_command(target).ok_or_else(|| format!("不支持的图片转换目标格式: {}", target))?;
+    let quality = quality.min(100).to_string();
+    let result = Command::new(program)
+        .args(["-q", &quality, input, output])
+        .output()
+        .await
+        .map_err(|e| format!("调用图片转换工具失败: {}", e))?;
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&result.stderr).trim().to_string())
+    }
+}
+
+// 检查 PNG 是否为 APNG 动图（在 IDAT 之前出现 acTL 块）
+fn is_animated_png(filepath: &str) -> bool {
+    let mut file = match std::fs::File::open(filepath) {
+        Ok(f) => f,
+        Err(_) => return false,
+    };
+
+    let mut signature = [0u8; 8];
+    if file.read_exact(&mut signature).is_err() || signature != *b"\x89PNG\r\n\x1a\n" {
+        return false;
+    }
+
+    let mut header = [0u8; 8];
+    while file.read_exact(&mut header).is_ok() {
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as i64;
+        match &header[4..8] {
+            b"acTL" => return true,
+            b"IDAT" | b"IEND" => return false,
+            _ => {}
+        }
+        // 跳过块数据和 CRC
+        if file.seek(SeekFrom::Current(length + 4)).is_err() {
+            return false;
+        }
+    }
+    false
+}
+
+// 按配置移除图片中的 EXIF/XMP 等元数据段（像素数据原样保留，不重新编码），
+// 返回是否改写了文件；失败时保留原文件并记录警告
+pub async fn strip_metadata_if_configured(filepath: &str, extension: &str) -> bool {
+    let extension = extension.to_lowercase();
+    if !config::get_config().strip_exif || !STRIPPABLE_EXTENSIONS.contains(&extension.as_str()) {
+        return false;
+    }
+
+    let path_owned = filepath.to_string();
+    let result = tokio::task::spawn_blocking(move || strip_metadata_file(Path::new(&path_owned), &extension))
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+    match result {
+        Ok(stripped) => {
+            if stripped {
+                log::info!("已移除图片元数据: {}", filepath);
+            }
+            stripped
+        }
+        Err(e) => {
+            log::warn!("移除图片元数据失败，保留原文件 {}: {}", filepath, e);
+            false
+        }
+    }
+}
+
+fn strip_metadata_file(path: &Path, extension: &str) -> std::io::Result<bool> {
+    if std::fs::metadata(path)?.len() > MAX_STRIP_SIZE {
+        log::warn!("图片过大，跳过元数据移除: {}", path.display());
+        return Ok(false);
+    }
+    let data = std::fs::read(path)?;
+    let stripped = match extension {
+        "jpg" | "jpeg" => strip_jpeg(&data)?,
+        "png" => strip_png(&data)?,
+        "webp" => strip_webp(&data)?,
+        _ => None,
+    };
+    let Some(stripped) = stripped else {
+        return Ok(false);
+    };
+
+    // 写入临时文件后原子替换
+    let tmp = path.with_file_name(format!(
+        "{}.tmp.{}",
+        path.file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+        uuid::Uuid::new_v4()
+    ));
+    let write = || -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&tmp)?;
+        std::io::Write::write_all(&mut file, &stripped)?;
+        if config::get_config().fsync_policy.sync_files() {
+            file.sync_all()?;
+        }
+        std::fs::rename(&tmp, path)
+    };
+    write().inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })?;
+    Ok(true)
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+// JPEG: 删除 APP1（EXIF/XMP）、APP12、APP13（IPTC）和 COM 段；SOS 之后的扫描数据原样复制。
+// APP0（JFIF）、APP2（ICC 色彩配置）、APP14（Adobe 颜色变换）影响显示效果，保留
+fn strip_jpeg(data: &[u8]) -> std::io::Result<Option<Vec<u8>>> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err(invalid("不是有效的 JPEG 文件"));
+    }
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..2]);
+    let mut pos = 2;
+    let mut removed = false;
+
+    loop {
+        if pos + 2 > data.len() || data[pos] != 0xFF {
+            return Err(invalid("JPEG 段结构损坏"));
+        }
+        let marker = data[pos + 1];
+        // 填充字节
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        // 无长度字段的标记
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            out.extend_from_slice(&data[pos..pos + 2]);
+            pos += 2;
+            continue;
+        }
+        if marker == 0xD9 {
+            out.extend_from_slice(&data[pos..]);
+            break;
+        }
+        if pos + 4 > data.len() {
+            return Err(invalid("JPEG 段结构损坏"));
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = pos + 2 + length;
+        if length < 2 || end > data.len() {
+            return Err(invalid("JPEG 段长度无效"));
+        }
+        // SOS 之后是熵编码数据，直到文件结尾整体保留
+        if marker == 0xDA {
+            out.extend_from_slice(&data[pos..]);
+            break;
+        }
+        if matches!(marker, 0xE1 | 0xEC | 0xED | 0xFE) {
+            removed = true;
+        } else {
+            out.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+    }
+
+    Ok(removed.then_some(out))
+}
+
+// PNG: 删除 eXIf、tEXt、zTXt、iTXt、tIME 块，其余块（含 CRC）原样复制
+fn strip_png(data: &[u8]) -> std::io::Result<Option<Vec<u8>>> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !data.starts_with(SIGNATURE) {
+        return Err(invalid("不是有效的 PNG 文件"));
+    }
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(SIGNATURE);
+    let mut pos = SIGNATURE.len();
+    let mut removed = false;
+
+    while pos < data.len() {
+        if pos + 8 > data.len() {
+            return Err(invalid("PNG 块结构损坏"));
+        }
+        let length = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        let chunk_type = &data[pos + 4..pos + 8];
+        let end = pos + 12 + length;
+        if end > data.len() {
+            return Err(invalid("PNG 块长度无效"));
+        }
+        if matches!(chunk_type, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            removed = true;
+        } else {
+            out.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+        if chunk_type == b"IEND" {
+            break;
+        }
+    }
+
+    Ok(removed.then_some(out))
+}
+
+// WebP: 删除 EXIF、XMP 块，清除 VP8X 头中对应的标志位并更新 RIFF 长度
+fn strip_webp(data: &[u8]) -> std::io::Result<Option<Vec<u8>>> {
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return Err(invalid("不是有效的 WebP 文件"));
+    }
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..12]);
+    let mut pos = 12;
+    let mut removed = false;
+    let mut vp8x_flags_pos = None;
+
+    while pos + 8 <= data.len() {
+        let fourcc = &data[pos..pos + 4];
+        let size = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
+        // 块数据按偶数字节对齐
+        let end = pos + 8 + size + (size & 1);
+        if end > data.len() {
+            return Err(invalid("WebP 块长度无效"));
+        }
+        if matches!(fourcc, b"EXIF" | b"XMP ") {
+            removed = true;
+        } else {
+            if fourcc == b"VP8X" && size > 0 {
+                vp8x_flags_pos = Some(out.len() + 8);
+            }
+            out.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+    }
+
+    if !removed {
+        return Ok(None);
+    }
+    if let Some(flags_pos) = vp8x_flags_pos {
+        // 0x08: EXIF, 0x04: XMP
+        out[flags_pos] &= !(0x08 | 0x04);
+    }
+    let riff_size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Ok(Some(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, read_body_json};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    #[actix_web::test]
+    async fn png_uploads_are_stored_as_webp() {
+        let env = TestEnv::with(|c| c.image_convert_to = Some("webp".to_string()));
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let module = env.uploads().join("default");
+        // 已存在的同名 webp 不会被转换结果覆盖
+        std::fs::write(module.join("photo.webp"), b"existing").unwrap();
+
+        let png = test_utils::png(64, 64);
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "photo.png", &png).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        let info = &body["data"][0];
+        let stored = info["filename"].as_str().unwrap().to_string();
+        assert!(stored.ends_with(".webp") && stored != "photo.webp", "{}", body);
+        assert!(info["url"].as_str().unwrap().ends_with(&stored), "{}", body);
+
+        // 转换后的文件是有效的 WebP，体积不大于原图，响应中的大小与存储一致
+        let data = std::fs::read(module.join(&stored)).unwrap();
+        assert!(data.starts_with(b"RIFF") && &data[8..12] == b"WEBP");
+        assert!(data.len() <= png.len(), "{} > {}", data.len(), png.len());
+        assert_eq!(info["size"], data.len());
+        let decoded = image::load_from_memory(&data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (64, 64));
+
+        assert_eq!(std::fs::read(module.join("photo.webp")).unwrap(), b"existing");
+        assert!(!module.join("photo.png").exists());
+    }
+
+    #[actix_web::test]
+    async fn undecodable_images_are_kept_as_is() {
+        let env = TestEnv::with(|c| c.image_convert_to = Some("webp".to_string()));
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let module = env.uploads().join("default");
+
+        // 文件头正确但图像数据被截断
+        let mut png = test_utils::png(32, 32);
+        png.truncate(60);
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "broken.png", &png).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(std::fs::read(module.join("broken.png")).unwrap(), png);
+        assert!(!module.join("broken.webp").exists());
+    }
+
+    // 由 image 编码一张 JPEG，并在 SOI 之后插入带 GPS 信息的 EXIF（APP1）段
+    fn jpeg_with_gps(width: u32, height: u32) -> Vec<u8> {
+        let pixels = image::RgbImage::from_fn(width, height, |x, y| image::Rgb([(x * 4) as u8, (y * 4) as u8, 128]));
+        let mut jpeg = Vec::new();
+        image::DynamicImage::ImageRgb8(pixels)
+            .write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+
+        // 大端 TIFF: IFD0 仅含 GPSInfo 指针，GPS IFD 含纬度方向 "N"
+        let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+        tiff.extend_from_slice(&[0, 1, 0x88, 0x25, 0, 4, 0, 0, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0]);
+        tiff.extend_from_slice(&[0, 1, 0, 1, 0, 2, 0, 0, 0, 2, b'N', 0, 0, 0, 0, 0, 0, 0]);
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend_from_slice(&tiff);
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(&app1);
+
+        jpeg.splice(2..2, segment);
+        jpeg
+    }
+
+    #[actix_web::test]
+    async fn exif_gps_tags_are_stripped_from_jpeg_uploads() {
+        let env = TestEnv::with(|c| c.strip_exif = true);
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let module = env.uploads().join("default");
+
+        let jpeg = jpeg_with_gps(48, 32);
+        let original = image::load_from_memory(&jpeg).unwrap();
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "gps.jpg", &jpeg).to_request()).await;
+        assert_eq!(resp.status(), 200);
+
+        let stored = std::fs::read(module.join("gps.jpg")).unwrap();
+        assert!(stored.len() < jpeg.len());
+        assert!(!stored.windows(6).any(|w| w == b"Exif\0\0"));
+        assert!(!stored.windows(2).any(|w| w == [0xFF, 0xE1]));
+        // 尺寸与像素不变
+        let decoded = image::load_from_memory(&stored).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (48, 32));
+        assert_eq!(decoded.to_rgb8(), original.to_rgb8());
+    }
+
+    #[actix_web::test]
+    async fn exif_is_kept_unless_stripping_is_enabled() {
+        let env = TestEnv::with(|_| {});
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let jpeg = jpeg_with_gps(16, 16);
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "keep.jpg", &jpeg).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(std::fs::read(env.uploads().join("default/keep.jpg")).unwrap(), jpeg);
+    }
+}
@@ -0,0 +1,264 @@
+// 模块校验清单: 遍历模块计算每个文件的 SHA-256，以 NDJSON 流式返回，并缓存到模块根目录的 .manifest.json
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use actix_web::web;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use crate::config::{self, HashAlgorithm};
+use crate::models::ManifestEntry;
+use crate::utils::file_utils;
+
+// 缓存中的条目: 在清单内容之外记录生成时的磁盘状态，用于判断缓存是否过期
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct CachedEntry {
+    #[serde(flatten)]
+    entry: ManifestEntry,
+    stored_size: u64,
+    modified_ms: u64,
+}
+
+// 磁盘上待计算摘要的文件
+struct ScannedFile {
+    path: PathBuf,
+    relative_path: Option<String>,
+    filename: String,
+    stored_size: u64,
+    modified_ms: u64,
+}
+
+impl ScannedFile {
+    fn fingerprint(&self) -> (Option<&str>, &str, u64, u64) {
+        (self.relative_path.as_deref(), &self.filename, self.stored_size, self.modified_ms)
+    }
+}
+
+fn manifest_path(module_path: &Path) -> PathBuf {
+    module_path.join(file_utils::MANIFEST_FILE)
+}
+
+// 递归收集模块内的文件（跳过 sidecar 和清单本身），按相对路径排序
+fn scan_files(base: &Path, current: &str, files: &mut Vec<ScannedFile>) -> std::io::Result<()> {
+    let dir = if current.is_empty() { base.to_path_buf() } else { base.join(current) };
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if file_type.is_file() {
+            if file_utils::is_sidecar_file(&name) {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            let modified_ms = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            files.push(ScannedFile {
+                path: entry.path(),
+                relative_path: Some(current.to_string()).filter(|p| !p.is_empty()),
+                filename: name,
+                stored_size: metadata.len(),
+                modified_ms,
+            });
+        } else if file_type.is_dir() {
+            let sub = if current.is_empty() { name } else { format!("{}/{}", current, name) };
+            scan_files(base, &sub, files)?;
+        }
+    }
+    Ok(())
+}
+
+// 计算文件摘要；压缩存储的文件按解压后的原始内容计算，与下载得到的内容一致
+fn hash_entry(file: &ScannedFile) -> std::io::Result<ManifestEntry> {
+    let compressed = file_utils::read_file_meta(&file.path).is_some_and(|meta| meta.original_size.is_some());
+    let (sha256, size) = file_utils::hash_original_content(&file.path, HashAlgorithm::Sha256, compressed)?;
+    Ok(ManifestEntry {
+        relative_path: file.relative_path.clone(),
+        filename: file.filename.clone(),
+        size,
+        sha256,
+    })
+}
+
+// 读取缓存；磁盘上的文件集合、大小或修改时间与缓存不一致时视为过期
+fn read_valid_cache(module_path: &Path, files: &[ScannedFile]) -> Option<Vec<CachedEntry>> {
+    let content = fs::read(manifest_path(module_path)).ok()?;
+    let cached: Vec<CachedEntry> = serde_json::from_slice(&content).ok()?;
+    let unchanged = cached.len() == files.len()
+        && cached.iter().zip(files).all(|(c, f)| {
+            (c.entry.relative_path.as_deref(), c.entry.filename.as_str(), c.stored_size, c.modified_ms)
+                == f.fingerprint()
+        });
+    unchanged.then_some(cached)
+}
+
+// 先写临时文件再重命名，避免并发请求读到半截缓存
+fn write_cache(module_path: &Path, entries: &[CachedEntry]) -> std::io::Result<()> {
+    let target = manifest_path(module_path);
+    let tmp = module_path.join(format!("{}.tmp.{}", file_utils::MANIFEST_FILE, uuid::Uuid::new_v4()));
+    fs::write(&tmp, serde_json::to_vec(entries).map_err(std::io::Error::other)?)?;
+    fs::rename(&tmp, &target).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+fn to_line(entry: &ManifestEntry) -> Result<web::Bytes, actix_web::Error> {
+    serde_json::to_vec(entry)
+        .map(|mut line| {
+            line.push(b'\n');
+            web::Bytes::from(line)
+        })
+        .map_err(actix_web::error::ErrorInternalServerError)
+}
+
+// 生成模块清单的 NDJSON 流。缓存有效且未要求刷新时直接返回缓存内容，
+// 否则以有限并发在阻塞线程池中计算摘要，边算边输出，全部成功后写回缓存
+pub async fn module_manifest(
+    module: &str,
+    refresh: bool,
+) -> Result<BoxStream<'static, Result<web::Bytes, actix_web::Error>>, String> {
+    let module_path = PathBuf::from(format!("{}/{}", config::uploads_root(), module));
+    if !module_path.is_dir() {
+        return Err(format!("模块 '{}' 不存在", module));
+    }
+
+    let scan_path = module_path.clone();
+    let (files, cached) = tokio::task::spawn_blocking(move || -> Result<_, String> {
+        let mut files = Vec::new();
+        scan_files(&scan_path, "", &mut files).map_err(|e| format!("读取模块目录失败: {}", e))?;
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path).then_with(|| a.filename.cmp(&b.filename)));
+        let cached = if refresh { None } else { read_valid_cache(&scan_path, &files) };
+        Ok((files, cached))
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))??;
+
+    if let Some(cached) = cached {
+        log::debug!("使用模块 '{}' 的清单缓存 ({} 个文件)", module, cached.len());
+        return Ok(stream::iter(cached).map(|c| to_line(&c.entry)).boxed());
+    }
+
+    let concurrency = config::get_config().max_scan_concurrency.max(1);
+    let module_owned = module.to_string();
+    let total = files.len();
+    let hashed = stream::iter(files)
+        .map(|file| async move {
+            tokio::task::spawn_blocking(move || {
+                let entry = hash_entry(&file)
+                    .map_err(|e| format!("计算摘要失败 {}: {}", file.path.display(), e))?;
+                Ok::<_, String>(CachedEntry {
+                    entry,
+                    stored_size: file.stored_size,
+                    modified_ms: file.modified_ms,
+                })
+            })
+            .await
+            .map_err(|e| format!("阻塞任务失败: {}", e))?
+        })
+        .buffered(concurrency);
+
+    // 按顺序输出，同时收集结果；流结束且没有错误时写入缓存
+    let state = (hashed, Vec::with_capacity(total), false, module_path, module_owned);
+    Ok(stream::unfold(state, |(mut hashed, mut done, mut failed, module_path, module)| async move {
+        match hashed.next().await {
+            Some(Ok(cached)) => {
+                let line = to_line(&cached.entry);
+                done.push(cached);
+                Some((line, (hashed, done, failed, module_path, module)))
+            }
+            Some(Err(e)) => {
+                log::error!("生成模块 '{}' 清单失败: {}", module, e);
+                failed = true;
+                Some((Err(actix_web::error::ErrorInternalServerError(e)), (hashed, done, failed, module_path, module)))
+            }
+            None => {
+                if !failed {
+                    let count = done.len();
+                    let result = tokio::task::spawn_blocking(move || write_cache(&module_path, &done)).await;
+                    match result {
+                        Ok(Ok(())) => log::info!("模块 '{}' 清单已生成 ({} 个文件)", module, count),
+                        Ok(Err(e)) => log::warn!("写入模块 '{}' 清单缓存失败: {}", module, e),
+                        Err(e) => log::warn!("写入模块 '{}' 清单缓存失败: {}", module, e),
+                    }
+                }
+                None
+            }
+        }
+    })
+    .boxed())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, read_body, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+    use crate::utils::hash_utils::FileHasher;
+    use super::*;
+
+    fn sha256_hex(data: &[u8]) -> String {
+        let mut hasher = FileHasher::new(HashAlgorithm::Sha256);
+        hasher.update(data);
+        hasher.finalize_hex()
+    }
+
+    fn parse_lines(body: &[u8]) -> Vec<ManifestEntry> {
+        body.split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn manifest_lists_every_file_with_its_hash() {
+        let env = TestEnv::with(|_| {});
+        let module = env.uploads().join("default");
+        let files: [(&str, &[u8]); 3] = [("a.txt", b"alpha"), ("docs/b.txt", b"bravo bravo"), ("docs/deep/c.txt", b"")];
+        for (path, content) in files {
+            std::fs::create_dir_all(module.join(path).parent().unwrap()).unwrap();
+            std::fs::write(module.join(path), content).unwrap();
+        }
+        std::fs::write(module.join(".a.txt.meta.json"), b"{}").unwrap();
+
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let manifest = |refresh: bool| {
+            TestRequest::get()
+                .uri(&format!("/api/modules/default/manifest{}", if refresh { "?refresh=true" } else { "" }))
+                .to_request()
+        };
+
+        let resp = call_service(&app, manifest(false)).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "application/x-ndjson");
+        let entries = parse_lines(&read_body(resp).await);
+        let listed: Vec<(Option<&str>, &str, u64, &str)> = entries
+            .iter()
+            .map(|e| (e.relative_path.as_deref(), e.filename.as_str(), e.size, e.sha256.as_str()))
+            .collect();
+        let expected: Vec<(Option<&str>, &str, u64, String)> = vec![
+            (None, "a.txt", 5, sha256_hex(b"alpha")),
+            (Some("docs"), "b.txt", 11, sha256_hex(b"bravo bravo")),
+            (Some("docs/deep"), "c.txt", 0, sha256_hex(b"")),
+        ];
+        let expected: Vec<_> = expected.iter().map(|(r, f, s, h)| (*r, *f, *s, h.as_str())).collect();
+        assert_eq!(listed, expected);
+        assert!(module.join(file_utils::MANIFEST_FILE).is_file());
+
+        // 缓存不会把自身列入清单；文件变化后缓存失效
+        let resp = call_service(&app, manifest(false)).await;
+        assert_eq!(parse_lines(&read_body(resp).await), entries);
+        std::fs::write(module.join("a.txt"), b"alpha, edited").unwrap();
+        let resp = call_service(&app, manifest(false)).await;
+        let entries = parse_lines(&read_body(resp).await);
+        assert_eq!((entries[0].size, entries[0].sha256.clone()), (13, sha256_hex(b"alpha, edited")));
+
+        let resp = call_service(&app, manifest(true)).await;
+        assert_eq!(parse_lines(&read_body(resp).await), entries);
+
+        let resp = call_service(&app, TestRequest::get().uri("/api/modules/missing/manifest").to_request()).await;
+        assert_eq!(resp.status(), 404);
+    }
+}
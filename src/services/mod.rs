@@ -1,4 +1,12 @@
 pub mod file_service;
 pub mod cleanup_service;
 pub mod system_service;
-pub mod upload_service;
\ No newline at end of file
+pub mod upload_service;
+pub mod image_service;
+pub mod download_service;
+pub mod trash_service;
+pub mod webhook_service;
+pub mod manifest_service;
+pub mod audit_service;
+pub mod tus_service;
+pub mod scan_service;
@@ -0,0 +1,357 @@
+// 可选的 ClamAV 病毒扫描：文件落盘后通过 clamd 的 INSTREAM 协议（TCP 或 unix socket）发送文件内容，
+// 检出病毒时将文件移入 uploads/.quarantine/{module}/ 隔离，不再对外提供
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::fs as tokio_fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::config::{self, ScanMode};
+use crate::models::UploadContext;
+use crate::services::{audit_service, system_service};
+use crate::utils::file_utils;
+
+// 检出病毒（处理器据此返回 422，错误信息为 "{INFECTED_ERROR}: {病毒签名}"）
+pub const INFECTED_ERROR: &str = "文件未通过病毒扫描";
+// 扫描服务不可用或扫描出错（blocking 模式下文件同样被隔离，处理器据此返回 503）
+pub const SCAN_FAILED_ERROR: &str = "病毒扫描失败";
+
+// 单个文件的扫描时限（包括发送文件内容）
+const SCAN_TIMEOUT: Duration = Duration::from_secs(120);
+// INSTREAM 每次发送的数据块大小，需小于 clamd 的 StreamMaxLength
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+// async 模式下扫描出错时的尝试次数与首次重试间隔（之后按次数递增）
+const ASYNC_SCAN_ATTEMPTS: u32 = 3;
+const ASYNC_SCAN_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    // 检出的病毒签名
+    Infected(String),
+}
+
+// 从 INFECTED_ERROR 错误信息中取出病毒签名
+pub fn infected_signature(error: &str) -> Option<&str> {
+    error
+        .strip_prefix(INFECTED_ERROR)
+        .map(|rest| rest.trim_start_matches(':').trim())
+}
+
+// 解析 clamd 的响应: "stream: OK" / "stream: {签名} FOUND" / "... ERROR"
+fn parse_reply(reply: &str) -> Result<ScanVerdict, String> {
+    let reply = reply.trim_end_matches('\0').trim();
+    let result = reply.split_once(": ").map(|(_, result)| result).unwrap_or(reply);
+    if result == "OK" {
+        Ok(ScanVerdict::Clean)
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        Ok(ScanVerdict::Infected(signature.trim().to_string()))
+    } else {
+        Err(format!("clamd 返回错误: {}", reply))
+    }
+}
+
+// 按 INSTREAM 协议发送文件：zINSTREAM\0，随后为若干 [4 字节大端长度][数据]，以长度 0 结束
+async fn scan_stream<S>(mut stream: S, path: &Path) -> Result<ScanVerdict, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut file = tokio_fs::File::open(path)
+        .await
+        .map_err(|e| format!("读取文件失败: {}", e))?;
+    stream
+        .write_all(b"zINSTREAM\0")
+        .await
+        .map_err(|e| format!("发送扫描请求失败: {}", e))?;
+
+    let mut buffer = vec![0u8; STREAM_CHUNK_SIZE];
+    loop {
+        let n = file.read(&mut buffer).await.map_err(|e| format!("读取文件失败: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        stream
+            .write_all(&(n as u32).to_be_bytes())
+            .await
+            .map_err(|e| format!("发送文件内容失败: {}", e))?;
+        stream
+            .write_all(&buffer[..n])
+            .await
+            .map_err(|e| format!("发送文件内容失败: {}", e))?;
+    }
+    stream
+        .write_all(&0u32.to_be_bytes())
+        .await
+        .map_err(|e| format!("发送文件内容失败: {}", e))?;
+    stream.flush().await.map_err(|e| format!("发送文件内容失败: {}", e))?;
+
+    let mut reply = Vec::new();
+    stream
+        .read_to_end(&mut reply)
+        .await
+        .map_err(|e| format!("读取扫描结果失败: {}", e))?;
+    parse_reply(&String::from_utf8_lossy(&reply))
+}
+
+async fn scan_with(address: &str, path: &Path) -> Result<ScanVerdict, String> {
+    if let Some(socket) = address.strip_prefix("unix:") {
+        #[cfg(unix)]
+        {
+            let stream = tokio::net::UnixStream::connect(socket)
+                .await
+                .map_err(|e| format!("连接 clamd 失败 ({}): {}", address, e))?;
+            return scan_stream(stream, path).await;
+        }
+        #[cfg(not(unix))]
+        {
+            return Err(format!("当前平台不支持 unix socket: {}", socket));
+        }
+    }
+    let stream = TcpStream::connect(address)
+        .await
+        .map_err(|e| format!("连接 clamd 失败 ({}): {}", address, e))?;
+    scan_stream(stream, path).await
+}
+
+// 扫描单个文件；未配置 clamav_address 时视为干净
+pub async fn scan_file(path: &Path) -> Result<ScanVerdict, String> {
+    let Some(address) = config::get_config().clamav_address.as_deref() else {
+        return Ok(ScanVerdict::Clean);
+    };
+    tokio::time::timeout(SCAN_TIMEOUT, scan_with(address, path))
+        .await
+        .map_err(|_| format!("扫描超时 ({} 秒)", SCAN_TIMEOUT.as_secs()))?
+}
+
+// 将模块内的文件（连同 sidecar 元数据）移入 .quarantine/{module}/，保留相对路径，返回隔离后的路径
+pub async fn quarantine(module: &str, path: &Path) -> Result<PathBuf, String> {
+    let module_root = Path::new(config::uploads_root()).join(module);
+    let relative = path.strip_prefix(&module_root).unwrap_or(Path::new(path.file_name().unwrap_or_default()));
+    let target = Path::new(config::uploads_root())
+        .join(file_utils::QUARANTINE_DIR)
+        .join(module)
+        .join(relative);
+    let source = path.to_path_buf();
+
+    tokio::task::spawn_blocking(move || -> Result<PathBuf, String> {
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("创建隔离目录失败: {}", e))?;
+        }
+        let name = target.file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let target = PathBuf::from(file_utils::generate_unique_filename(&name, &target.to_string_lossy()));
+        std::fs::rename(&source, &target).map_err(|e| format!("隔离文件失败: {}", e))?;
+        let _ = std::fs::rename(file_utils::sidecar_path(&source), file_utils::sidecar_path(&target));
+        system_service::record_files_removed(1);
+        Ok(target)
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))?
+}
+
+// 隔离未通过扫描的文件（隔离失败时删除），error 为记入审计日志的原因
+async fn reject_file(module: &str, path: &Path, context: &UploadContext, error: &str) {
+    let client_ip = context.source_ip.as_deref().unwrap_or("unknown");
+    let relative = path
+        .strip_prefix(Path::new(config::uploads_root()).join(module))
+        .map(|p| p.to_string_lossy().to_string())
+        .ok();
+    match quarantine(module, path).await {
+        Ok(target) => {
+            log::warn!("已隔离文件: {} -> {}", path.display(), target.display());
+            audit_service::record("quarantine", module, relative.as_deref(), None, client_ip, context.uploader.as_deref(), Some(error));
+        }
+        Err(e) => {
+            // 隔离失败时删除文件，避免未通过扫描的文件继续对外提供
+            log::error!("隔离文件失败 {}: {}", path.display(), e);
+            let _ = tokio_fs::remove_file(path).await;
+            file_utils::remove_file_meta(path);
+            system_service::record_files_removed(1);
+            audit_service::record("quarantine", module, relative.as_deref(), None, client_ip, context.uploader.as_deref(), Some(&e));
+        }
+    }
+}
+
+// blocking 模式：扫描未通过（检出病毒或扫描出错）时隔离文件并返回与 INFECTED_ERROR / SCAN_FAILED_ERROR 对应的错误
+async fn scan_and_quarantine(module: &str, path: &Path, context: &UploadContext) -> Result<(), String> {
+    let error = match scan_file(path).await {
+        Ok(ScanVerdict::Clean) => return Ok(()),
+        Ok(ScanVerdict::Infected(signature)) => {
+            log::warn!("检出病毒 {}: {}", path.display(), signature);
+            format!("{}: {}", INFECTED_ERROR, signature)
+        }
+        Err(e) => {
+            log::error!("病毒扫描失败 {}: {}", path.display(), e);
+            format!("{}: {}", SCAN_FAILED_ERROR, e)
+        }
+    };
+    reject_file(module, path, context, &error).await;
+    Err(error)
+}
+
+// async 模式：客户端已收到上传成功，只在检出病毒时隔离；扫描出错时按递增间隔重试，
+// 多次失败后保留文件并记录 scan_failed 审计，不因扫描服务故障丢弃已确认的上传
+async fn scan_in_background(module: &str, path: &Path, context: &UploadContext) {
+    for attempt in 1..=ASYNC_SCAN_ATTEMPTS {
+        match scan_file(path).await {
+            Ok(ScanVerdict::Clean) => return,
+            Ok(ScanVerdict::Infected(signature)) => {
+                log::warn!("检出病毒 {}: {}", path.display(), signature);
+                reject_file(module, path, context, &format!("{}: {}", INFECTED_ERROR, signature)).await;
+                return;
+            }
+            Err(e) if attempt < ASYNC_SCAN_ATTEMPTS => {
+                log::warn!("病毒扫描失败 {}（第 {} 次，稍后重试）: {}", path.display(), attempt, e);
+                tokio::time::sleep(ASYNC_SCAN_RETRY_DELAY * attempt).await;
+            }
+            Err(e) => {
+                log::error!("病毒扫描多次失败，文件未经扫描保留 {}: {}", path.display(), e);
+                let relative = path
+                    .strip_prefix(Path::new(config::uploads_root()).join(module))
+                    .map(|p| p.to_string_lossy().to_string())
+                    .ok();
+                let client_ip = context.source_ip.as_deref().unwrap_or("unknown");
+                audit_service::record("scan_failed", module, relative.as_deref(), None, client_ip, context.uploader.as_deref(), Some(&e));
+            }
+        }
+    }
+}
+
+// 文件落盘后调用：未启用扫描时直接返回；blocking 模式等待扫描结果，未通过时返回错误；
+// async 模式在后台扫描，立即返回，检出病毒时稍后隔离
+pub async fn check_uploaded(module: &str, path: &Path, context: &UploadContext) -> Result<(), String> {
+    let config = config::get_config();
+    if config.clamav_address.is_none() {
+        return Ok(());
+    }
+    match config.scan_mode {
+        ScanMode::Blocking => scan_and_quarantine(module, path, context).await,
+        ScanMode::Async => {
+            let module = module.to_string();
+            let path = path.to_path_buf();
+            let context = context.clone();
+            tokio::spawn(async move {
+                scan_in_background(&module, &path, &context).await;
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, read_body_json};
+    use tokio::net::TcpListener;
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    const EICAR_MARKER: &[u8] = b"EICAR-STANDARD-ANTIVIRUS-TEST-FILE";
+
+    // 模拟 clamd：读取 INSTREAM 数据，内容包含 EICAR 标记时报告病毒
+    async fn mock_clamd() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut command = [0u8; 10];
+                    stream.read_exact(&mut command).await.unwrap();
+                    assert_eq!(&command, b"zINSTREAM\0");
+                    let mut content = Vec::new();
+                    loop {
+                        let len = stream.read_u32().await.unwrap() as usize;
+                        if len == 0 {
+                            break;
+                        }
+                        let mut chunk = vec![0u8; len];
+                        stream.read_exact(&mut chunk).await.unwrap();
+                        content.extend_from_slice(&chunk);
+                    }
+                    let infected = content.windows(EICAR_MARKER.len()).any(|w| w == EICAR_MARKER);
+                    let reply: &[u8] = if infected { b"stream: Eicar-Test-Signature FOUND\0" } else { b"stream: OK\0" };
+                    stream.write_all(reply).await.unwrap();
+                });
+            }
+        });
+        address
+    }
+
+    // 已关闭的端口，连接会被拒绝
+    async fn unreachable_address() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    async fn wait_until(mut condition: impl FnMut() -> bool) -> bool {
+        for _ in 0..50 {
+            if condition() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        condition()
+    }
+
+    #[test]
+    fn parses_clamd_replies() {
+        assert_eq!(parse_reply("stream: OK\0"), Ok(ScanVerdict::Clean));
+        assert_eq!(
+            parse_reply("stream: Eicar-Test-Signature FOUND\0"),
+            Ok(ScanVerdict::Infected("Eicar-Test-Signature".to_string()))
+        );
+        assert!(parse_reply("INSTREAM size limit exceeded. ERROR").is_err());
+        assert_eq!(infected_signature(&format!("{}: Eicar", INFECTED_ERROR)), Some("Eicar"));
+    }
+
+    #[actix_web::test]
+    async fn blocking_scan_quarantines_infected_uploads() {
+        let address = mock_clamd().await;
+        let env = TestEnv::with(|c| c.clamav_address = Some(address));
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let mut infected = b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$".to_vec();
+        infected.extend_from_slice(EICAR_MARKER);
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "virus.txt", &infected).to_request()).await;
+        assert_eq!(resp.status(), 422);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["data"]["code"], "VIRUS_FOUND");
+        assert!(!env.uploads().join("default/virus.txt").exists());
+        assert!(env.uploads().join(file_utils::QUARANTINE_DIR).join("default/virus.txt").exists());
+
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "clean.txt", b"hello").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert!(env.uploads().join("default/clean.txt").exists());
+    }
+
+    #[actix_web::test]
+    async fn async_scan_quarantines_only_infected_files() {
+        let address = mock_clamd().await;
+        let env = TestEnv::with(|c| {
+            c.clamav_address = Some(address);
+            c.scan_mode = ScanMode::Async;
+        });
+        let context = UploadContext::default();
+
+        let infected_path = env.uploads().join("default/virus.txt");
+        std::fs::write(&infected_path, EICAR_MARKER).unwrap();
+        assert_eq!(check_uploaded("default", &infected_path, &context).await, Ok(()));
+        let quarantined = env.uploads().join(file_utils::QUARANTINE_DIR).join("default/virus.txt");
+        assert!(wait_until(|| quarantined.exists()).await);
+        assert!(!infected_path.exists());
+    }
+
+    #[actix_web::test]
+    async fn async_scan_keeps_files_when_the_scanner_is_down() {
+        let address = unreachable_address().await;
+        let env = TestEnv::with(|c| {
+            c.clamav_address = Some(address);
+            c.scan_mode = ScanMode::Async;
+        });
+
+        let path = env.uploads().join("default/report.txt");
+        std::fs::write(&path, b"report").unwrap();
+        assert_eq!(check_uploaded("default", &path, &UploadContext::default()).await, Ok(()));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(path.exists());
+        assert!(!env.uploads().join(file_utils::QUARANTINE_DIR).join("default/report.txt").exists());
+    }
+}
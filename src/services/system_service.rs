@@ -1,24 +1,35 @@
 use actix_web::web;
 use chrono::Utc;
-use crate::state::{AppState, SERVER_START_TIME};
+use std::sync::atomic::Ordering;
+use crate::config;
+use crate::state::{AppState, SERVER_START_TIME, TOTAL_FILES};
 use crate::utils::lock_utils;
 
-pub async fn get_health_info(state: web::Data<AppState>) -> serde_json::Value {
+// 返回 (是否健康, 详情)；存储不可写或剩余空间低于 min_free_bytes 时为 degraded
+pub async fn get_health_info(state: web::Data<AppState>) -> (bool, serde_json::Value) {
     let resources = monitor_system_resources().await;
     let app_stats = state.get_stats();
-    
-    serde_json::json!({
-        "status": "healthy",
+
+    let disk_writable = resources["disk_writable"].as_bool().unwrap_or(false);
+    let low_space = resources["free_bytes"]
+        .as_u64()
+        .is_some_and(|free| free < config::get_config().min_free_bytes);
+    let healthy = disk_writable && !low_space;
+
+    let info = serde_json::json!({
+        "status": if healthy { "healthy" } else { "degraded" },
         "timestamp": Utc::now().to_rfc3339(),
+        "read_only": state.is_read_only(),
         "resources": resources,
         "app_stats": app_stats,
-    })
+    });
+    (healthy, info)
 }
 
 pub async fn get_system_stats(state: web::Data<AppState>) -> Result<serde_json::Value, String> {
     let stats = tokio::task::spawn_blocking(|| -> Result<serde_json::Value, String> {
-        let uploads_dir = "./uploads";
-        let temp_dir = "./temp";
+        let uploads_dir = config::uploads_root();
+        let temp_dir = config::temp_root();
         let mut total_modules = 0usize;
         let mut total_files = 0usize;
         let mut total_size = 0u64;
@@ -31,7 +42,7 @@ pub async fn get_system_stats(state: web::Data<AppState>) -> Result<serde_json::
                 if let Ok(file_type) = entry.file_type() {
                     if file_type.is_dir() {
                         let name = entry.file_name().to_string_lossy().to_string();
-                        if name != "." && name != ".." {
+                        if name != "." && name != ".." && !crate::utils::file_utils::is_reserved_dir(&name) {
                             total_modules += 1;
                             let _ = crate::utils::file_utils::count_files_recursive(
                                 &entry.path(), &mut total_files, &mut total_size
@@ -67,7 +78,7 @@ pub async fn get_system_stats(state: web::Data<AppState>) -> Result<serde_json::
             "total_files": total_files,
             "total_size": total_size,
             "total_size_mb": (total_size as f64 / 1024.0 / 1024.0).round() as u64,
-            "total_size_gb": (total_size as f64 / 1024.0 / 1024.0 / 1024.0).round() as f64,
+            "total_size_gb": (total_size as f64 / 1024.0 / 1024.0 / 1024.0).round(),
             "temp_files_count": temp_files_count,
             "temp_files_size": temp_files_size,
         });
@@ -75,6 +86,11 @@ pub async fn get_system_stats(state: web::Data<AppState>) -> Result<serde_json::
         Ok(stats)
     }).await.map_err(|e| format!("阻塞任务失败: {}", e))??;
 
+    // 顺带刷新文件总数缓存
+    if let Some(total_files) = stats.get("total_files").and_then(|v| v.as_u64()) {
+        TOTAL_FILES.store(total_files, Ordering::Relaxed);
+    }
+
     // 合并应用状态统计
     let mut stats_value = stats;
     if let Some(obj) = stats_value.as_object_mut() {
@@ -84,33 +100,303 @@ pub async fn get_system_stats(state: web::Data<AppState>) -> Result<serde_json::
     Ok(stats_value)
 }
 
+// 按配置载入吞吐历史，并定期写回持久化文件
+pub async fn persist_throughput_history(state: AppState) {
+    let config = crate::config::get_config();
+    let Some(path) = config.stats_history_path.as_ref().map(std::path::PathBuf::from) else {
+        return;
+    };
+
+    let history = state.throughput_history.clone();
+    let load_path = path.clone();
+    match tokio::task::spawn_blocking(move || history.load_from(&load_path)).await {
+        Ok(Ok(())) => log::info!("已载入吞吐历史: {}", path.display()),
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Ok(Err(e)) => log::warn!("载入吞吐历史失败 {}: {}", path.display(), e),
+        Err(e) => log::warn!("载入吞吐历史任务失败: {}", e),
+    }
+
+    let mut interval = tokio::time::interval(config.stats_history_resolution);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        save_throughput_history(&state).await;
+    }
+}
+
+pub async fn save_throughput_history(state: &AppState) {
+    let Some(path) = crate::config::get_config().stats_history_path.as_ref().map(std::path::PathBuf::from) else {
+        return;
+    };
+    let history = state.throughput_history.clone();
+    match tokio::task::spawn_blocking(move || history.save_to(&path)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::warn!("保存吞吐历史失败: {}", e),
+        Err(e) => log::warn!("保存吞吐历史任务失败: {}", e),
+    }
+}
+
+// 全量扫描上传目录，刷新文件总数缓存
+pub async fn refresh_file_count() -> Result<u64, String> {
+    let (total_files, _) =
+        crate::utils::file_utils::count_files_async(std::path::PathBuf::from(config::uploads_root())).await?;
+    let total_files = total_files as u64;
+
+    TOTAL_FILES.store(total_files, Ordering::Relaxed);
+    log::debug!("文件总数缓存已刷新: {}", total_files);
+    Ok(total_files)
+}
+
+pub fn record_files_added(count: u64) {
+    TOTAL_FILES.fetch_add(count, Ordering::Relaxed);
+}
+
+pub fn record_files_removed(count: u64) {
+    let _ = TOTAL_FILES.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+        Some(current.saturating_sub(count))
+    });
+}
+
+// 新增 additional 个文件后是否会超过全局文件数上限
+pub fn file_count_limit_exceeded(additional: u64) -> bool {
+    match crate::config::get_config().max_total_files {
+        Some(max_total_files) => TOTAL_FILES.load(Ordering::Relaxed) + additional > max_total_files,
+        None => false,
+    }
+}
+
+// 存储目录所在文件系统的容量信息
+#[derive(Debug, Clone, Copy)]
+pub struct StorageInfo {
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+    pub total_inodes: u64,
+    pub free_inodes: u64,
+}
+
+// 通过 statvfs 查询文件系统容量（非 unix 平台返回 None）
+#[cfg(unix)]
+pub fn query_storage(path: &str) -> Option<StorageInfo> {
+    let c_path = std::ffi::CString::new(path).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path 是以 NUL 结尾的有效字符串，stat 为可写的 statvfs 结构
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let fragment_size = stat.f_frsize as u64;
+    Some(StorageInfo {
+        total_bytes: stat.f_blocks as u64 * fragment_size,
+        free_bytes: stat.f_bavail as u64 * fragment_size,
+        total_inodes: stat.f_files as u64,
+        free_inodes: stat.f_favail as u64,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn query_storage(_path: &str) -> Option<StorageInfo> {
+    None
+}
+
+// 上传前的 inode 检查：开启检查且可用 inode 低于阈值时返回当前可用数量
+pub fn inode_shortage() -> Option<u64> {
+    let config = crate::config::get_config();
+    if !config.check_free_inodes {
+        return None;
+    }
+    let storage = query_storage(config::uploads_root())?;
+    (storage.free_inodes < config.min_free_inodes).then_some(storage.free_inodes)
+}
+
+// 已知上传大小时的剩余空间检查：可用空间容纳不下 required 字节时返回当前可用字节数
+pub fn space_shortage(required: u64) -> Option<u64> {
+    let storage = query_storage(config::uploads_root())?;
+    (storage.free_bytes < required).then_some(storage.free_bytes)
+}
+
+pub async fn get_storage_info() -> Result<serde_json::Value, String> {
+    let storage = tokio::task::spawn_blocking(|| query_storage(config::uploads_root()))
+        .await
+        .map_err(|e| format!("阻塞任务失败: {}", e))?
+        .ok_or_else(|| "无法获取存储信息".to_string())?;
+    let config = crate::config::get_config();
+
+    Ok(serde_json::json!({
+        "total_bytes": storage.total_bytes,
+        "free_bytes": storage.free_bytes,
+        "total_inodes": storage.total_inodes,
+        "free_inodes": storage.free_inodes,
+        "check_free_inodes": config.check_free_inodes,
+        "min_free_inodes": config.min_free_inodes,
+    }))
+}
+
+// 内存使用情况（字节）
+struct MemoryUsage {
+    used_bytes: u64,
+    limit_bytes: u64,
+    source: &'static str,
+}
+
+// 读取单值的 cgroup 文件，"max" 或超大值（v1 的无限制）视为无上限
+fn read_cgroup_value(path: &str) -> Option<u64> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let value = content.trim().parse::<u64>().ok()?;
+    // cgroup v1 未设置上限时返回接近 i64::MAX 的页对齐值
+    if value >= (i64::MAX as u64) & !0xfff {
+        return None;
+    }
+    Some(value)
+}
+
+// 读取 /proc/meminfo，返回 (已用, 总量) 字节数
+fn read_meminfo() -> Option<(u64, u64)> {
+    let content = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let mut total = None;
+    let mut free = None;
+    let mut available = None;
+    for line in content.lines() {
+        let value = || line.split_whitespace().nth(1).and_then(|s| s.parse::<u64>().ok());
+        if line.starts_with("MemTotal:") {
+            total = value();
+        } else if line.starts_with("MemFree:") {
+            free = value();
+        } else if line.starts_with("MemAvailable:") {
+            available = value();
+        }
+    }
+    let total = total? * 1024;
+    let free = available.or(free).unwrap_or(0) * 1024;
+    Some((total.saturating_sub(free), total))
+}
+
+// 优先读取 cgroup 限制（容器内更准确），其次 /proc/meminfo
+fn read_memory_usage() -> Option<MemoryUsage> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+
+    // cgroup v2
+    if let (Some(limit), Some(used)) = (
+        read_cgroup_value("/sys/fs/cgroup/memory.max"),
+        read_cgroup_value("/sys/fs/cgroup/memory.current"),
+    ) {
+        return Some(MemoryUsage { used_bytes: used, limit_bytes: limit, source: "cgroup_v2" });
+    }
+
+    // cgroup v1
+    if let (Some(limit), Some(used)) = (
+        read_cgroup_value("/sys/fs/cgroup/memory/memory.limit_in_bytes"),
+        read_cgroup_value("/sys/fs/cgroup/memory/memory.usage_in_bytes"),
+    ) {
+        return Some(MemoryUsage { used_bytes: used, limit_bytes: limit, source: "cgroup_v1" });
+    }
+
+    read_meminfo().map(|(used, total)| MemoryUsage {
+        used_bytes: used,
+        limit_bytes: total,
+        source: "meminfo",
+    })
+}
+
+// 在临时目录和上传目录中各写入并删除一个小文件，确认存储实际可写（阻塞调用）
+fn probe_disk_writable() -> bool {
+    [config::temp_root(), config::uploads_root()].into_iter().all(|dir| {
+        let probe = std::path::Path::new(dir).join(format!(".health_check.tmp.{}", uuid::Uuid::new_v4()));
+        let writable = std::fs::write(&probe, b"ok").is_ok();
+        let _ = std::fs::remove_file(&probe);
+        if !writable {
+            log::warn!("健康检查: 目录不可写 {}", dir);
+        }
+        writable
+    })
+}
+
 // 系统资源监控
 pub async fn monitor_system_resources() -> serde_json::Value {
-    let memory_info = if cfg!(target_os = "linux") {
-        if let Ok(content) = std::fs::read_to_string("/proc/meminfo") {
-            let mut total = 0;
-            let mut free = 0;
-            for line in content.lines() {
-                if line.starts_with("MemTotal:") {
-                    total = line.split_whitespace().nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-                } else if line.starts_with("MemFree:") {
-                    free = line.split_whitespace().nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-                }
-            }
-            format!("{}MB/{}MB", free / 1024, total / 1024)
-        } else {
-            "unknown".to_string()
-        }
-    } else {
-        "unknown".to_string()
+    let memory_usage = read_memory_usage();
+    // 兼容旧字段: "可用MB/总量MB"
+    let memory_info = match &memory_usage {
+        Some(usage) => format!(
+            "{}MB/{}MB",
+            usage.limit_bytes.saturating_sub(usage.used_bytes) / 1024 / 1024,
+            usage.limit_bytes / 1024 / 1024
+        ),
+        None => "unknown".to_string(),
+    };
+    let memory = match &memory_usage {
+        Some(usage) => serde_json::json!({
+            "used_bytes": usage.used_bytes,
+            "limit_bytes": usage.limit_bytes,
+            "source": usage.source,
+        }),
+        None => serde_json::Value::Null,
     };
 
     let file_locks_count = lock_utils::get_file_lock_count().await;
+    let upload_progress_count = crate::services::upload_service::get_progress_count().await;
+    let (disk_writable, storage) = tokio::task::spawn_blocking(|| {
+        (probe_disk_writable(), query_storage(config::uploads_root()))
+    })
+    .await
+    .unwrap_or((false, None));
 
     serde_json::json!({
         "timestamp": Utc::now().to_rfc3339(),
         "file_locks_count": file_locks_count,
+        "upload_progress_count": upload_progress_count,
         "uptime_seconds": Utc::now().timestamp() as u64 - SERVER_START_TIME.load(std::sync::atomic::Ordering::Relaxed),
         "memory_usage": memory_info,
+        "memory": memory,
+        "disk_writable": disk_writable,
+        "free_bytes": storage.map(|s| s.free_bytes),
     })
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, read_body_json, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    // 返回 (状态码, data)
+    macro_rules! health {
+        ($app:expr) => {{
+            let resp = call_service($app, TestRequest::get().uri("/api/health").to_request()).await;
+            let status = resp.status().as_u16();
+            let body: serde_json::Value = read_body_json(resp).await;
+            (status, body["data"].clone())
+        }};
+    }
+
+    #[actix_web::test]
+    async fn health_is_degraded_when_storage_is_not_writable() {
+        let env = TestEnv::with(|c| c.min_free_bytes = 0);
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let (status, data) = health!(&app);
+        assert_eq!(status, 200);
+        assert_eq!(data["status"], "healthy");
+        assert_eq!(data["resources"]["disk_writable"], true);
+        assert!(data["resources"]["free_bytes"].as_u64().unwrap() > 0);
+
+        // 用同名普通文件替换临时目录：即使以 root 运行也无法在其中创建文件
+        let temp = env.root.join("temp");
+        std::fs::remove_dir_all(&temp).unwrap();
+        std::fs::write(&temp, b"not a directory").unwrap();
+        let (status, data) = health!(&app);
+        assert_eq!(status, 503);
+        assert_eq!(data["status"], "degraded");
+        assert_eq!(data["resources"]["disk_writable"], false);
+    }
+
+    #[actix_web::test]
+    async fn health_is_degraded_below_the_free_space_floor() {
+        let _env = TestEnv::with(|c| c.min_free_bytes = u64::MAX);
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let (status, data) = health!(&app);
+        assert_eq!(status, 503);
+        assert_eq!(data["status"], "degraded");
+        assert_eq!(data["resources"]["disk_writable"], true);
+    }
+}
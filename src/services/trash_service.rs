@@ -0,0 +1,298 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use crate::config;
+use crate::models::TrashItem;
+use crate::services::{file_service, system_service};
+use crate::utils::file_utils;
+
+// 回收站条目名: "{删除时间}_{原名}"，删除时间精确到毫秒（17 位数字）
+const TRASH_TIME_FORMAT: &str = "%Y%m%d%H%M%S%3f";
+const TRASH_TIME_LEN: usize = 17;
+
+// 恢复的条目不存在或名称不符合命名规则（处理器据此返回 404）
+pub const TRASH_ITEM_NOT_FOUND: &str = "回收站条目不存在";
+
+fn trash_root() -> PathBuf {
+    Path::new(config::uploads_root()).join(file_utils::TRASH_DIR)
+}
+
+fn module_trash_dir(module: &str) -> PathBuf {
+    trash_root().join(module)
+}
+
+// 解析回收站条目名，返回 (删除时间, 原名)；不符合命名规则时返回 None
+fn parse_trash_name(name: &str) -> Option<(DateTime<Utc>, &str)> {
+    let (time, original) = name.split_once('_')?;
+    if time.len() != TRASH_TIME_LEN || !time.bytes().all(|b| b.is_ascii_digit()) || original.is_empty() {
+        return None;
+    }
+    let deleted_at = NaiveDateTime::parse_from_str(time, TRASH_TIME_FORMAT).ok()?.and_utc();
+    Some((deleted_at, original))
+}
+
+fn entry_size(path: &Path, is_dir: bool) -> u64 {
+    if is_dir {
+        let mut count = 0;
+        let mut size = 0;
+        let _ = file_utils::count_files_recursive(path, &mut count, &mut size);
+        size
+    } else {
+        fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    }
+}
+
+// 将模块内的文件或文件夹移入回收站（保留相对路径），返回回收站条目名
+pub async fn move_to_trash(module: &str, relative_path: &str) -> Result<String, String> {
+    let module = module.to_string();
+    let relative_path = relative_path.trim_matches('/').to_string();
+    let (name, is_dir) = tokio::task::spawn_blocking(move || -> Result<(String, bool), String> {
+        let source = Path::new(config::uploads_root()).join(&module).join(&relative_path);
+        let metadata = fs::metadata(&source).map_err(|e| format!("读取文件信息失败: {}", e))?;
+
+        let rel = Path::new(&relative_path);
+        let original_name = rel
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .ok_or_else(|| "无效的路径".to_string())?;
+        let parent = rel.parent().unwrap_or(Path::new(""));
+
+        let target_dir = module_trash_dir(&module).join(parent);
+        fs::create_dir_all(&target_dir).map_err(|e| format!("创建回收站目录失败: {}", e))?;
+
+        let trash_name = format!("{}_{}", Utc::now().format(TRASH_TIME_FORMAT), original_name);
+        let target = file_utils::generate_unique_filename(
+            &trash_name,
+            &target_dir.join(&trash_name).to_string_lossy(),
+        );
+        let target = PathBuf::from(target);
+
+        fs::rename(&source, &target).map_err(|e| format!("移入回收站失败: {}", e))?;
+        if metadata.is_file() {
+            let _ = fs::rename(file_utils::sidecar_path(&source), file_utils::sidecar_path(&target));
+        }
+
+        let stored_name = target.file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or(trash_name);
+        Ok((parent.join(stored_name).to_string_lossy().to_string(), metadata.is_dir()))
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))??;
+
+    if is_dir {
+        tokio::spawn(system_service::refresh_file_count());
+    } else {
+        system_service::record_files_removed(1);
+    }
+    Ok(name)
+}
+
+fn collect_trash_items(base: &Path, current: &Path, items: &mut Vec<TrashItem>) -> std::io::Result<()> {
+    for entry in fs::read_dir(current)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if file_utils::is_sidecar_file(&name) {
+            continue;
+        }
+
+        let path = entry.path();
+        match parse_trash_name(&name) {
+            Some((deleted_at, original)) => {
+                let relative_dir = current
+                    .strip_prefix(base)
+                    .ok()
+                    .map(|p| p.to_string_lossy().replace('\\', "/"))
+                    .filter(|p| !p.is_empty());
+                let item_name = match &relative_dir {
+                    Some(dir) => format!("{}/{}", dir, name),
+                    None => name.clone(),
+                };
+                items.push(TrashItem {
+                    name: item_name,
+                    original_name: original.to_string(),
+                    relative_path: relative_dir,
+                    deleted_at: deleted_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    size: entry_size(&path, file_type.is_dir()),
+                    is_dir: file_type.is_dir(),
+                });
+            }
+            // 保留原相对路径的中间目录
+            None if file_type.is_dir() => collect_trash_items(base, &path, items)?,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+// 列出模块回收站中的条目，按删除时间倒序
+pub async fn list_trash(module: &str) -> Result<Vec<TrashItem>, String> {
+    let base = module_trash_dir(module);
+    tokio::task::spawn_blocking(move || -> Result<Vec<TrashItem>, String> {
+        let mut items = Vec::new();
+        match collect_trash_items(&base, &base, &mut items) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("读取回收站失败: {}", e)),
+        }
+        items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at).then_with(|| a.name.cmp(&b.name)));
+        Ok(items)
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))?
+}
+
+// 将回收站条目恢复到原位置，返回恢复后的相对路径；原位置已有同名文件时返回 FILE_EXISTS_ERROR
+pub async fn restore_from_trash(module: &str, name: &str) -> Result<String, String> {
+    let module = module.to_string();
+    let name = name.trim_matches('/').to_string();
+    let (restored, is_dir) = tokio::task::spawn_blocking(move || -> Result<(String, bool), String> {
+        let rel = Path::new(&name);
+        let trash_name = rel
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .ok_or_else(|| TRASH_ITEM_NOT_FOUND.to_string())?;
+        let (_, original) = parse_trash_name(&trash_name).ok_or_else(|| TRASH_ITEM_NOT_FOUND.to_string())?;
+        let parent = rel.parent().unwrap_or(Path::new(""));
+
+        let source = module_trash_dir(&module).join(rel);
+        let metadata = fs::metadata(&source).map_err(|_| TRASH_ITEM_NOT_FOUND.to_string())?;
+
+        let target_dir = Path::new(config::uploads_root()).join(&module).join(parent);
+        let target = target_dir.join(original);
+        if target.exists() {
+            return Err(file_service::FILE_EXISTS_ERROR.to_string());
+        }
+        fs::create_dir_all(&target_dir).map_err(|e| format!("创建目录失败: {}", e))?;
+        fs::rename(&source, &target).map_err(|e| format!("恢复失败: {}", e))?;
+        if metadata.is_file() {
+            let _ = fs::rename(file_utils::sidecar_path(&source), file_utils::sidecar_path(&target));
+        }
+
+        Ok((parent.join(original).to_string_lossy().to_string(), metadata.is_dir()))
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))??;
+
+    if is_dir {
+        tokio::spawn(system_service::refresh_file_count());
+    } else {
+        system_service::record_files_added(1);
+    }
+    Ok(restored)
+}
+
+// 递归删除早于 cutoff 的回收站条目，并移除清空后的中间目录
+fn purge_dir(dir: &Path, cutoff: DateTime<Utc>, removed: &mut usize, freed: &mut u64) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+
+        match parse_trash_name(&name) {
+            Some((deleted_at, _)) if deleted_at < cutoff => {
+                let size = entry_size(&path, file_type.is_dir());
+                let result = if file_type.is_dir() {
+                    fs::remove_dir_all(&path)
+                } else {
+                    fs::remove_file(&path).inspect(|_| file_utils::remove_file_meta(&path))
+                };
+                match result {
+                    Ok(()) => {
+                        *removed += 1;
+                        *freed += size;
+                    }
+                    Err(e) => log::warn!("清理回收站条目失败 {}: {}", path.display(), e),
+                }
+            }
+            Some(_) => {}
+            None if file_type.is_dir() => {
+                purge_dir(&path, cutoff, removed, freed)?;
+                // 目录已空时删除（非空时失败，忽略）
+                let _ = fs::remove_dir(&path);
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+// 清理超过保留期的回收站条目，返回 (删除条目数, 释放字节数)
+pub async fn purge_expired_trash(retention: Duration) -> Result<(usize, u64), String> {
+    tokio::task::spawn_blocking(move || -> Result<(usize, u64), String> {
+        let retention = chrono::Duration::from_std(retention).map_err(|e| format!("无效的保留期: {}", e))?;
+        let cutoff = Utc::now() - retention;
+        let mut removed = 0;
+        let mut freed = 0;
+        match purge_dir(&trash_root(), cutoff, &mut removed, &mut freed) {
+            Ok(()) => Ok((removed, freed)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok((0, 0)),
+            Err(e) => Err(format!("清理回收站失败: {}", e)),
+        }
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, read_body_json, TestRequest};
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    #[actix_web::test]
+    async fn deleted_files_can_be_listed_and_restored() {
+        let env = TestEnv::with(|c| c.soft_delete = true);
+        let file = env.uploads().join("default/docs/a.txt");
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, "keep me").unwrap();
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let resp = call_service(&app, TestRequest::delete().uri("/api/file/default/docs/a.txt").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert!(!file.exists());
+
+        let resp = call_service(&app, TestRequest::get().uri("/api/trash/default").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        let items = body["data"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["original_name"], "a.txt");
+        let name = items[0]["name"].as_str().unwrap().to_string();
+
+        let resp = call_service(
+            &app,
+            TestRequest::post().uri("/api/trash/default/restore").set_json(serde_json::json!({ "name": name })).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep me");
+        assert!(list_trash("default").await.unwrap().is_empty());
+
+        // 已恢复的条目不能再次恢复
+        let resp = call_service(
+            &app,
+            TestRequest::post().uri("/api/trash/default/restore").set_json(serde_json::json!({ "name": name })).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn purge_removes_only_expired_entries() {
+        let env = TestEnv::with(|c| c.soft_delete = true);
+        let trash = env.uploads().join(file_utils::TRASH_DIR).join("default");
+        std::fs::create_dir_all(trash.join("nested")).unwrap();
+        std::fs::write(trash.join("20000101000000000_old.txt"), "old").unwrap();
+        std::fs::write(trash.join("nested/20000101000000000_old2.txt"), "old2").unwrap();
+        let recent = format!("{}_new.txt", Utc::now().format(TRASH_TIME_FORMAT));
+        std::fs::write(trash.join(&recent), "new").unwrap();
+
+        let (removed, freed) = purge_expired_trash(Duration::from_secs(3600)).await.unwrap();
+        assert_eq!((removed, freed), (2, 7));
+        assert!(trash.join(&recent).exists());
+        assert!(!trash.join("nested").exists());
+    }
+}
@@ -0,0 +1,348 @@
+// tus 1.0.0 可续传上传（核心协议 + creation 扩展）：数据追加写入临时目录，达到 Upload-Length 后按普通上传的流程写入模块目录
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use futures_util::{Stream, TryStreamExt};
+use tokio::fs as tokio_fs;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use actix_web::web;
+use uuid::Uuid;
+
+use crate::config;
+use crate::models::{FileInfo, TusUpload, UploadContext};
+use crate::services::{audit_service, system_service, scan_service, upload_service, webhook_service};
+use crate::utils::{file_utils, lock_utils, mime_utils, rate_limit, validation_utils};
+
+pub const TUS_VERSION: &str = "1.0.0";
+pub const TUS_EXTENSIONS: &str = "creation";
+// 临时目录下保存 tus 会话的子目录
+const TUS_DIR: &str = ".tus";
+
+// 处理器据以下错误返回对应的状态码
+pub const UPLOAD_NOT_FOUND_ERROR: &str = "上传会话不存在或已过期";
+pub const OFFSET_MISMATCH_ERROR: &str = "Upload-Offset 与已接收的长度不一致";
+pub const LENGTH_EXCEEDED_ERROR: &str = "上传数据超过 Upload-Length";
+pub const FILE_TOO_LARGE_ERROR: &str = "Upload-Length 超过文件大小限制";
+pub const CONTENT_TYPE_MISMATCH_ERROR: &str = "文件内容与扩展名不符";
+// 与普通上传一致，空文件不保存
+pub const EMPTY_UPLOAD_ERROR: &str = "Upload-Length 不能为 0";
+// 读写会话文件失败时的错误前缀（其余创建错误均为请求参数问题）
+pub const STORAGE_ERROR: &str = "tus 会话存储失败";
+
+// Upload-Metadata 中决定存储位置的键，其余键作为文件元数据保存
+const RESERVED_METADATA_KEYS: [&str; 5] = ["filename", "name", "module", "submodule", "relative_path"];
+
+fn tus_dir() -> PathBuf {
+    Path::new(config::temp_root()).join(TUS_DIR)
+}
+
+fn data_path(id: &str) -> PathBuf {
+    tus_dir().join(format!("{}.tus", id))
+}
+
+fn info_path(id: &str) -> PathBuf {
+    tus_dir().join(format!("{}.tus.json", id))
+}
+
+// 会话 ID 由服务端生成（uuid 的十六进制形式），拒绝其他字符以免拼接出目录外的路径
+fn is_valid_upload_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// 标准 base64 解码（Upload-Metadata 的值使用带填充的标准字母表）
+fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+            b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    };
+    let input = input.trim_end_matches('=').as_bytes();
+    if input.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    for chunk in input.chunks(4) {
+        let mut acc = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            acc |= value(c)? << (18 - 6 * i);
+        }
+        let bytes = acc.to_be_bytes();
+        out.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+    Some(out)
+}
+
+// 解析 Upload-Metadata: 逗号分隔的 "key base64(value)"，值可省略
+pub fn parse_metadata(header: &str) -> Result<HashMap<String, String>, String> {
+    let mut metadata = HashMap::new();
+    for pair in header.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let mut parts = pair.split_whitespace();
+        let key = parts.next().unwrap_or_default();
+        let value = match parts.next() {
+            Some(encoded) => base64_decode(encoded)
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or_else(|| format!("Upload-Metadata 中 {} 的值不是有效的 base64", key))?,
+            None => String::new(),
+        };
+        if parts.next().is_some() {
+            return Err(format!("Upload-Metadata 格式错误: {}", pair));
+        }
+        metadata.insert(key.to_string(), value);
+    }
+    Ok(metadata)
+}
+
+async fn load_upload(id: &str) -> Result<TusUpload, String> {
+    if !is_valid_upload_id(id) {
+        return Err(UPLOAD_NOT_FOUND_ERROR.to_string());
+    }
+    let content = match tokio_fs::read(info_path(id)).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(UPLOAD_NOT_FOUND_ERROR.to_string()),
+        Err(e) => return Err(format!("{}: {}", STORAGE_ERROR, e)),
+    };
+    serde_json::from_slice(&content).map_err(|e| format!("{}: {}", STORAGE_ERROR, e))
+}
+
+async fn save_upload(upload: &TusUpload) -> Result<(), String> {
+    let content = serde_json::to_vec(upload).map_err(|e| format!("{}: {}", STORAGE_ERROR, e))?;
+    tokio_fs::write(info_path(&upload.id), content)
+        .await
+        .map_err(|e| format!("{}: {}", STORAGE_ERROR, e))
+}
+
+// 当前已接收的字节数（即下一次 PATCH 应携带的 Upload-Offset）
+async fn current_offset(id: &str) -> Result<u64, String> {
+    match tokio_fs::metadata(data_path(id)).await {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(UPLOAD_NOT_FOUND_ERROR.to_string()),
+        Err(e) => Err(format!("{}: {}", STORAGE_ERROR, e)),
+    }
+}
+
+// 创建上传会话。文件名取自 Upload-Metadata 的 filename（或 name），
+// 模块优先取 metadata 中的 module，其次为查询参数，默认为 default
+pub async fn create_upload(
+    length: u64,
+    raw_metadata: Option<&str>,
+    module_param: Option<&str>,
+) -> Result<TusUpload, String> {
+    let config = config::get_config();
+    if length > config.max_file_size {
+        return Err(FILE_TOO_LARGE_ERROR.to_string());
+    }
+    if length == 0 {
+        return Err(EMPTY_UPLOAD_ERROR.to_string());
+    }
+
+    let mut metadata = match raw_metadata {
+        Some(header) => parse_metadata(header)?,
+        None => HashMap::new(),
+    };
+
+    let filename = metadata
+        .get("filename")
+        .or_else(|| metadata.get("name"))
+        .and_then(|name| Path::new(name).file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| "Upload-Metadata 缺少 filename".to_string())?;
+    if !validation_utils::is_valid_filename(&filename) {
+        return Err("文件名包含非法字符".to_string());
+    }
+    let extension = Path::new(&filename)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if !file_utils::is_valid_file_extension(&extension, config) {
+        return Err(format!("不支持的文件类型: {}", extension));
+    }
+
+    let module = metadata
+        .get("module")
+        .map(String::as_str)
+        .or(module_param)
+        .unwrap_or(&config.default_module);
+    let submodule = metadata
+        .get("submodule")
+        .map(|s| s.trim_matches('/'))
+        .filter(|s| !s.is_empty());
+    let module = upload_service::resolve_target_module(module, submodule)?;
+    upload_service::check_module_exists(&module).await?;
+
+    let relative_path = metadata
+        .get("relative_path")
+        .map(|p| p.trim_matches('/').to_string())
+        .filter(|p| !p.is_empty());
+    if !upload_service::is_valid_relative_path(relative_path.as_deref()) {
+        return Err(upload_service::INVALID_RELATIVE_PATH_ERROR.to_string());
+    }
+
+    metadata.retain(|key, _| !RESERVED_METADATA_KEYS.contains(&key.as_str()));
+    let upload = TusUpload {
+        id: Uuid::new_v4().simple().to_string(),
+        module,
+        filename,
+        relative_path,
+        length,
+        raw_metadata: raw_metadata.map(str::to_string),
+        metadata,
+    };
+
+    tokio_fs::create_dir_all(tus_dir())
+        .await
+        .map_err(|e| format!("{}: {}", STORAGE_ERROR, e))?;
+    tokio_fs::File::create(data_path(&upload.id))
+        .await
+        .map_err(|e| format!("{}: {}", STORAGE_ERROR, e))?;
+    save_upload(&upload).await?;
+
+    log::info!("创建 tus 上传: {} -> {}/{} ({} bytes)", upload.id, upload.module, upload.filename, length);
+    Ok(upload)
+}
+
+// 查询上传会话及已接收的字节数
+pub async fn get_upload(id: &str) -> Result<(TusUpload, u64), String> {
+    let upload = load_upload(id).await?;
+    let offset = current_offset(id).await?;
+    Ok((upload, offset))
+}
+
+// 从 offset 处追加请求体，返回新的 offset；数据达到 Upload-Length 时完成上传并返回文件信息。
+// 请求体中途断开时已写入的部分保留，客户端可通过 HEAD 查询后继续
+pub async fn append_chunk<S, E>(
+    id: &str,
+    offset: u64,
+    body: &mut S,
+    context: &UploadContext,
+) -> Result<(u64, Option<FileInfo>), String>
+where
+    S: Stream<Item = Result<web::Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    let upload = load_upload(id).await?;
+
+    // 同一会话的 PATCH 串行执行，避免并发追加打乱 offset
+    let lock = lock_utils::get_file_lock(&format!("tus_{}", id)).await;
+    let _guard = lock.lock().await;
+
+    let start = current_offset(id).await?;
+    if offset != start {
+        return Err(OFFSET_MISMATCH_ERROR.to_string());
+    }
+
+    let file = tokio_fs::OpenOptions::new()
+        .append(true)
+        .open(data_path(id))
+        .await
+        .map_err(|e| format!("打开上传数据文件失败: {}", e))?;
+    let mut writer = BufWriter::with_capacity(config::get_config().write_buffer_size, file);
+    let mut throttle = rate_limit::Throttle::new(context.max_bps);
+
+    let mut written = 0u64;
+    let mut failure = None;
+    loop {
+        match body.try_next().await {
+            Ok(Some(chunk)) => {
+                if start + written + chunk.len() as u64 > upload.length {
+                    failure = Some(LENGTH_EXCEEDED_ERROR.to_string());
+                    break;
+                }
+                if let Err(e) = writer.write_all(&chunk).await {
+                    failure = Some(format!("写入上传数据失败: {}", e));
+                    break;
+                }
+                written += chunk.len() as u64;
+                throttle.pace(chunk.len()).await;
+            }
+            Ok(None) => break,
+            Err(e) => {
+                failure = Some(format!("读取请求体失败: {}", e));
+                break;
+            }
+        }
+    }
+
+    writer.flush().await.map_err(|e| format!("写入上传数据失败: {}", e))?;
+    // 每次追加都同步（always），或在数据写满、即将移入模块目录时同步（on_merge）
+    let policy = config::get_config().fsync_policy;
+    if policy.sync_chunks() || (policy.sync_files() && start + written == upload.length) {
+        writer.get_ref().sync_all().await.map_err(|e| format!("同步上传数据失败: {}", e))?;
+    }
+    // 刷新会话文件的修改时间，进行中的上传不会被临时文件清理删除
+    save_upload(&upload).await?;
+
+    if let Some(error) = failure {
+        return Err(error);
+    }
+
+    let offset = start + written;
+    if offset < upload.length {
+        return Ok((offset, None));
+    }
+    let file_info = finish_upload(&upload, context).await?;
+    Ok((offset, Some(file_info)))
+}
+
+// 校验内容类型后将数据文件移入模块目录，按配置的重名策略确定最终文件名
+async fn finish_upload(upload: &TusUpload, context: &UploadContext) -> Result<FileInfo, String> {
+    let data = data_path(&upload.id);
+    let audit_path = match &upload.relative_path {
+        Some(rel) => format!("{}/{}", rel, upload.filename),
+        None => upload.filename.clone(),
+    };
+    let client_ip = context.source_ip.as_deref().unwrap_or("unknown");
+    let actor = context.uploader.as_deref();
+
+    let result = move_into_module(upload, &data, context).await;
+    match &result {
+        Ok(file_info) => {
+            audit_service::record("upload", &upload.module, Some(&audit_path), Some(file_info.size), client_ip, actor, None);
+            webhook_service::notify_upload(file_info);
+        }
+        Err(e) => audit_service::record("upload", &upload.module, Some(&audit_path), None, client_ip, actor, Some(e)),
+    }
+    // 无论成功与否，会话都已结束（失败时数据无法再通过 PATCH 修正）
+    let _ = tokio_fs::remove_file(&data).await;
+    let _ = tokio_fs::remove_file(info_path(&upload.id)).await;
+    result
+}
+
+// 经由与普通上传相同的写入流程存入模块（内容类型校验、重名策略、压缩存储、图片处理、摘要与去重），
+// 写入新文件后再原子地替换，不会原地改写已有文件（其可能与内容存储中的副本共享 inode）
+async fn move_into_module(upload: &TusUpload, data: &Path, context: &UploadContext) -> Result<FileInfo, String> {
+    let config = config::get_config();
+    if system_service::file_count_limit_exceeded(1) {
+        return Err("已达到服务器文件数上限".to_string());
+    }
+
+    let stored = upload_service::store_received_file(
+        &upload.module,
+        &upload.filename,
+        &upload.relative_path,
+        config.on_conflict,
+        data,
+    )
+    .await
+    .map_err(|e| match e.as_error::<mime_utils::ContentTypeMismatch>() {
+        Some(mismatch) => format!("{}: {}", CONTENT_TYPE_MISMATCH_ERROR, mismatch),
+        None => e.to_string(),
+    })?;
+    let Some(mut file_info) = stored else {
+        return Err(EMPTY_UPLOAD_ERROR.to_string());
+    };
+
+    file_info.metadata = (!upload.metadata.is_empty()).then(|| upload.metadata.clone());
+    let provenance = config.store_upload_provenance.then(|| context.provenance(&upload.filename));
+    upload_service::write_upload_meta(&upload.module, &file_info, provenance);
+
+    let final_path = upload_service::stored_file_path(&upload.module, &file_info);
+    log::info!("tus 上传完成: {} -> {} ({} bytes)", upload.id, final_path, file_info.size);
+    scan_service::check_uploaded(&upload.module, Path::new(&final_path), context).await?;
+    Ok(file_info)
+}
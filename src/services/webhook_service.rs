@@ -0,0 +1,194 @@
+// 上传成功后向配置的 webhook 地址推送 FileInfo（后台发送，失败只记录日志，不影响上传响应）
+use std::sync::OnceLock;
+use std::time::Duration;
+use crate::config;
+use crate::models::FileInfo;
+use crate::utils::hash_utils;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+// 首次失败后的重试次数
+const WEBHOOK_RETRIES: usize = 1;
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+// 复用连接池的全局客户端
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .user_agent("image-upload-system")
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+// 校验 webhook 地址：仅允许带主机名的 http:// 或 https:// 地址（启动时调用）
+pub fn validate_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|e| format!("无效的 webhook 地址 {}: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("webhook 地址仅支持 http:// 或 https://: {}", url));
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err(format!("无效的 webhook 地址: {}", url));
+    }
+    Ok(())
+}
+
+async fn post_once(url: &str, body: &[u8], signature: Option<&str>) -> Result<u16, String> {
+    let mut request = client()
+        .post(url.trim())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_vec());
+    if let Some(signature) = signature {
+        request = request.header("X-Upload-Signature", format!("sha256={}", signature));
+    }
+    let response = request.send().await.map_err(|e| {
+        if e.is_timeout() {
+            "请求超时".to_string()
+        } else {
+            format!("发送失败: {}", e)
+        }
+    })?;
+    Ok(response.status().as_u16())
+}
+
+async fn deliver(url: &str, body: Vec<u8>, signature: Option<String>) {
+    if let Err(e) = validate_url(url) {
+        log::warn!("webhook 通知失败: {}", e);
+        return;
+    }
+
+    for attempt in 0..=WEBHOOK_RETRIES {
+        if attempt > 0 {
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+        match post_once(url, &body, signature.as_deref()).await {
+            Ok(status) if (200..300).contains(&status) => {
+                log::debug!("webhook 通知成功: {} ({})", url, status);
+                return;
+            }
+            Ok(status) => log::warn!("webhook 通知失败: {} 返回 {} (第 {} 次)", url, status, attempt + 1),
+            Err(e) => log::warn!("webhook 通知失败: {} {} (第 {} 次)", url, e, attempt + 1),
+        }
+    }
+}
+
+// 配置了 webhook_url 时在后台推送上传结果
+pub fn notify_upload(file_info: &FileInfo) {
+    let config = config::get_config();
+    let Some(url) = config.webhook_url.clone() else {
+        return;
+    };
+    let body = match serde_json::to_vec(file_info) {
+        Ok(body) => body,
+        Err(e) => {
+            log::warn!("序列化 webhook 请求体失败: {}", e);
+            return;
+        }
+    };
+    let signature = config
+        .webhook_secret
+        .as_ref()
+        .map(|secret| hash_utils::hmac_sha256_hex(secret.as_bytes(), &body));
+    tokio::spawn(async move { deliver(&url, body, signature).await });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::call_service;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    // 模拟 webhook 接收方: 第一次请求返回 500，之后返回 200；每个请求的 (请求头, 请求体) 通过通道发出
+    async fn mock_receiver() -> (String, mpsc::UnboundedReceiver<(String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks/upload", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for attempt in 0.. {
+                let Ok((mut stream, _)) = listener.accept().await else { return };
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                let header_end = loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    assert!(n > 0, "connection closed before headers");
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(i) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break i + 4;
+                    }
+                };
+                let headers = String::from_utf8_lossy(&request[..header_end]).to_string();
+                let length: usize = header(&headers, "content-length").and_then(|v| v.parse().ok()).unwrap();
+                while request.len() < header_end + length {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let status = if attempt == 0 { "500 Internal Server Error" } else { "200 OK" };
+                let _ = stream.write_all(format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).as_bytes()).await;
+                let _ = tx.send((headers, request[header_end..].to_vec()));
+            }
+        });
+        (url, rx)
+    }
+
+    // 按名称（不区分大小写）取请求头的值
+    fn header<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+        headers
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim())
+    }
+
+    #[test]
+    fn webhook_urls_are_validated() {
+        assert!(validate_url("http://indexer.local/hooks").is_ok());
+        assert!(validate_url("https://indexer.example.com:8443/hooks?x=1").is_ok());
+        assert!(validate_url("http://[::1]:8080/hooks").is_ok());
+        assert!(validate_url("ftp://indexer.local/hooks").is_err());
+        assert!(validate_url("indexer.local/hooks").is_err());
+        assert!(validate_url("http://").is_err());
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        assert_eq!(
+            hash_utils::hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[actix_web::test]
+    async fn successful_uploads_are_posted_to_the_webhook() {
+        let (url, mut received) = mock_receiver().await;
+        let _env = TestEnv::with(|c| {
+            c.webhook_url = Some(url);
+            c.webhook_secret = Some("s3cret".to_string());
+        });
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+
+        let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "hooked.txt", b"hello").to_request()).await;
+        assert_eq!(resp.status(), 200);
+
+        // 第一次返回 500 后重试一次
+        for _ in 0..2 {
+            let (headers, body) = tokio::time::timeout(Duration::from_secs(5), received.recv())
+                .await
+                .expect("webhook should be delivered")
+                .unwrap();
+            assert!(headers.starts_with("POST /hooks/upload HTTP/1.1\r\n"), "{}", headers);
+            let signature = format!("sha256={}", hash_utils::hmac_sha256_hex(b"s3cret", &body));
+            assert_eq!(header(&headers, "x-upload-signature"), Some(signature.as_str()), "{}", headers);
+            let file_info: FileInfo = serde_json::from_slice(&body).unwrap();
+            assert_eq!(file_info.filename, "hooked.txt");
+            assert_eq!(file_info.module, "default");
+            assert_eq!(file_info.size, 5);
+        }
+        assert!(tokio::time::timeout(Duration::from_millis(1500), received.recv()).await.is_err());
+    }
+}
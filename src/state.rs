@@ -1,11 +1,175 @@
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::sync::Semaphore;
 
 // 全局统计
 pub static TOTAL_UPLOADED: AtomicU64 = AtomicU64::new(0);
 pub static ACTIVE_UPLOADS: AtomicU64 = AtomicU64::new(0);
 pub static SERVER_START_TIME: AtomicU64 = AtomicU64::new(0);
+pub static ACTIVE_SCANS: AtomicU64 = AtomicU64::new(0);
+// 缓存的上传文件总数（启动与统计时全量刷新，上传/删除时增量维护）
+pub static TOTAL_FILES: AtomicU64 = AtomicU64::new(0);
+
+// 上传大小分布的桶边界（字节）
+const UPLOAD_SIZE_BUCKETS: &[f64] = &[
+    1024.0, 10240.0, 102400.0, 1048576.0, 10485760.0, 104857600.0, 1073741824.0, 10737418240.0,
+];
+// 合并耗时分布的桶边界（秒）
+const MERGE_DURATION_BUCKETS: &[f64] = &[0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+// 固定桶直方图，使用原子计数避免加锁
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    // f64 总和以位模式存储
+    sum_bits: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: bounds.iter().map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_bits: AtomicU64::new(0f64.to_bits()),
+        }
+    }
+
+    pub fn observe(&self, value: f64) {
+        if let Some(index) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let _ = self.sum_bits.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some((f64::from_bits(bits) + value).to_bits())
+        });
+    }
+
+    // 以 Prometheus 文本格式输出（桶计数为累计值）
+    pub fn render(&self, name: &str, help: &str) -> String {
+        let mut out = format!("# HELP {} {}\n# TYPE {} histogram\n", name, help, name);
+        let mut cumulative = 0u64;
+        for (bound, bucket) in self.bounds.iter().zip(self.buckets.iter()) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            out.push_str(&format!("{}_bucket{{le=\"{}\"}} {}\n", name, bound, cumulative));
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        out.push_str(&format!("{}_bucket{{le=\"+Inf\"}} {}\n", name, count));
+        out.push_str(&format!("{}_sum {}\n", name, f64::from_bits(self.sum_bits.load(Ordering::Relaxed))));
+        out.push_str(&format!("{}_count {}\n", name, count));
+        out
+    }
+}
+
+// 吞吐历史中的一个时间槽
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct ThroughputSlot {
+    // 槽对应的时间段序号（unix 秒 / 分辨率）
+    period: u64,
+    bytes: u64,
+    requests: u64,
+}
+
+// 持久化格式
+#[derive(Debug, Serialize, Deserialize)]
+struct ThroughputSnapshot {
+    resolution_secs: u64,
+    slots: Vec<ThroughputSlot>,
+}
+
+// 固定大小的吞吐历史环形缓冲区，每个槽覆盖 resolution_secs 秒
+#[derive(Debug)]
+pub struct ThroughputHistory {
+    resolution_secs: u64,
+    slots: Mutex<Vec<ThroughputSlot>>,
+}
+
+impl ThroughputHistory {
+    pub fn new(resolution_secs: u64, window: usize) -> Self {
+        Self {
+            resolution_secs: resolution_secs.max(1),
+            slots: Mutex::new(vec![ThroughputSlot::default(); window.max(1)]),
+        }
+    }
+
+    fn current_period(&self) -> u64 {
+        chrono::Utc::now().timestamp().max(0) as u64 / self.resolution_secs
+    }
+
+    pub fn record(&self, bytes: u64) {
+        let period = self.current_period();
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        let len = slots.len() as u64;
+        let slot = &mut slots[(period % len) as usize];
+        if slot.period != period {
+            *slot = ThroughputSlot { period, bytes: 0, requests: 0 };
+        }
+        slot.bytes += bytes;
+        slot.requests += 1;
+    }
+
+    // 按时间顺序返回整个窗口的序列，没有数据的时间段补零
+    pub fn series(&self) -> serde_json::Value {
+        let current = self.current_period();
+        let slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        let len = slots.len() as u64;
+        let points: Vec<serde_json::Value> = (current.saturating_sub(len - 1)..=current)
+            .map(|period| {
+                let slot = slots[(period % len) as usize];
+                let (bytes, requests) = if slot.period == period {
+                    (slot.bytes, slot.requests)
+                } else {
+                    (0, 0)
+                };
+                serde_json::json!({
+                    "timestamp": period * self.resolution_secs,
+                    "bytes": bytes,
+                    "requests": requests,
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "resolution_secs": self.resolution_secs,
+            "window": len,
+            "points": points,
+        })
+    }
+
+    pub fn save_to(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let snapshot = ThroughputSnapshot {
+            resolution_secs: self.resolution_secs,
+            slots: self.slots.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        };
+        let content = serde_json::to_vec(&snapshot).map_err(std::io::Error::other)?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    // 载入持久化的历史；分辨率不一致时丢弃旧数据
+    pub fn load_from(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let content = std::fs::read(path)?;
+        let snapshot: ThroughputSnapshot = serde_json::from_slice(&content)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        if snapshot.resolution_secs != self.resolution_secs {
+            log::warn!("吞吐历史分辨率已变更（{}s -> {}s），忽略已保存的数据", snapshot.resolution_secs, self.resolution_secs);
+            return Ok(());
+        }
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        let len = slots.len() as u64;
+        for slot in snapshot.slots.into_iter().filter(|s| s.period > 0) {
+            let target = &mut slots[(slot.period % len) as usize];
+            if slot.period > target.period {
+                *target = slot;
+            }
+        }
+        Ok(())
+    }
+}
 
 // 应用状态管理
 #[derive(Debug, Clone)]
@@ -13,14 +177,27 @@ pub struct AppState {
     pub global_semaphore: Arc<Semaphore>,
     pub request_count: Arc<AtomicU64>,
     pub error_count: Arc<AtomicU64>,
+    pub upload_size_histogram: Arc<Histogram>,
+    pub merge_duration_histogram: Arc<Histogram>,
+    pub throughput_history: Arc<ThroughputHistory>,
+    // 只读维护模式：开启时拒绝所有写操作，读取与下载照常
+    pub read_only: Arc<AtomicBool>,
 }
 
 impl AppState {
     pub fn new(max_concurrent: usize) -> Self {
+        let config = crate::config::get_config();
         Self {
             global_semaphore: Arc::new(Semaphore::new(max_concurrent)),
             request_count: Arc::new(AtomicU64::new(0)),
             error_count: Arc::new(AtomicU64::new(0)),
+            upload_size_histogram: Arc::new(Histogram::new(UPLOAD_SIZE_BUCKETS)),
+            merge_duration_histogram: Arc::new(Histogram::new(MERGE_DURATION_BUCKETS)),
+            throughput_history: Arc::new(ThroughputHistory::new(
+                config.stats_history_resolution.as_secs(),
+                config.stats_history_window,
+            )),
+            read_only: Arc::new(AtomicBool::new(false)),
         }
     }
     
@@ -32,6 +209,47 @@ impl AppState {
         self.error_count.fetch_add(1, Ordering::Relaxed);
     }
     
+    pub fn record_upload_size(&self, size: u64) {
+        self.upload_size_histogram.observe(size as f64);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    pub fn set_read_only(&self, enabled: bool) {
+        self.read_only.store(enabled, Ordering::Relaxed);
+    }
+
+    // 记录一次上传请求及其写入的字节数（用于吞吐历史）
+    pub fn record_upload_activity(&self, bytes: u64) {
+        self.throughput_history.record(bytes);
+    }
+
+    pub fn record_merge_duration(&self, seconds: f64) {
+        self.merge_duration_histogram.observe(seconds);
+    }
+
+    // Prometheus 文本格式的指标
+    pub fn render_metrics(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP upload_requests_total Total number of API requests\n");
+        out.push_str("# TYPE upload_requests_total counter\n");
+        out.push_str(&format!("upload_requests_total {}\n", self.request_count.load(Ordering::Relaxed)));
+        out.push_str("# HELP upload_errors_total Total number of failed requests\n");
+        out.push_str("# TYPE upload_errors_total counter\n");
+        out.push_str(&format!("upload_errors_total {}\n", self.error_count.load(Ordering::Relaxed)));
+        out.push_str("# HELP upload_bytes_total Total bytes received by upload endpoints\n");
+        out.push_str("# TYPE upload_bytes_total counter\n");
+        out.push_str(&format!("upload_bytes_total {}\n", TOTAL_UPLOADED.load(Ordering::Relaxed)));
+        out.push_str("# HELP upload_active Number of uploads currently in progress\n");
+        out.push_str("# TYPE upload_active gauge\n");
+        out.push_str(&format!("upload_active {}\n", ACTIVE_UPLOADS.load(Ordering::Relaxed)));
+        out.push_str(&self.upload_size_histogram.render("upload_size_bytes", "Size of uploaded files in bytes"));
+        out.push_str(&self.merge_duration_histogram.render("merge_duration_seconds", "Duration of chunk merges in seconds"));
+        out
+    }
+    
     pub fn get_stats(&self) -> serde_json::Value {
         serde_json::json!({
             "total_requests": self.request_count.load(Ordering::Relaxed),
@@ -39,6 +257,8 @@ impl AppState {
             "available_permits": self.global_semaphore.available_permits(),
             "active_uploads": ACTIVE_UPLOADS.load(Ordering::Relaxed),
             "total_uploaded": TOTAL_UPLOADED.load(Ordering::Relaxed),
+            "active_scans": ACTIVE_SCANS.load(Ordering::Relaxed),
+            "max_scan_concurrency": crate::config::get_config().max_scan_concurrency,
         })
     }
 }
\ No newline at end of file
@@ -0,0 +1,131 @@
+// 测试辅助：每个测试使用独立的存储目录与配置，依赖全局配置的测试串行执行
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use actix_web::test::TestRequest;
+use crate::config::{self, ServerConfig};
+
+static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct TestEnv {
+    pub root: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestEnv {
+    // 在默认配置的基础上按需修改
+    pub fn with(configure: impl FnOnce(&mut ServerConfig)) -> Self {
+        let lock = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let root = std::env::temp_dir().join(format!("upload-test-{}", uuid::Uuid::new_v4()));
+        let mut config = ServerConfig {
+            uploads_root: root.join("uploads").to_string_lossy().to_string(),
+            temp_root: root.join("temp").to_string_lossy().to_string(),
+            audit_log_path: None,
+            ..ServerConfig::default()
+        };
+        configure(&mut config);
+        std::fs::create_dir_all(&config.uploads_root).expect("create uploads root");
+        std::fs::create_dir_all(&config.temp_root).expect("create temp root");
+        std::fs::create_dir_all(format!("{}/{}", config.uploads_root, config.default_module)).expect("create default module");
+        config::set_test_config(Some(config));
+        Self { root, _lock: lock }
+    }
+
+    pub fn uploads(&self) -> PathBuf {
+        PathBuf::from(config::uploads_root())
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        config::set_test_config(None);
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+// 构造 multipart/form-data 请求体，返回 (Content-Type, 请求体)；parts 为 (字段名, 文件名, 内容)
+pub fn multipart(parts: &[(&str, Option<&str>, &[u8])]) -> (String, Vec<u8>) {
+    let boundary = "----upload-test-boundary";
+    let mut body = Vec::new();
+    for (name, filename, content) in parts {
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        match filename {
+            Some(filename) => body.extend_from_slice(
+                format!(
+                    "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                    name, filename
+                )
+                .as_bytes(),
+            ),
+            None => body.extend_from_slice(format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name).as_bytes()),
+        }
+        body.extend_from_slice(content);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+// 以 multipart 上传单个文件（字段名 file）的请求
+pub fn upload_request(uri: &str, filename: &str, content: &[u8]) -> TestRequest {
+    let (content_type, body) = multipart(&[("file", Some(filename), content)]);
+    TestRequest::post().uri(uri).insert_header(("Content-Type", content_type)).set_payload(body)
+}
+
+// 生成 width x height 的 RGB 渐变 PNG
+pub fn png(width: u32, height: u32) -> Vec<u8> {
+    fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(kind);
+        crc.update(data);
+        out.extend_from_slice(&crc.finalize().to_be_bytes());
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 位深度、RGB、默认压缩/过滤/无隔行
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut raw = Vec::new();
+    for y in 0..height {
+        raw.push(0);
+        for x in 0..width {
+            raw.extend_from_slice(&[(x * 255 / width.max(1)) as u8, (y * 255 / height.max(1)) as u8, 128]);
+        }
+    }
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    std::io::Write::write_all(&mut encoder, &raw).expect("compress png");
+    let idat = encoder.finish().expect("compress png");
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &idat);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+// 与 main 中相同的中间件与路由，state 为 web::Data<AppState>
+macro_rules! test_app {
+    ($state:expr) => {
+        actix_web::test::init_service(
+            actix_web::App::new()
+                .app_data($state.clone())
+                .wrap(actix_web::middleware::from_fn(crate::middleware::read_only::read_only_guard))
+                .wrap(actix_web::middleware::from_fn(crate::middleware::origin_check::origin_check))
+                .wrap(actix_web::middleware::from_fn(crate::middleware::auth::jwt_auth))
+                .wrap(actix_web::middleware::from_fn(crate::middleware::cors::cors))
+                .wrap(actix_web::middleware::from_fn(crate::middleware::request_log::request_context))
+                .configure(crate::handlers::configure_routes),
+        )
+        .await
+    };
+}
+pub(crate) use test_app;
+
+// 新建应用状态
+pub fn app_state() -> actix_web::web::Data<crate::state::AppState> {
+    actix_web::web::Data::new(crate::state::AppState::new(config::get_config().global_max_concurrent))
+}
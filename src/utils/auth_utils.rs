@@ -0,0 +1,48 @@
+use actix_web::http::header::HeaderMap;
+use actix_web::HttpRequest;
+use crate::config;
+
+// 从 Authorization: Bearer 或 X-Admin-Token 头中提取令牌
+fn extract_token(req: &HttpRequest) -> Option<&str> {
+    let headers = req.headers();
+    if let Some(bearer) = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        return Some(bearer.trim());
+    }
+    headers.get("x-admin-token").and_then(|v| v.to_str().ok()).map(|v| v.trim())
+}
+
+// 常数时间比较，避免通过响应时间推测令牌
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// 检查请求是否携带有效的管理令牌（未配置令牌时一律拒绝）
+pub fn is_admin_request(req: &HttpRequest) -> bool {
+    match (&config::get_config().admin_token, extract_token(req)) {
+        (Some(expected), Some(token)) => constant_time_eq(expected.as_bytes(), token.as_bytes()),
+        _ => false,
+    }
+}
+
+// 检查请求是否携带有效的 API Key（管理令牌同样视为有效）
+pub fn has_valid_api_key(headers: &HeaderMap) -> bool {
+    let config = config::get_config();
+    let Some(key) = headers.get("x-api-key").and_then(|v| v.to_str().ok()).map(|v| v.trim()) else {
+        return false;
+    };
+
+    config.api_keys.iter().any(|expected| constant_time_eq(expected.as_bytes(), key.as_bytes()))
+        || config.admin_token.as_ref().is_some_and(|t| constant_time_eq(t.as_bytes(), key.as_bytes()))
+}
+
+// 请求是否携带任一有效凭据（管理令牌或 API Key）
+pub fn is_authenticated(req: &HttpRequest) -> bool {
+    is_admin_request(req) || has_valid_api_key(req.headers())
+}
@@ -1,26 +1,57 @@
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
 use chrono::{DateTime, Utc};
-use crate::models::{FileInfo, ModuleInfo};
+use crate::config::ServerConfig;
+use crate::models::{FileInfo, FileMeta, ModuleInfo, ModuleTreeNode};
+use crate::utils::image_utils;
 
-// 检查文件扩展名是否为有效的文件格式
-pub fn is_valid_file_extension(ext: &str) -> bool {
+// 文件系统不支持创建时间（如 ext4 上的旧内核）时只提示一次
+static BIRTH_TIME_UNAVAILABLE: std::sync::Once = std::sync::Once::new();
+
+// 文件时间戳: 优先使用创建时间，不支持时退回修改时间，都不可用时使用当前时间
+pub fn file_timestamp(metadata: &fs::Metadata) -> SystemTime {
+    timestamp_with_fallback(metadata.created(), || metadata.modified())
+}
+
+fn timestamp_with_fallback(
+    created: std::io::Result<SystemTime>,
+    modified: impl FnOnce() -> std::io::Result<SystemTime>,
+) -> SystemTime {
+    created
+        .or_else(|e| {
+            BIRTH_TIME_UNAVAILABLE.call_once(|| {
+                log::warn!("文件系统不支持创建时间，改用修改时间: {}", e);
+            });
+            modified()
+        })
+        .unwrap_or_else(|_| SystemTime::now())
+}
+
+// 由文件大小与修改时间生成强 ETag；JSON 详情与文件下载共用同一个值
+pub fn file_etag(metadata: &fs::Metadata) -> String {
+    let modified_nanos = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", metadata.len(), modified_nanos)
+}
+
+// 检查文件扩展名是否允许上传：须在允许列表中且不在拒绝列表中
+pub fn is_valid_file_extension(ext: &str, config: &ServerConfig) -> bool {
     let ext_lower = ext.to_lowercase();
-    matches!(
-        ext_lower.as_str(),
-        "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "svg" | "ico" |
-        "zip" | "rar" | "7z" | "tar" | "gz" |
-        "pdf" | "doc" | "docx" | "txt" | "md" | "json" | "xml" | "csv" | "xls" | "xlsx" | "ppt" | "pptx" |
-        "mp4" | "avi" | "mov" | "wmv" | "flv" | "mkv" |
-        "mp3" | "wav" | "ogg" | "flac"
-    )
+    config.allowed_extensions.contains(&ext_lower) && !config.denied_extensions.contains(&ext_lower)
 }
 
 // 获取文件类型分类
 pub fn get_file_type(ext: &str) -> String {
     let ext_lower = ext.to_lowercase();
     match ext_lower.as_str() {
-        "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "svg" | "ico" => "image".to_string(),
+        "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "svg" | "ico" | "avif" => "image".to_string(),
         "zip" | "rar" | "7z" | "tar" | "gz" => "archive".to_string(),
         "pdf" | "doc" | "docx" | "txt" | "md" | "json" | "xml" | "csv" | "xls" | "xlsx" | "ppt" | "pptx" => "document".to_string(),
         "mp4" | "avi" | "mov" | "wmv" | "flv" | "mkv" => "video".to_string(),
@@ -29,85 +60,550 @@ pub fn get_file_type(ext: &str) -> String {
     }
 }
 
-// 递归统计文件数量和大小
-pub fn count_files_recursive(
+// 按扩展名确定下载响应的 Content-Type（浏览器据此决定内联预览还是下载），未知扩展名按二进制流处理
+pub fn mime_for_extension(ext: &str) -> &'static str {
+    match ext.to_lowercase().as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "zip" => "application/zip",
+        "rar" => "application/vnd.rar",
+        "7z" => "application/x-7z-compressed",
+        "tar" => "application/x-tar",
+        "gz" => "application/gzip",
+        "pdf" => "application/pdf",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "mp4" => "video/mp4",
+        "avi" => "video/x-msvideo",
+        "mov" => "video/quicktime",
+        "wmv" => "video/x-ms-wmv",
+        "flv" => "video/x-flv",
+        "mkv" => "video/x-matroska",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "flac" => "audio/flac",
+        _ => "application/octet-stream",
+    }
+}
+
+// 文件元数据 sidecar 命名: 同目录下的 ".{filename}.meta.json"（隐藏文件，不会被静态服务暴露）
+const SIDECAR_SUFFIX: &str = ".meta.json";
+
+pub fn sidecar_name(filename: &str) -> String {
+    format!(".{}{}", filename, SIDECAR_SUFFIX)
+}
+
+// 模块根目录下的校验清单缓存，与 sidecar 一样属于内部元数据
+pub const MANIFEST_FILE: &str = ".manifest.json";
+
+pub fn is_sidecar_file(name: &str) -> bool {
+    (name.starts_with('.') && name.ends_with(SIDECAR_SUFFIX)) || name == MANIFEST_FILE
+}
+
+pub fn sidecar_path(file_path: &Path) -> PathBuf {
+    let filename = file_path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    file_path.with_file_name(sidecar_name(&filename))
+}
+
+// 读取文件的 sidecar 元数据，不存在或无法解析时返回 None
+pub fn read_file_meta(file_path: &Path) -> Option<FileMeta> {
+    let content = fs::read(sidecar_path(file_path)).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+// 写入文件的 sidecar 元数据（先写临时文件再重命名，避免读到半截内容）
+pub fn write_file_meta(file_path: &Path, meta: &FileMeta) -> std::io::Result<()> {
+    let target = sidecar_path(file_path);
+    let tmp = target.with_file_name(format!(
+        "{}.tmp.{}",
+        target.file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+        uuid::Uuid::new_v4()
+    ));
+    let content = serde_json::to_vec(meta).map_err(std::io::Error::other)?;
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, &target).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+// fsync 文件所在目录，使新建/重命名的目录项在崩溃后仍然存在
+pub fn sync_parent_dir(file_path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if let Some(parent) = file_path.parent() {
+        let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+        fs::File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = file_path;
+    Ok(())
+}
+
+// 删除文件的 sidecar 元数据（不存在时忽略）
+pub fn remove_file_meta(file_path: &Path) {
+    let _ = fs::remove_file(sidecar_path(file_path));
+}
+
+// 去重模式下的内容寻址存储目录（位于 uploads 根目录下，不属于任何模块）
+pub const CONTENT_STORE_DIR: &str = ".cas";
+// 软删除的回收站目录
+pub const TRASH_DIR: &str = ".trash";
+// 未通过病毒扫描的文件的隔离目录
+pub const QUARANTINE_DIR: &str = ".quarantine";
+
+// uploads 根目录下的保留目录，不作为模块列出或统计
+pub fn is_reserved_dir(name: &str) -> bool {
+    name == CONTENT_STORE_DIR || name == TRASH_DIR || name == QUARANTINE_DIR
+}
+
+// 按 64KB 分块读取并计算文件摘要（阻塞调用）
+pub fn hash_file(path: &Path, algorithm: crate::config::HashAlgorithm) -> std::io::Result<String> {
+    use std::io::Read;
+    let mut file = fs::File::open(path)?;
+    let mut hasher = crate::utils::hash_utils::FileHasher::new(algorithm);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize_hex())
+}
+
+// 计算文件原始内容的摘要（阻塞调用），压缩存储的文件按解压后的内容计算，返回 (摘要, 原始大小)
+pub fn hash_original_content(
     path: &Path,
-    file_count: &mut usize,
-    total_size: &mut u64,
-) -> std::io::Result<()> {
+    algorithm: crate::config::HashAlgorithm,
+    compressed: bool,
+) -> std::io::Result<(String, u64)> {
+    use std::io::Read;
+    let file = fs::File::open(path)?;
+    let mut reader: Box<dyn Read> = if compressed {
+        Box::new(zstd::stream::read::Decoder::new(file)?)
+    } else {
+        Box::new(file)
+    };
+    let mut hasher = crate::utils::hash_utils::FileHasher::new(algorithm);
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        size += n as u64;
+        hasher.update(&buf[..n]);
+    }
+    Ok((hasher.finalize_hex(), size))
+}
+
+// 两个文件是否为同一份数据（硬链接）；非 unix 平台无法判断时视为不同
+#[cfg(unix)]
+pub fn same_inode(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+pub fn same_inode(_a: &fs::Metadata, _b: &fs::Metadata) -> bool {
+    false
+}
+
+// 压缩存储的文件后缀
+pub const COMPRESSED_SUFFIX: &str = ".zst";
+
+// 是否应压缩存储: 开启 compress_at_rest 且为文档类型（大小阈值在写入时判断）
+pub fn should_compress(ext: &str, config: &ServerConfig) -> bool {
+    config.compress_at_rest && get_file_type(ext) == "document"
+}
+
+// 目录遍历中止的原因: 嵌套超过深度限制、符号链接成环或 I/O 错误
+#[derive(Debug)]
+pub enum WalkError {
+    TooDeep(PathBuf),
+    Cycle(PathBuf),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for WalkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalkError::TooDeep(path) => write!(f, "目录嵌套过深: {}", path.display()),
+            WalkError::Cycle(path) => write!(f, "检测到目录环路: {}", path.display()),
+            WalkError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for WalkError {
+    fn from(e: std::io::Error) -> Self {
+        WalkError::Io(e)
+    }
+}
+
+// 标识一个目录: unix 下使用 (设备号, inode)，其他平台使用规范化路径
+#[cfg(unix)]
+fn directory_key(_path: &Path, metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    format!("{}:{}", metadata.dev(), metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn directory_key(path: &Path, _metadata: &fs::Metadata) -> String {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).to_string_lossy().to_string()
+}
+
+// 递归构建目录树节点：统计直接包含的文件数并展开子目录（不跟随符号链接），
+// depth 达到 max_depth 或 node_budget 耗尽时不再展开并标记 truncated
+pub fn build_module_tree(
+    path: &Path,
+    name: String,
+    depth: usize,
+    max_depth: usize,
+    node_budget: &AtomicUsize,
+) -> std::io::Result<ModuleTreeNode> {
+    let mut node = ModuleTreeNode { name, file_count: 0, truncated: false, children: Vec::new() };
+    let mut subdirs = Vec::new();
     for entry in fs::read_dir(path)? {
         let entry = entry?;
         let file_type = entry.file_type()?;
+        let entry_name = entry.file_name().to_string_lossy().to_string();
+        if file_type.is_dir() {
+            subdirs.push((entry.path(), entry_name));
+        } else if file_type.is_file() && !is_sidecar_file(&entry_name) {
+            node.file_count += 1;
+        }
+    }
 
-        if file_type.is_file() {
-            *file_count += 1;
-            if let Ok(metadata) = entry.metadata() {
-                *total_size += metadata.len();
+    if depth >= max_depth {
+        node.truncated = !subdirs.is_empty();
+        return Ok(node);
+    }
+    subdirs.sort_by(|a, b| a.1.cmp(&b.1));
+    for (subdir, subdir_name) in subdirs {
+        // 节点数预算由同一请求内的所有模块共享，耗尽后其余子目录不再展开
+        if node_budget.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1)).is_err() {
+            node.truncated = true;
+            break;
+        }
+        node.children.push(build_module_tree(&subdir, subdir_name, depth + 1, max_depth, node_budget)?);
+    }
+    Ok(node)
+}
+
+// 以显式栈迭代遍历目录树，对每个普通文件调用 visit（跳过 sidecar 与保留目录）。
+// 符号链接会被跟随，已访问过的目录再次出现时报告环路；超过 max_depth 层时报告嵌套过深。
+// 出错时立即停止，此前已回调的文件保持有效
+pub fn walk_files(
+    root: &Path,
+    max_depth: usize,
+    mut visit: impl FnMut(&Path, &str, &fs::Metadata),
+) -> Result<(), WalkError> {
+    let mut visited = HashSet::new();
+    visited.insert(directory_key(root, &fs::metadata(root)?));
+    let mut stack = vec![(root.to_path_buf(), 0usize)];
+
+    while let Some((dir, depth)) = stack.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let path = entry.path();
+            let metadata = if entry.file_type()?.is_symlink() {
+                // 失效的符号链接直接跳过
+                match fs::metadata(&path) {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                }
+            } else {
+                entry.metadata()?
+            };
+
+            if metadata.is_file() {
+                if !is_sidecar_file(&name) {
+                    visit(&path, &name, &metadata);
+                }
+            } else if metadata.is_dir() {
+                if is_reserved_dir(&name) {
+                    continue;
+                }
+                if depth + 1 > max_depth {
+                    return Err(WalkError::TooDeep(path));
+                }
+                if !visited.insert(directory_key(&path, &metadata)) {
+                    return Err(WalkError::Cycle(path));
+                }
+                stack.push((path, depth + 1));
             }
-        } else if file_type.is_dir() {
-            count_files_recursive(&entry.path(), file_count, total_size)?;
         }
     }
     Ok(())
 }
 
-// 递归收集文件信息
-pub fn collect_files_recursive(
+// 统计文件数量和大小（深度上限取自配置）
+pub fn count_files_recursive(
+    path: &Path,
+    file_count: &mut usize,
+    total_size: &mut u64,
+) -> Result<(), WalkError> {
+    walk_files(path, crate::config::get_config().max_walk_depth, |_, _, metadata| {
+        *file_count += 1;
+        *total_size += metadata.len();
+    })
+}
+
+// count_files_recursive 的异步版本，在阻塞线程池中遍历，返回 (文件数, 总大小)
+pub async fn count_files_async(path: PathBuf) -> Result<(usize, u64), String> {
+    tokio::task::spawn_blocking(move || {
+        let mut file_count = 0;
+        let mut total_size = 0;
+        count_files_recursive(&path, &mut file_count, &mut total_size)
+            .map(|_| (file_count, total_size))
+            .map_err(|e| format!("统计目录失败: {}", e))
+    })
+    .await
+    .map_err(|e| format!("阻塞任务失败: {}", e))?
+}
+
+// 按文件类型统计数量和占用字节数；压缩存储的文件按原始扩展名分类
+pub fn collect_type_stats(
+    path: &Path,
+    stats: &mut HashMap<String, (usize, u64)>,
+) -> Result<(), WalkError> {
+    walk_files(path, crate::config::get_config().max_walk_depth, |file_path, name, metadata| {
+        let type_name = match name.strip_suffix(COMPRESSED_SUFFIX) {
+            Some(original) if read_file_meta(file_path).is_some_and(|m| m.original_size.is_some()) => original,
+            _ => name,
+        };
+        let ext = Path::new(type_name).extension().and_then(|s| s.to_str()).unwrap_or("");
+        let slot = stats.entry(get_file_type(ext)).or_insert((0, 0));
+        slot.0 += 1;
+        slot.1 += metadata.len();
+    })
+}
+
+// 根据磁盘上的文件构建 FileInfo
+pub fn build_file_info(
+    path: &Path,
+    metadata: &fs::Metadata,
+    module: &str,
+    relative_path: Option<String>,
+    file_meta: Option<&FileMeta>,
+) -> FileInfo {
+    let filename = path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let upload_time: DateTime<Utc> = file_timestamp(metadata).into();
+
+    // 压缩存储的文件按原始扩展名分类，并指向会透明解压的下载接口
+    let original_size = file_meta.and_then(|meta| meta.original_size);
+    let type_path = match original_size {
+        Some(_) => Path::new(filename.strip_suffix(COMPRESSED_SUFFIX).unwrap_or(&filename)),
+        None => path,
+    };
+    let file_extension = type_path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let url_prefix = if original_size.is_some() { "/api/download" } else { "/uploads" };
+    let url = if let Some(rel_path) = &relative_path {
+        format!("{}/{}/{}/{}", url_prefix, module, rel_path, filename)
+    } else {
+        format!("{}/{}/{}", url_prefix, module, filename)
+    };
+
+    let file_type = get_file_type(&file_extension);
+    let dimensions = if original_size.is_none() { read_image_dimensions(path, &file_type) } else { None };
+
+    FileInfo {
+        filename,
+        url,
+        module: module.to_string(),
+        upload_time: upload_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        size: original_size.unwrap_or(metadata.len()),
+        file_type,
+        relative_path,
+        file_hash: file_meta.and_then(|meta| meta.file_hash.clone()),
+        compressed: original_size.is_some(),
+        width: dimensions.map(|(w, _)| w),
+        height: dimensions.map(|(_, h)| h),
+        metadata: file_meta.and_then(|meta| meta.metadata.clone()),
+    }
+}
+
+// 图片类文件读取文件头中的宽高，其他类型不打开文件
+pub fn read_image_dimensions(path: &Path, file_type: &str) -> Option<(u32, u32)> {
+    if file_type != "image" {
+        return None;
+    }
+    image_utils::image_dimensions(path)
+}
+
+// 文件列表过滤条件，在遍历时对每个文件求值，不匹配的文件不会构建 FileInfo 或读取 sidecar
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    // 文件名包含的子串（小写，不区分大小写匹配）
+    pub name_contains: Option<String>,
+    // 允许的文件类型，可以是分类（image、document ...）或扩展名
+    pub file_types: Option<HashSet<String>>,
+    pub modified_after: Option<SystemTime>,
+    pub modified_before: Option<SystemTime>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+impl FileFilter {
+    // 从查询参数构建过滤条件: name, type（逗号分隔）, modified_after / modified_before（RFC3339）,
+    // min_size / max_size（字节）
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self, String> {
+        let param = |key: &str| query.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+        let time = |key: &str| -> Result<Option<SystemTime>, String> {
+            param(key)
+                .map(|raw| {
+                    DateTime::parse_from_rfc3339(raw)
+                        .map(SystemTime::from)
+                        .map_err(|e| format!("{} 参数格式错误，需为 RFC3339 时间: {}", key, e))
+                })
+                .transpose()
+        };
+        let size = |key: &str| -> Result<Option<u64>, String> {
+            param(key)
+                .map(|raw| raw.parse::<u64>().map_err(|_| format!("{} 参数需为非负整数: {}", key, raw)))
+                .transpose()
+        };
+
+        let file_types = param("type").map(|raw| {
+            raw.split(',')
+                .map(|t| t.trim().to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect::<HashSet<_>>()
+        }).filter(|types| !types.is_empty());
+
+        let filter = Self {
+            name_contains: param("name").map(|n| n.to_lowercase()),
+            file_types,
+            modified_after: time("modified_after")?,
+            modified_before: time("modified_before")?,
+            min_size: size("min_size")?,
+            max_size: size("max_size")?,
+        };
+
+        if let (Some(min), Some(max)) = (filter.min_size, filter.max_size) {
+            if min > max {
+                return Err("min_size 不能大于 max_size".to_string());
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, filename: &str, metadata: &fs::Metadata) -> bool {
+        if let Some(needle) = &self.name_contains {
+            if !filename.to_lowercase().contains(needle.as_str()) {
+                return false;
+            }
+        }
+        if let Some(types) = &self.file_types {
+            let ext = Path::new(filename)
+                .extension()
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            if !types.contains(&ext) && !types.contains(&get_file_type(&ext)) {
+                return false;
+            }
+        }
+        let size = metadata.len();
+        if self.min_size.is_some_and(|min| size < min) || self.max_size.is_some_and(|max| size > max) {
+            return false;
+        }
+        if self.modified_after.is_some() || self.modified_before.is_some() {
+            let Ok(modified) = metadata.modified() else {
+                return false;
+            };
+            if self.modified_after.is_some_and(|after| modified <= after)
+                || self.modified_before.is_some_and(|before| modified >= before)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// 读取单层目录：返回该层匹配过滤条件的文件信息以及子目录的相对路径
+pub fn read_directory_level(
     base_path: &Path,
     current_path: &str,
-    files: &mut Vec<FileInfo>,
     module: &str,
-) -> std::io::Result<()> {
+    filter: &FileFilter,
+) -> std::io::Result<(Vec<FileInfo>, Vec<String>)> {
     let full_path = if current_path.is_empty() {
         base_path.to_path_buf()
     } else {
         base_path.join(current_path)
     };
 
-    for entry in fs::read_dir(full_path)? {
-        let entry = entry?;
+    let mut files = Vec::new();
+    let mut subdirs = Vec::new();
+
+    let entries = fs::read_dir(full_path)?.collect::<std::io::Result<Vec<_>>>()?;
+    // 先收集本层的元数据文件，只为存在元数据的文件读取 sidecar
+    let sidecars: HashSet<String> = entries
+        .iter()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| is_sidecar_file(name))
+        .collect();
+
+    for entry in entries {
         let file_type = entry.file_type()?;
 
         if file_type.is_file() {
             let path = entry.path();
             if let Some(file_name) = path.file_name() {
                 let filename = file_name.to_string_lossy().to_string();
+                if is_sidecar_file(&filename) {
+                    continue;
+                }
 
                 let metadata = entry.metadata()?;
-                let size = metadata.len();
-                let created = metadata
-                    .created()
-                    .unwrap_or(std::time::SystemTime::UNIX_EPOCH);
-                let upload_time: DateTime<Utc> = created.into();
-
-                let file_extension = path
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("")
-                    .to_lowercase();
-
+                if !filter.matches(&filename, &metadata) {
+                    continue;
+                }
                 let relative_path = if current_path.is_empty() {
                     None
                 } else {
                     Some(current_path.to_string())
                 };
 
-                let url = if let Some(rel_path) = &relative_path {
-                    format!("/uploads/{}/{}/{}", module, rel_path, filename)
+                let file_meta = if sidecars.contains(&sidecar_name(&filename)) {
+                    read_file_meta(&path)
                 } else {
-                    format!("/uploads/{}/{}", module, filename)
+                    None
                 };
 
-                let file_info = FileInfo {
-                    filename: filename.clone(),
-                    url,
-                    module: module.to_string(),
-                    upload_time: upload_time.format("%Y-%m-%d %H:%M:%S").to_string(),
-                    size,
-                    file_type: get_file_type(&file_extension),
-                    relative_path,
-                    file_hash: None,
-                };
+                let file_info = build_file_info(&path, &metadata, module, relative_path, file_meta.as_ref());
                 files.push(file_info);
             }
         } else if file_type.is_dir() {
@@ -117,17 +613,29 @@ pub fn collect_files_recursive(
             } else {
                 format!("{}/{}", current_path, dir_name)
             };
-            collect_files_recursive(base_path, &new_path, files, module)?;
+            subdirs.push(new_path);
         }
     }
-    Ok(())
+    Ok((files, subdirs))
 }
 
-// 生成唯一的文件名
+// 生成唯一的文件名（仅检查当前是否存在；需要与并发写入互斥时应配合 create_new 使用 numbered_path）
 pub fn generate_unique_filename(original_filename: &str, filepath: &str) -> String {
+    let mut counter = 0;
+    loop {
+        let candidate = numbered_path(original_filename, filepath, counter);
+        if !candidate.exists() {
+            return candidate.to_string_lossy().to_string();
+        }
+        counter += 1;
+    }
+}
+
+// 重名时的候选路径：counter 为 0 时即原路径，否则为同目录下的 {stem}_{counter}.{ext}
+pub fn numbered_path(original_filename: &str, filepath: &str, counter: usize) -> PathBuf {
     let path = Path::new(filepath);
-    if !path.exists() {
-        return filepath.to_string();
+    if counter == 0 {
+        return path.to_path_buf();
     }
 
     let file_extension = Path::new(original_filename)
@@ -142,49 +650,141 @@ pub fn generate_unique_filename(original_filename: &str, filepath: &str) -> Stri
         .unwrap_or("file");
 
     let parent = path.parent().unwrap_or(Path::new("."));
-    
-    let mut counter = 1;
+    let new_name = if file_extension.is_empty() {
+        format!("{}_{}", stem, counter)
+    } else {
+        format!("{}_{}.{}", stem, counter, file_extension)
+    };
+    parent.join(new_name)
+}
+
+// 判断 path（可以尚未存在）是否位于 root 之内：对已存在的最深一级祖先取规范路径后比较，
+// 中间目录是指向 root 之外的符号链接时返回 false；悬空链接等无法解析的情况返回错误
+pub fn is_within_root(root: &Path, path: &Path) -> std::io::Result<bool> {
+    let root = fs::canonicalize(root)?;
+    let mut existing = path;
     loop {
-        let new_name = if file_extension.is_empty() {
-            format!("{}_{}", stem, counter)
-        } else {
-            format!("{}_{}.{}", stem, counter, file_extension)
-        };
-        
-        let new_path = parent.join(&new_name);
-        
-        if !new_path.exists() {
-            return new_path.to_string_lossy().to_string();
+        match fs::symlink_metadata(existing) {
+            Ok(_) => break,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => match existing.parent() {
+                Some(parent) => existing = parent,
+                None => return Ok(false),
+            },
+            Err(e) => return Err(e),
         }
-        counter += 1;
     }
+    Ok(fs::canonicalize(existing)?.starts_with(&root))
 }
 
 // 获取模块信息
-pub fn get_module_info(entry: &fs::DirEntry) -> std::io::Result<ModuleInfo> {
-    let name = entry.file_name().to_string_lossy().to_string();
-    let module_path = entry.path();
+// 按模块名与目录路径统计模块信息
+pub fn module_info_at(name: String, module_path: &Path) -> ModuleInfo {
+    let (file_count, total_size) = module_file_stats(&name, module_path);
+    ModuleInfo {
+        created_time: module_created_time(module_path),
+        name,
+        file_count: Some(file_count),
+        total_size: Some(total_size),
+    }
+}
+
+// 递归统计模块内的文件数与总大小（模块较大时开销明显）
+pub fn module_file_stats(name: &str, module_path: &Path) -> (usize, u64) {
     let mut file_count = 0;
     let mut total_size = 0;
 
-    let _ = count_files_recursive(&module_path, &mut file_count, &mut total_size);
+    if let Err(e) = count_files_recursive(module_path, &mut file_count, &mut total_size) {
+        log::warn!("统计模块 '{}' 时遍历中止，结果可能不完整: {}", name, e);
+    }
+    (file_count, total_size)
+}
 
-    let created_time = match entry.metadata() {
+pub fn module_created_time(module_path: &Path) -> String {
+    match fs::symlink_metadata(module_path) {
         Ok(metadata) => {
-            if let Ok(created) = metadata.created() {
-                let datetime: DateTime<Utc> = created.into();
-                datetime.format("%Y-%m-%d %H:%M:%S").to_string()
-            } else {
-                "未知".to_string()
-            }
+            let datetime: DateTime<Utc> = file_timestamp(&metadata).into();
+            datetime.format("%Y-%m-%d %H:%M:%S").to_string()
         }
         Err(_) => "未知".to_string(),
-    };
+    }
+}
 
-    Ok(ModuleInfo {
-        name,
-        file_count,
-        created_time,
-        total_size,
-    })
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn mime_types_follow_the_extension() {
+        assert_eq!(mime_for_extension("webp"), "image/webp");
+        assert_eq!(mime_for_extension("JPG"), "image/jpeg");
+        assert_eq!(mime_for_extension("pdf"), "application/pdf");
+        assert_eq!(mime_for_extension("txt"), "text/plain; charset=utf-8");
+        assert_eq!(mime_for_extension("mp4"), "video/mp4");
+        // 未知或缺失的扩展名按二进制流下载
+        assert_eq!(mime_for_extension("xyz"), "application/octet-stream");
+        assert_eq!(mime_for_extension(""), "application/octet-stream");
+    }
+
+    #[test]
+    fn timestamps_fall_back_when_birth_time_is_unavailable() {
+        let dir = std::env::temp_dir().join(format!("timestamp-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        fs::write(&path, b"data").unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        let unsupported = || Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "creation time is not available"));
+
+        // created() 不可用时使用修改时间，而不是 UNIX 纪元
+        let modified = metadata.modified().unwrap();
+        let timestamp = timestamp_with_fallback(unsupported(), || metadata.modified());
+        assert_eq!(timestamp, modified);
+        assert!(timestamp.duration_since(SystemTime::UNIX_EPOCH).unwrap() > Duration::from_secs(86_400));
+
+        // 两者都不可用时使用当前时间
+        let before = SystemTime::now();
+        let timestamp = timestamp_with_fallback(unsupported(), unsupported);
+        assert!(timestamp >= before && timestamp <= SystemTime::now());
+
+        let created = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(timestamp_with_fallback(Ok(created), || panic!("modified() should not be consulted")), created);
+
+        // 真实文件在任何平台上都得到非纪元时间
+        assert!(file_timestamp(&metadata).duration_since(SystemTime::UNIX_EPOCH).unwrap() > Duration::from_secs(86_400));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walks_stop_at_symlink_cycles_and_depth_limits() {
+        let dir = std::env::temp_dir().join(format!("walk-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        fs::write(dir.join("top.txt"), b"top").unwrap();
+        fs::write(dir.join("a/b/inner.txt"), b"inner").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("a/b/loop")).unwrap();
+
+        // 成环时立即返回，而不是无限遍历
+        let (tx, rx) = std::sync::mpsc::channel();
+        let root = dir.clone();
+        std::thread::spawn(move || {
+            let mut visited = 0;
+            let result = walk_files(&root, 32, |_, _, _| visited += 1);
+            let _ = tx.send((result, visited));
+        });
+        let (result, visited) = rx.recv_timeout(Duration::from_secs(10)).expect("walk should terminate on a symlink loop");
+        match result {
+            Err(WalkError::Cycle(path)) => assert!(path.ends_with("a/b/loop"), "{}", path.display()),
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+        assert!(visited <= 2);
+
+        // 没有环时按深度限制报告嵌套过深
+        fs::remove_file(dir.join("a/b/loop")).unwrap();
+        let mut names = Vec::new();
+        walk_files(&dir, 2, |_, name, _| names.push(name.to_string())).unwrap();
+        names.sort();
+        assert_eq!(names, ["inner.txt", "top.txt"]);
+        assert!(matches!(walk_files(&dir, 1, |_, _, _| {}), Err(WalkError::TooDeep(path)) if path.ends_with("a/b")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,106 @@
+// 增量计算 SHA-256 / MD5 摘要，用于合并后的完整性校验（按块喂入数据，不需要整个文件驻留内存）
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use crate::config::HashAlgorithm;
+
+pub struct FileHasher(Hasher);
+
+enum Hasher {
+    Sha256(Sha256),
+    Md5(Md5),
+}
+
+impl FileHasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Self(Hasher::Sha256(Sha256::new())),
+            HashAlgorithm::Md5 => Self(Hasher::Md5(Md5::new())),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match &mut self.0 {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Md5(h) => h.update(data),
+        }
+    }
+
+    // 返回小写十六进制摘要
+    pub fn finalize_hex(self) -> String {
+        let digest = match self.0 {
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Md5(h) => h.finalize().to_vec(),
+        };
+        to_hex(&digest)
+    }
+}
+
+// HMAC-SHA256，返回小写十六进制签名
+pub fn hmac_sha256_hex(key: &[u8], message: &[u8]) -> String {
+    to_hex(&hmac_sha256(key, message))
+}
+
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    // HMAC 接受任意长度的密钥，new_from_slice 不会失败
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC 接受任意长度的密钥");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+// 比较客户端提供的摘要与计算结果（忽略大小写与首尾空白）
+pub fn hash_matches(expected: &str, actual_hex: &str) -> bool {
+    expected.trim().eq_ignore_ascii_case(actual_hex)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(algorithm: HashAlgorithm, parts: &[&[u8]]) -> String {
+        let mut hasher = FileHasher::new(algorithm);
+        for part in parts {
+            hasher.update(part);
+        }
+        hasher.finalize_hex()
+    }
+
+    #[test]
+    fn sha256_matches_nist_vectors() {
+        assert_eq!(digest(HashAlgorithm::Sha256, &[b""]), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(digest(HashAlgorithm::Sha256, &[b"abc"]), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        // 两个分组的输入，且分多次喂入
+        assert_eq!(
+            digest(HashAlgorithm::Sha256, &[b"abcdbcdecdefdefgefghfghighij", b"hijkijkljklmklmnlmnomnopnopq"]),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        // 恰好 64 字节（一个完整分组）
+        assert_eq!(
+            digest(HashAlgorithm::Sha256, &[&[b'a'; 64]]),
+            "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb"
+        );
+    }
+
+    #[test]
+    fn md5_matches_rfc_1321_vectors() {
+        assert_eq!(digest(HashAlgorithm::Md5, &[b""]), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(digest(HashAlgorithm::Md5, &[b"abc"]), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(digest(HashAlgorithm::Md5, &[b"message digest"]), "f96b697d7cb7938d525a2f31aaf161d0");
+        // 80 字节（跨越两个分组），分多次喂入
+        assert_eq!(
+            digest(HashAlgorithm::Md5, &[b"1234567890123456789012345678901234567890", b"1234567890123456789012345678901234567890"]),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+        assert_eq!(digest(HashAlgorithm::Md5, &[&[b'a'; 64]]), "014842d480b571495a4a0363793f7367");
+    }
+
+    #[test]
+    fn hash_comparison_ignores_case_and_whitespace() {
+        assert!(hash_matches(" 900150983CD24FB0D6963F7D28E17F72\n", "900150983cd24fb0d6963f7d28e17f72"));
+        assert!(!hash_matches("900150983cd24fb0d6963f7d28e17f73", "900150983cd24fb0d6963f7d28e17f72"));
+    }
+}
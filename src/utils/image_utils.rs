@@ -0,0 +1,146 @@
+// 从图片文件头读取宽高（只读取头部若干字节，不解码像素数据）
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+// 读取图片尺寸；不支持的格式或文件头损坏时返回 None
+pub fn image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut reader = BufReader::with_capacity(512, File::open(path).ok()?);
+    let mut head = [0u8; 30];
+    let len = read_up_to(&mut reader, &mut head).ok()?;
+    let head = &head[..len];
+
+    if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_dimensions(head)
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        gif_dimensions(head)
+    } else if head.starts_with(b"BM") {
+        bmp_dimensions(head)
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP") {
+        webp_dimensions(head)
+    } else if head.starts_with(&[0xFF, 0xD8]) {
+        jpeg_dimensions(&mut reader)
+    } else {
+        None
+    }
+}
+
+fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]) as u32)
+}
+
+fn le_u16(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32)
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn le_u24(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 3).map(|b| u32::from_le_bytes([b[0], b[1], b[2], 0]))
+}
+
+fn le_i32(data: &[u8], at: usize) -> Option<i32> {
+    data.get(at..at + 4).map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// PNG: 签名之后紧跟 IHDR 块，宽高为其前 8 字节
+fn png_dimensions(head: &[u8]) -> Option<(u32, u32)> {
+    if head.get(12..16) != Some(b"IHDR") {
+        return None;
+    }
+    Some((be_u32(head, 16)?, be_u32(head, 20)?))
+}
+
+// GIF: 逻辑屏幕描述符中的宽高
+fn gif_dimensions(head: &[u8]) -> Option<(u32, u32)> {
+    Some((le_u16(head, 6)?, le_u16(head, 8)?))
+}
+
+// BMP: BITMAPINFOHEADER 中的宽高（高度为负表示自上而下存储）；旧式 BITMAPCOREHEADER 为 16 位
+fn bmp_dimensions(head: &[u8]) -> Option<(u32, u32)> {
+    let header_size = le_i32(head, 14)?;
+    if header_size == 12 {
+        return Some((le_u16(head, 18)?, le_u16(head, 20)?));
+    }
+    let width = le_i32(head, 18)?;
+    let height = le_i32(head, 22)?;
+    Some((width.unsigned_abs(), height.unsigned_abs()))
+}
+
+// WebP: 按首个块类型分别解析有损（VP8）、无损（VP8L）和扩展（VP8X）格式
+fn webp_dimensions(head: &[u8]) -> Option<(u32, u32)> {
+    match head.get(12..16)? {
+        b"VP8 " => {
+            // 帧头: 3 字节帧标记 + 起始码 9d 01 2a，之后为 14 位宽高
+            if head.get(23..26) != Some(&[0x9d, 0x01, 0x2a]) {
+                return None;
+            }
+            Some((le_u16(head, 26)? & 0x3FFF, le_u16(head, 28)? & 0x3FFF))
+        }
+        b"VP8L" => {
+            if head.get(20) != Some(&0x2f) {
+                return None;
+            }
+            let bits = u32::from_le_bytes(head.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        b"VP8X" => Some((le_u24(head, 24)? + 1, le_u24(head, 27)? + 1)),
+        _ => None,
+    }
+}
+
+// JPEG: 逐段跳过，直到遇到 SOF 段（APP 段可能很大，使用 seek 跳过而不是读取）
+fn jpeg_dimensions(reader: &mut (impl Read + Seek)) -> Option<(u32, u32)> {
+    reader.seek(SeekFrom::Start(2)).ok()?;
+    let mut marker = [0u8; 2];
+    loop {
+        reader.read_exact(&mut marker[..1]).ok()?;
+        if marker[0] != 0xFF {
+            return None;
+        }
+        // 段标记前允许有填充的 0xFF
+        loop {
+            reader.read_exact(&mut marker[1..]).ok()?;
+            if marker[1] != 0xFF {
+                break;
+            }
+        }
+        match marker[1] {
+            // 无长度字段的独立标记
+            0x01 | 0xD0..=0xD7 => continue,
+            // 在 SOF 之前遇到图像数据或结束标记
+            0xD9 | 0xDA => return None,
+            _ => {}
+        }
+
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length).ok()?;
+        let length = u16::from_be_bytes(length) as i64;
+        if length < 2 {
+            return None;
+        }
+
+        // SOF0-SOF15（排除 DHT C4、JPG C8、DAC CC）
+        if matches!(marker[1], 0xC0..=0xCF) && !matches!(marker[1], 0xC4 | 0xC8 | 0xCC) {
+            let mut sof = [0u8; 5];
+            reader.read_exact(&mut sof).ok()?;
+            let height = be_u16(&sof, 1)?;
+            let width = be_u16(&sof, 3)?;
+            return Some((width, height));
+        }
+        reader.seek(SeekFrom::Current(length - 2)).ok()?;
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::HashMap as StdHashMap;
 use std::sync::OnceLock as StdOnceLock;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::{Mutex, Semaphore};
+use tokio::sync::{Mutex, RwLock, Semaphore};
 
 #[derive(Debug, Clone)]
 pub struct FileLockEntry {
@@ -27,8 +27,53 @@ impl FileLockEntry {
 }
 
 static FILE_LOCKS: StdOnceLock<Mutex<StdHashMap<String, FileLockEntry>>> = StdOnceLock::new();
+// 读写锁：多个写入方可同时持有读锁，需要独占的操作（取消上传、重命名模块）持有写锁
+type RwLockEntry = (Arc<RwLock<()>>, Instant);
+static RW_LOCKS: StdOnceLock<Mutex<StdHashMap<String, RwLockEntry>>> = StdOnceLock::new();
 static CHUNK_SEMAPHORE: StdOnceLock<Semaphore> = StdOnceLock::new();
 static MERGE_SEMAPHORE: StdOnceLock<Semaphore> = StdOnceLock::new();
+// 每个客户端 IP 正在进行的上传数（计数归零即移除，map 大小不超过在途上传数）
+static CLIENT_UPLOADS: StdOnceLock<std::sync::Mutex<StdHashMap<String, usize>>> = StdOnceLock::new();
+
+// 客户端上传计数守卫，drop 时归还
+#[derive(Debug)]
+pub struct ClientUploadGuard {
+    client: String,
+}
+
+impl Drop for ClientUploadGuard {
+    fn drop(&mut self) {
+        let map = CLIENT_UPLOADS.get_or_init(Default::default);
+        let mut guard = map.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = guard.get_mut(&self.client) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                guard.remove(&self.client);
+            }
+        }
+    }
+}
+
+// 为客户端登记一个在途上传；超过 max_concurrent_uploads_per_ip 时返回 None
+pub fn try_acquire_client_upload(client: &str) -> Option<ClientUploadGuard> {
+    let limit = crate::config::get_config().max_concurrent_uploads_per_ip;
+    let map = CLIENT_UPLOADS.get_or_init(Default::default);
+    let mut guard = map.lock().unwrap_or_else(|e| e.into_inner());
+    let current = guard.get(client).copied().unwrap_or(0);
+    if limit.is_some_and(|limit| current >= limit) {
+        return None;
+    }
+    *guard.entry(client.to_string()).or_insert(0) += 1;
+    Some(ClientUploadGuard { client: client.to_string() })
+}
+
+// 各客户端当前在途上传数（用于管理统计）
+pub fn get_client_upload_counts() -> StdHashMap<String, usize> {
+    CLIENT_UPLOADS
+        .get()
+        .map(|map| map.lock().unwrap_or_else(|e| e.into_inner()).clone())
+        .unwrap_or_default()
+}
 
 pub fn init_global_semaphore(max_concurrent: usize) {
     let _ = CHUNK_SEMAPHORE.get_or_init(|| Semaphore::new(max_concurrent));
@@ -51,13 +96,12 @@ pub async fn get_file_lock(key: &str) -> Arc<Mutex<()>> {
     let map = FILE_LOCKS.get_or_init(|| Mutex::new(StdHashMap::new()));
     let mut guard = map.lock().await;
     
-    // 使用默认配置的最大内存锁数量
-    let max_memory_locks = 10000; // 或者从配置中获取
+    let max_memory_locks = crate::config::get_config().max_memory_locks;
     
     // 自动清理：如果锁数量过多，清理最久未使用的
     if guard.len() >= max_memory_locks {
         let mut entries: Vec<(String, FileLockEntry)> = guard.drain().collect();
-        entries.sort_by(|a, b| a.1.last_used.cmp(&b.1.last_used));
+        entries.sort_by_key(|e| e.1.last_used);
         
         // 保留最近使用的 80%
         let retain_count = (max_memory_locks * 8) / 10;
@@ -73,10 +117,32 @@ pub async fn get_file_lock(key: &str) -> Arc<Mutex<()>> {
     entry.lock.clone()
 }
 
+// 获取或创建读写锁；锁数量过多时清理当前没有持有者的锁
+pub async fn get_rw_lock(key: &str) -> Arc<RwLock<()>> {
+    let map = RW_LOCKS.get_or_init(|| Mutex::new(StdHashMap::new()));
+    let mut guard = map.lock().await;
+
+    if guard.len() >= crate::config::get_config().max_memory_locks {
+        guard.retain(|_, (lock, _)| Arc::strong_count(lock) > 1);
+    }
+
+    let entry = guard
+        .entry(key.to_string())
+        .or_insert_with(|| (Arc::new(RwLock::new(())), Instant::now()));
+    entry.1 = Instant::now();
+    entry.0.clone()
+}
+
 // 定期清理文件锁
 pub async fn cleanup_file_locks() -> usize {
     let now = Instant::now();
     let max_age = Duration::from_secs(7200); // 2小时未使用则清理
+
+    if let Some(map) = RW_LOCKS.get() {
+        map.lock().await.retain(|_, (lock, last_used)| {
+            Arc::strong_count(lock) > 1 || now.duration_since(*last_used) < max_age
+        });
+    }
     
     if let Some(map) = FILE_LOCKS.get() {
         let mut guard = map.lock().await;
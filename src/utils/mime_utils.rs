@@ -0,0 +1,195 @@
+// 根据文件头部的魔数识别真实文件类型，用于与扩展名交叉校验
+use actix_web::{http::StatusCode, ResponseError};
+use crate::config::ServerConfig;
+
+// 识别所需的最大头部长度（tar 的 "ustar" 标记位于偏移 257）
+pub const SNIFF_LEN: usize = 4096;
+
+// 识别出的类型及与之相符的扩展名（第一个为规范名）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetectedType {
+    pub extensions: &'static [&'static str],
+}
+
+impl DetectedType {
+    pub fn name(&self) -> &'static str {
+        self.extensions[0]
+    }
+}
+
+const fn kind(extensions: &'static [&'static str]) -> Option<DetectedType> {
+    Some(DetectedType { extensions })
+}
+
+// 根据头部字节识别类型；纯文本类格式（txt / csv / json / svg 等）没有魔数，返回 None
+pub fn detect(head: &[u8]) -> Option<DetectedType> {
+    let at = |offset: usize, sig: &[u8]| head.len() >= offset + sig.len() && &head[offset..offset + sig.len()] == sig;
+
+    if at(0, b"\xFF\xD8\xFF") {
+        return kind(&["jpg", "jpeg"]);
+    }
+    if at(0, b"\x89PNG\r\n\x1A\n") {
+        return kind(&["png"]);
+    }
+    if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        return kind(&["gif"]);
+    }
+    if at(0, b"RIFF") {
+        if at(8, b"WEBP") {
+            return kind(&["webp"]);
+        }
+        if at(8, b"WAVE") {
+            return kind(&["wav"]);
+        }
+        if at(8, b"AVI ") {
+            return kind(&["avi"]);
+        }
+    }
+    if at(4, b"ftyp") && head.len() >= 12 {
+        return match &head[8..12] {
+            b"qt  " => kind(&["mov"]),
+            b"avif" | b"avis" => kind(&["avif"]),
+            b"heic" | b"heix" | b"hevc" | b"mif1" | b"msf1" => kind(&["heic", "heif"]),
+            b"M4A " => kind(&["m4a", "mp4"]),
+            _ => kind(&["mp4", "m4v", "mov"]),
+        };
+    }
+    if at(0, b"%PDF") {
+        return kind(&["pdf"]);
+    }
+    // Office Open XML 等格式本身就是 zip 容器
+    if at(0, b"PK\x03\x04") || at(0, b"PK\x05\x06") || at(0, b"PK\x07\x08") {
+        return kind(&["zip", "docx", "xlsx", "pptx", "jar", "apk"]);
+    }
+    if at(0, b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1") {
+        return kind(&["doc", "xls", "ppt", "msi"]);
+    }
+    if at(0, b"Rar!\x1A\x07") {
+        return kind(&["rar"]);
+    }
+    if at(0, b"7z\xBC\xAF\x27\x1C") {
+        return kind(&["7z"]);
+    }
+    if at(0, b"\x1F\x8B") {
+        return kind(&["gz", "tgz"]);
+    }
+    if at(257, b"ustar") {
+        return kind(&["tar"]);
+    }
+    if at(0, b"\x1A\x45\xDF\xA3") {
+        return kind(&["mkv", "webm"]);
+    }
+    if at(0, b"FLV\x01") {
+        return kind(&["flv"]);
+    }
+    if at(0, b"\x30\x26\xB2\x75\x8E\x66\xCF\x11") {
+        return kind(&["wmv", "wma", "asf"]);
+    }
+    if at(0, b"OggS") {
+        return kind(&["ogg", "oga", "ogv"]);
+    }
+    if at(0, b"fLaC") {
+        return kind(&["flac"]);
+    }
+    if at(0, b"ID3") || at(0, b"\xFF\xFB") || at(0, b"\xFF\xF3") || at(0, b"\xFF\xF2") {
+        return kind(&["mp3"]);
+    }
+    if at(0, b"\x00\x00\x01\x00") {
+        return kind(&["ico"]);
+    }
+    if at(0, b"BM") && head.len() >= 14 {
+        return kind(&["bmp"]);
+    }
+    if at(0, b"\x7FELF") {
+        return kind(&["elf", "so"]);
+    }
+    if at(0, b"\xCF\xFA\xED\xFE") || at(0, b"\xCE\xFA\xED\xFE") || at(0, b"\xCA\xFE\xBA\xBE") {
+        return kind(&["macho", "dylib"]);
+    }
+    // PE 可执行文件: "MZ" 头且 e_lfanew 指向 "PE\0\0"，避免把以 MZ 开头的文本误判
+    if at(0, b"MZ") && head.len() >= 0x40 {
+        let pe_offset = u32::from_le_bytes([head[0x3C], head[0x3D], head[0x3E], head[0x3F]]) as usize;
+        if at(pe_offset, b"PE\0\0") {
+            return kind(&["exe", "dll"]);
+        }
+    }
+    None
+}
+
+// 文件内容与扩展名不符且真实类型不在允许列表中
+#[derive(Debug)]
+pub struct ContentTypeMismatch {
+    pub claimed: String,
+    pub detected: &'static str,
+}
+
+impl std::fmt::Display for ContentTypeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "文件内容为 {}，与扩展名 {} 不符", self.detected, self.claimed)
+    }
+}
+
+impl ResponseError for ContentTypeMismatch {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    }
+}
+
+// 交叉校验头部内容与声明的扩展名：无法识别、与扩展名相符、或真实类型本身允许上传时通过
+pub fn check_content(head: &[u8], claimed_ext: &str, config: &ServerConfig) -> Result<(), ContentTypeMismatch> {
+    let Some(detected) = detect(head) else {
+        return Ok(());
+    };
+    let claimed = claimed_ext.to_lowercase();
+    if detected.extensions.contains(&claimed.as_str()) {
+        return Ok(());
+    }
+    if detected
+        .extensions
+        .iter()
+        .any(|ext| crate::utils::file_utils::is_valid_file_extension(ext, config))
+    {
+        log::info!("文件扩展名 {} 与内容 {} 不符，但真实类型允许上传", claimed, detected.name());
+        return Ok(());
+    }
+    Err(ContentTypeMismatch {
+        claimed,
+        detected: detected.name(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 最小的 PE 头: "MZ"，e_lfanew (0x3C) 指向 0x80 处的 "PE\0\0"
+    fn pe_header() -> Vec<u8> {
+        let mut head = vec![0u8; 0x100];
+        head[..2].copy_from_slice(b"MZ");
+        head[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        head[0x80..0x84].copy_from_slice(b"PE\0\0");
+        head
+    }
+
+    #[test]
+    fn detects_types_by_magic_bytes() {
+        assert_eq!(detect(b"\x89PNG\r\n\x1A\n\0\0\0\rIHDR").map(|t| t.name()), Some("png"));
+        assert_eq!(detect(b"\xFF\xD8\xFF\xE0\0\x10JFIF").map(|t| t.name()), Some("jpg"));
+        assert_eq!(detect(&pe_header()).map(|t| t.name()), Some("exe"));
+        // 以 MZ 开头的普通文本不是可执行文件
+        assert_eq!(detect(b"MZ is just the start of this sentence, not a DOS header at all......."), None);
+        assert_eq!(detect(b"plain text"), None);
+    }
+
+    #[test]
+    fn spoofed_extensions_fail_the_cross_check() {
+        let config = ServerConfig::default();
+        assert!(check_content(b"\x89PNG\r\n\x1A\n", "PNG", &config).is_ok());
+        // 真实类型本身允许上传时放行
+        assert!(check_content(b"\x89PNG\r\n\x1A\n", "jpg", &config).is_ok());
+        assert!(check_content(b"just text", "jpg", &config).is_ok());
+        let mismatch = check_content(&pe_header(), "jpg", &config).unwrap_err();
+        assert_eq!((mismatch.claimed.as_str(), mismatch.detected), ("jpg", "exe"));
+        assert_eq!(mismatch.status_code(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
@@ -1,3 +1,9 @@
 pub mod file_utils;
 pub mod lock_utils;
-pub mod validation_utils;
\ No newline at end of file
+pub mod validation_utils;
+pub mod auth_utils;
+pub mod zip_utils;
+pub mod hash_utils;
+pub mod image_utils;
+pub mod mime_utils;
+pub mod rate_limit;
@@ -0,0 +1,183 @@
+// 按客户端 IP 的令牌桶限流：桶容量为每分钟允许的请求数，令牌按 每分钟请求数 / 60 每秒匀速补充
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use actix_web::HttpRequest;
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+static BUCKETS: OnceLock<Mutex<HashMap<String, TokenBucket>>> = OnceLock::new();
+
+// 客户端标识：使用 TCP 对端地址，不信任可伪造的转发头
+pub fn client_ip(req: &HttpRequest) -> String {
+    req.peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// 为客户端消耗一个令牌；未配置限流时始终放行，令牌不足时返回需要等待的时间
+pub fn check(client: &str) -> Result<(), Duration> {
+    let Some(per_minute) = crate::config::get_config().rate_limit_per_minute else {
+        return Ok(());
+    };
+    let capacity = per_minute as f64;
+    let refill_per_sec = capacity / 60.0;
+    let now = Instant::now();
+
+    let map = BUCKETS.get_or_init(Default::default);
+    let mut guard = map.lock().unwrap_or_else(|e| e.into_inner());
+    let bucket = guard.entry(client.to_string()).or_insert(TokenBucket {
+        tokens: capacity,
+        last_refill: now,
+    });
+
+    let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+    bucket.last_refill = now;
+
+    if bucket.tokens >= 1.0 {
+        bucket.tokens -= 1.0;
+        Ok(())
+    } else {
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / refill_per_sec))
+    }
+}
+
+// 定期清理空闲的令牌桶：超过一分钟未使用的桶已经补满，删除后与新建等价
+pub fn cleanup_idle_buckets() -> usize {
+    let Some(map) = BUCKETS.get() else {
+        return 0;
+    };
+    let now = Instant::now();
+    let mut guard = map.lock().unwrap_or_else(|e| e.into_inner());
+    let initial_len = guard.len();
+    guard.retain(|_, bucket| now.duration_since(bucket.last_refill) < Duration::from_secs(60));
+    let cleaned = initial_len - guard.len();
+    if cleaned > 0 {
+        log::info!("清理了 {} 个空闲的限流令牌桶", cleaned);
+    }
+    cleaned
+}
+
+// 单次上传实际使用的速率上限：请求指定的值不能超过配置上限，未配置上限时按请求的值限制
+pub fn upload_bps_limit(requested: Option<&str>) -> Option<u64> {
+    let requested = requested.and_then(|v| v.trim().parse::<u64>().ok()).filter(|bps| *bps > 0);
+    match (requested, crate::config::get_config().max_upload_bps) {
+        (Some(requested), Some(max)) => Some(requested.min(max)),
+        (requested, max) => requested.or(max),
+    }
+}
+
+// 上传带宽节流：按已写入字节数计算按限速应花费的时间，写得过快时 sleep 补足差值。
+// 只放慢读取请求体的速度，不会断开连接
+pub struct Throttle {
+    bytes_per_sec: Option<u64>,
+    started: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    pub fn new(bytes_per_sec: Option<u64>) -> Self {
+        Self {
+            bytes_per_sec,
+            started: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    pub async fn pace(&mut self, len: usize) {
+        let Some(bytes_per_sec) = self.bytes_per_sec else {
+            return;
+        };
+        self.bytes += len as u64;
+        let expected = Duration::from_secs_f64(self.bytes as f64 / bytes_per_sec as f64);
+        let elapsed = self.started.elapsed();
+        if expected > elapsed {
+            tokio::time::sleep(expected - elapsed).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::call_service;
+    use crate::test_utils::{self, test_app, TestEnv};
+
+    #[actix_web::test]
+    async fn exhausted_clients_get_429_and_recover() {
+        // 每秒补充一个令牌
+        let _env = TestEnv::with(|c| c.rate_limit_per_minute = Some(60));
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let peer: std::net::SocketAddr = "203.0.113.16:40000".parse().unwrap();
+        let upload = |name: &str| test_utils::upload_request("/api/upload?module=default", name, b"data").peer_addr(peer);
+
+        for _ in 0..60 {
+            assert!(check("203.0.113.16").is_ok());
+        }
+        let resp = call_service(&app, upload("limited.txt").to_request()).await;
+        assert_eq!(resp.status(), 429);
+        let retry_after: u64 = resp.headers().get("Retry-After").unwrap().to_str().unwrap().parse().unwrap();
+        assert!((1..=2).contains(&retry_after));
+
+        // 其它客户端不受影响
+        let other = test_utils::upload_request("/api/upload?module=default", "other.txt", b"data")
+            .peer_addr("203.0.113.17:40000".parse().unwrap());
+        assert_eq!(call_service(&app, other.to_request()).await.status(), 200);
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let resp = call_service(&app, upload("recovered.txt").to_request()).await;
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[actix_web::test]
+    async fn throttled_uploads_take_longer() {
+        let content = vec![b'x'; 32 * 1024];
+        {
+            let _env = TestEnv::with(|_| {});
+            let state = test_utils::app_state();
+            let app = test_app!(state);
+
+            let started = Instant::now();
+            let resp = call_service(&app, test_utils::upload_request("/api/upload?module=default", "fast.txt", &content).to_request()).await;
+            assert_eq!(resp.status(), 200);
+            let unthrottled = started.elapsed();
+
+            // 32KB 按 64KB/s 写入约需 0.5 秒
+            let started = Instant::now();
+            let resp = call_service(
+                &app,
+                test_utils::upload_request("/api/upload?module=default&max_bps=65536", "slow.txt", &content).to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), 200);
+            let throttled = started.elapsed();
+            assert!(throttled >= Duration::from_millis(450), "{:?}", throttled);
+            assert!(throttled > unthrottled + Duration::from_millis(300), "{:?} vs {:?}", throttled, unthrottled);
+
+            let started = Instant::now();
+            let uri = "/api/upload/chunk?module=default&filename=slow-chunk.txt&chunk_number=0&total_chunks=1&upload_id=slow&max_bps=65536";
+            let resp = call_service(&app, test_utils::upload_request(uri, "slow-chunk.txt", &content).to_request()).await;
+            assert_eq!(resp.status(), 200);
+            assert!(started.elapsed() >= Duration::from_millis(450));
+        }
+
+        // 请求指定的速率不能超过配置上限
+        let env = TestEnv::with(|c| c.max_upload_bps = Some(64 * 1024));
+        let state = test_utils::app_state();
+        let app = test_app!(state);
+        let started = Instant::now();
+        let resp = call_service(
+            &app,
+            test_utils::upload_request("/api/upload?module=default&max_bps=10000000000", "clamped.txt", &content).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        assert!(started.elapsed() >= Duration::from_millis(450));
+        assert_eq!(std::fs::read(env.uploads().join("default/clamped.txt")).unwrap(), content);
+    }
+}
@@ -1,6 +1,7 @@
 // 验证模块名称
 pub fn is_valid_module_name(name: &str) -> bool {
     !name.contains("..") && !name.contains("/") && !name.contains("\\")
+        && !crate::utils::file_utils::is_reserved_dir(name)
 }
 
 // 允许包含 '/' 的模块路径（用于指定子模块路径），但不允许路径穿越或绝对路径
@@ -8,6 +9,8 @@ pub fn is_valid_module_path(path: &str) -> bool {
     if path.is_empty() { return false; }
     if path.contains("..") { return false; }
     if path.starts_with('/') || path.contains('\\') { return false; }
+    // 回收站、内容存储等保留目录不能作为模块访问
+    if path.split('/').next().is_some_and(crate::utils::file_utils::is_reserved_dir) { return false; }
     // 禁止以 '.' 或空节段为模块名
     for seg in path.split('/') {
         if seg.trim().is_empty() { return false; }
@@ -26,11 +29,22 @@ pub fn is_valid_path(path: &str) -> bool {
     !path.contains("..") && !path.contains("//")
 }
 
+// 验证完整存储路径（转换为绝对路径后）的字节长度
+pub fn is_valid_path_length(path: &std::path::Path, max_bytes: usize) -> bool {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    absolute.as_os_str().len() <= max_bytes
+}
+
 // 验证文件大小
 pub fn is_valid_file_size(size: u64, max_size: u64) -> bool {
     size <= max_size
 }
 
+// 验证分片上传会话 id（用作临时目录名）
+pub fn is_valid_upload_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 128 && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
 // 验证分块参数
 pub fn is_valid_chunk_params(chunk_number: usize, total_chunks: usize) -> bool {
     chunk_number < total_chunks
@@ -0,0 +1,208 @@
+// 仅存储（不压缩）的流式 ZIP 编码器：文件大小预先已知，CRC 在写完数据后通过数据描述符给出；
+// 单个文件或偏移超过 4GB 时使用 ZIP64 扩展
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const ZIP64_END_SIGNATURE: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+const END_SIGNATURE: u32 = 0x0605_4b50;
+
+// bit 3: 使用数据描述符; bit 11: 文件名为 UTF-8
+const FLAGS: u16 = 0x0808;
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+// 高字节 3 表示 unix
+const VERSION_MADE_BY: u16 = (3 << 8) | VERSION_ZIP64;
+const U32_LIMIT: u64 = 0xFFFF_FFFF;
+
+#[derive(Debug)]
+struct ZipEntry {
+    name: String,
+    size: u64,
+    crc: u32,
+    offset: u64,
+    dos_time: u16,
+    dos_date: u16,
+}
+
+impl ZipEntry {
+    fn is_zip64(&self) -> bool {
+        self.size >= U32_LIMIT || self.offset >= U32_LIMIT
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ZipStreamWriter {
+    entries: Vec<ZipEntry>,
+    offset: u64,
+}
+
+impl ZipStreamWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 开始一个新条目，返回本地文件头；随后应写出恰好 size 字节的数据并调用 finish_entry
+    pub fn start_entry(&mut self, name: &str, size: u64, modified: chrono::NaiveDateTime) -> Vec<u8> {
+        let (dos_time, dos_date) = dos_datetime(modified);
+        let entry = ZipEntry {
+            name: name.to_string(),
+            size,
+            crc: 0,
+            offset: self.offset,
+            dos_time,
+            dos_date,
+        };
+
+        let zip64 = size >= U32_LIMIT;
+        let mut buf = Vec::with_capacity(30 + name.len() + 20);
+        put_u32(&mut buf, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut buf, if zip64 { VERSION_ZIP64 } else { VERSION_DEFAULT });
+        put_u16(&mut buf, FLAGS);
+        put_u16(&mut buf, 0); // 存储方式
+        put_u16(&mut buf, dos_time);
+        put_u16(&mut buf, dos_date);
+        put_u32(&mut buf, 0); // CRC 在数据描述符中给出
+        let size32 = if zip64 { U32_LIMIT as u32 } else { size as u32 };
+        put_u32(&mut buf, size32);
+        put_u32(&mut buf, size32);
+        put_u16(&mut buf, name.len() as u16);
+        put_u16(&mut buf, if zip64 { 20 } else { 0 });
+        buf.extend_from_slice(name.as_bytes());
+        if zip64 {
+            put_u16(&mut buf, 0x0001);
+            put_u16(&mut buf, 16);
+            put_u64(&mut buf, size);
+            put_u64(&mut buf, size);
+        }
+
+        self.offset += buf.len() as u64 + size;
+        self.entries.push(entry);
+        buf
+    }
+
+    // 结束当前条目，返回数据描述符
+    pub fn finish_entry(&mut self, crc: u32) -> Vec<u8> {
+        let Some(entry) = self.entries.last_mut() else {
+            return Vec::new();
+        };
+        entry.crc = crc;
+
+        let mut buf = Vec::with_capacity(24);
+        put_u32(&mut buf, DATA_DESCRIPTOR_SIGNATURE);
+        put_u32(&mut buf, crc);
+        if entry.size >= U32_LIMIT {
+            put_u64(&mut buf, entry.size);
+            put_u64(&mut buf, entry.size);
+        } else {
+            put_u32(&mut buf, entry.size as u32);
+            put_u32(&mut buf, entry.size as u32);
+        }
+        self.offset += buf.len() as u64;
+        buf
+    }
+
+    // 输出中央目录与结束记录，comment 作为归档注释（超长部分截断）
+    pub fn finish(self, comment: &str) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let central_offset = self.offset;
+
+        for entry in &self.entries {
+            let zip64 = entry.is_zip64();
+            let mut extra = Vec::new();
+            if zip64 {
+                let mut fields = Vec::new();
+                if entry.size >= U32_LIMIT {
+                    put_u64(&mut fields, entry.size);
+                    put_u64(&mut fields, entry.size);
+                }
+                if entry.offset >= U32_LIMIT {
+                    put_u64(&mut fields, entry.offset);
+                }
+                put_u16(&mut extra, 0x0001);
+                put_u16(&mut extra, fields.len() as u16);
+                extra.extend_from_slice(&fields);
+            }
+
+            let size32 = if entry.size >= U32_LIMIT { U32_LIMIT as u32 } else { entry.size as u32 };
+            put_u32(&mut buf, CENTRAL_HEADER_SIGNATURE);
+            put_u16(&mut buf, VERSION_MADE_BY);
+            put_u16(&mut buf, if zip64 { VERSION_ZIP64 } else { VERSION_DEFAULT });
+            put_u16(&mut buf, FLAGS);
+            put_u16(&mut buf, 0);
+            put_u16(&mut buf, entry.dos_time);
+            put_u16(&mut buf, entry.dos_date);
+            put_u32(&mut buf, entry.crc);
+            put_u32(&mut buf, size32);
+            put_u32(&mut buf, size32);
+            put_u16(&mut buf, entry.name.len() as u16);
+            put_u16(&mut buf, extra.len() as u16);
+            put_u16(&mut buf, 0); // 文件注释长度
+            put_u16(&mut buf, 0); // 磁盘号
+            put_u16(&mut buf, 0); // 内部属性
+            put_u32(&mut buf, 0o100644 << 16); // 外部属性: 普通文件 rw-r--r--
+            put_u32(&mut buf, entry.offset.min(U32_LIMIT) as u32);
+            buf.extend_from_slice(entry.name.as_bytes());
+            buf.extend_from_slice(&extra);
+        }
+
+        let central_size = buf.len() as u64;
+        let count = self.entries.len() as u64;
+        let needs_zip64 = count >= 0xFFFF || central_offset >= U32_LIMIT || central_size >= U32_LIMIT;
+
+        if needs_zip64 {
+            let zip64_end_offset = central_offset + central_size;
+            put_u32(&mut buf, ZIP64_END_SIGNATURE);
+            put_u64(&mut buf, 44);
+            put_u16(&mut buf, VERSION_MADE_BY);
+            put_u16(&mut buf, VERSION_ZIP64);
+            put_u32(&mut buf, 0);
+            put_u32(&mut buf, 0);
+            put_u64(&mut buf, count);
+            put_u64(&mut buf, count);
+            put_u64(&mut buf, central_size);
+            put_u64(&mut buf, central_offset);
+
+            put_u32(&mut buf, ZIP64_LOCATOR_SIGNATURE);
+            put_u32(&mut buf, 0);
+            put_u64(&mut buf, zip64_end_offset);
+            put_u32(&mut buf, 1);
+        }
+
+        let comment = &comment.as_bytes()[..comment.len().min(0xFFFF)];
+        put_u32(&mut buf, END_SIGNATURE);
+        put_u16(&mut buf, 0);
+        put_u16(&mut buf, 0);
+        put_u16(&mut buf, count.min(0xFFFF) as u16);
+        put_u16(&mut buf, count.min(0xFFFF) as u16);
+        put_u32(&mut buf, central_size.min(U32_LIMIT) as u32);
+        put_u32(&mut buf, central_offset.min(U32_LIMIT) as u32);
+        put_u16(&mut buf, comment.len() as u16);
+        buf.extend_from_slice(comment);
+        buf
+    }
+}
+
+// MS-DOS 时间格式（精度 2 秒，年份从 1980 开始）
+fn dos_datetime(time: chrono::NaiveDateTime) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    if time.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let dos_time = ((time.hour() << 11) | (time.minute() << 5) | (time.second() / 2)) as u16;
+    let dos_date = ((((time.year() - 1980) as u32).min(127) << 9) | (time.month() << 5) | time.day()) as u16;
+    (dos_time, dos_date)
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}